@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::dto::document::AskResponse;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateConversationRequest {
+    pub title: Option<String>,
+    #[validate(length(min = 1, message = "At least one document is required"))]
+    pub document_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SendMessageRequest {
+    #[validate(length(min = 1, message = "Message content is required"))]
+    pub content: String,
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 20, message = "limit must be between 1 and 20"))]
+    pub limit: u64,
+}
+
+fn default_limit() -> u64 {
+    5
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationResponse {
+    pub id: String,
+    pub title: String,
+    pub document_ids: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationListResponse {
+    pub conversations: Vec<ConversationResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageResponse {
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    pub standalone_query: Option<String>,
+    pub chunk_ids: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationDetailResponse {
+    pub conversation: ConversationResponse,
+    pub messages: Vec<MessageResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendMessageResponse {
+    pub user_message: MessageResponse,
+    pub assistant_message: MessageResponse,
+    pub answer: AskResponse,
+}
\ No newline at end of file
@@ -1,11 +1,30 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::services::chunking::ChunkingConfig;
+use crate::services::llm::TokenUsage;
+use crate::services::search::SearchMode;
 
 #[derive(Debug, Deserialize)]
 pub struct UploadDocumentRequest {
     pub title: String,
-    pub file_url: String, // Vercel Blob URL
+    pub file_url: String, // Vercel Blob URL; direct uploads go through /api/documents/upload
     pub file_name: String,
     pub file_size: i32,
+    pub chunking: Option<ChunkingConfig>, // e.g. {"strategy": "token", "max_tokens": 256}
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub folder: Option<String>, // e.g. "Physics/Mechanics"
+}
+
+/// Only the fields given are changed
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateDocumentRequest {
+    #[validate(length(min = 1, max = 255, message = "Title must be 1-255 characters"))]
+    pub title: Option<String>,
+    pub tags: Option<Vec<String>>, // replaces the document's tags
+    pub folder: Option<String>,    // "" takes the document out of its folder
 }
 
 #[derive(Debug, Serialize)]
@@ -13,10 +32,14 @@ pub struct DocumentResponse {
     pub id: String,
     pub title: String,
     pub file_name: String,
-    pub file_url: String,
+    pub file_url: String, // storage key, or the URL the document was created from
     pub file_size: i32,
     pub page_count: Option<i32>,
     pub processing_status: String,
+    pub processing_error: Option<String>,
+    pub chunking: Option<ChunkingConfig>,
+    pub tags: Vec<String>,
+    pub folder: Option<String>,
     pub created_at: String,
 }
 
@@ -25,12 +48,38 @@ pub struct DocumentListResponse {
     pub documents: Vec<DocumentResponse>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchRequest {
     pub query: String,
     pub document_id: Option<String>, // Optional: search within specific document
+    #[serde(default)]
+    pub document_ids: Vec<String>, // Optional: search within any of these documents
+    pub section: Option<String>,   // Optional: e.g. "Ch 3", also matches its subsections
+    #[serde(default)]
+    pub tags: Vec<String>, // documents with any of these tags
+    #[serde(default)]
+    pub folders: Vec<String>, // documents in any of these folders, including subfolders
+    pub uploaded_after: Option<DateTime<Utc>>, // RFC 3339, inclusive
+    pub uploaded_before: Option<DateTime<Utc>>,
+    pub page_from: Option<i32>, // chunks overlapping this page range, inclusive
+    pub page_to: Option<i32>,
+    /// Drop results scoring lower: the re-ranker score when re-ranked, else cosine similarity
+    /// (vector mode) or full-text rank (keyword mode). Hybrid mode needs rerank for it.
+    pub min_score: Option<f32>,
     #[serde(default = "default_limit")]
     pub limit: u64,
+    #[serde(default)]
+    pub offset: u64,
+    pub cursor: Option<String>, // next_cursor from a previous page; overrides offset
+    #[serde(default)]
+    pub mode: SearchMode, // "vector" (default), "keyword" or "hybrid"
+    #[serde(default)]
+    pub rerank: bool, // reorder results with the configured re-ranker
+    pub mmr_lambda: Option<f32>, // 0-1; diversify results, lower favours distinct passages
+    #[serde(default)]
+    pub collapse_adjacent: bool, // merge neighbouring chunks of a document into one result
+    #[serde(default)]
+    pub context_chunks: u32, // include this many chunks before and after each result
 }
 
 fn default_limit() -> u64 {
@@ -40,12 +89,133 @@ fn default_limit() -> u64 {
 #[derive(Debug, Serialize)]
 pub struct SearchResponse {
     pub results: Vec<SearchResultItem>,
+    pub next_cursor: Option<String>, // null on the last page
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchHistoryQuery {
+    #[serde(default)]
+    pub pinned: bool, // only saved searches
+    #[serde(default = "default_history_limit")]
+    pub limit: u64,
+}
+
+fn default_history_limit() -> u64 {
+    50
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClearSearchHistoryQuery {
+    #[serde(default)]
+    pub include_pinned: bool, // also delete saved searches
+}
+
+/// Only the fields given are changed
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateSearchHistoryRequest {
+    pub pinned: Option<bool>, // true saves the search
+    #[validate(length(max = 255, message = "Name must be at most 255 characters"))]
+    pub name: Option<String>, // "" removes the name
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHistoryEntryResponse {
+    pub id: String,
+    pub query: String,
+    pub request: serde_json::Value, // the search as sent, re-run by POST .../run
+    pub chunk_ids: Vec<String>,     // results when it was run, in rank order
+    pub pinned: bool,
+    pub name: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHistoryResponse {
+    pub entries: Vec<SearchHistoryEntryResponse>,
+}
+
+/// Re-index progress, without the model, collection or error details kept in the logs
+#[derive(Debug, Serialize)]
+pub struct SearchIndexResponse {
+    pub status: String, // "building", "active", "retired", "failed"
+    pub progress: f32,  // percent of chunks re-embedded
+    pub ready: bool,    // whether searches can be served right now
+    pub updated_at: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SearchResultItem {
     pub document_id: String,
     pub chunk_id: String,
+    pub chunk_ids: Vec<String>, // more than one when neighbouring chunks were merged
+    pub content: String,
+    pub page_start: Option<i32>, // null for documents processed before pages were tracked
+    pub page_end: Option<i32>,
+    pub char_start: Option<i32>, // range within the document's extracted text
+    pub char_end: Option<i32>,
+    pub section_path: Option<String>,
+    pub score: f32, // cosine similarity, full-text rank, or fused RRF score, by mode
+    pub rerank_score: Option<f32>, // re-ranker relevance, when re-ranked
+    pub highlights: Vec<HighlightSpan>, // query terms and their stems within content
+    pub snippet: SnippetResponse,
+}
+
+/// Character offsets (not bytes) of a matched query term, end exclusive
+#[derive(Debug, Serialize)]
+pub struct HighlightSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Best-matching stretch of a result's content
+#[derive(Debug, Serialize)]
+pub struct SnippetResponse {
+    pub text: String,
+    pub highlights: Vec<HighlightSpan>, // offsets within text
+    pub truncated_start: bool,          // whether content precedes text
+    pub truncated_end: bool,            // whether content follows text
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AskRequest {
+    #[validate(length(min = 1, message = "Question is required"))]
+    pub question: String,
+    pub document_id: Option<String>, // Optional: answer from a specific document only
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 20, message = "limit must be between 1 and 20"))]
+    pub limit: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CitationResponse {
+    pub source: usize,
+    pub chunk_id: String,
+    pub document_id: String,
+    pub page: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnswerSentenceResponse {
+    pub text: String,
+    pub citations: Vec<CitationResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceResponse {
+    pub number: usize,
+    pub chunk_id: String,
+    pub document_id: String,
+    pub document_title: Option<String>,
+    pub page: Option<i32>,
+    pub section_path: Option<String>,
     pub content: String,
     pub score: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AskResponse {
+    pub answer: String,
+    pub sentences: Vec<AnswerSentenceResponse>,
+    pub sources: Vec<SourceResponse>,
+    pub usage: Option<TokenUsage>,
 }
\ No newline at end of file
@@ -1,2 +1,4 @@
 pub mod auth;
-pub mod document;
\ No newline at end of file
+pub mod conversation;
+pub mod document;
+pub mod quiz;
\ No newline at end of file
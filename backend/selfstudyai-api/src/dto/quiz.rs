@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::entities::question::Answer;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateQuizRequest {
+    pub document_id: String,
+    pub title: Option<String>,
+    #[validate(range(min = 1, max = 50, message = "num_questions must be between 1 and 50"))]
+    #[serde(default = "default_num_questions")]
+    pub num_questions: usize,
+}
+
+fn default_num_questions() -> usize {
+    10
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuizListQuery {
+    pub document_id: Option<String>, // Optional: only quizzes for this document
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuizQuestionResponse {
+    pub index: usize,
+    pub prompt: String,
+    #[serde(flatten)]
+    pub kind: QuestionViewKind,
+}
+
+/// What a student needs to answer a question, without the solution
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuestionViewKind {
+    MultipleChoice { options: Vec<String> },
+    TrueFalse,
+    MultiSelect { options: Vec<String> },
+    ShortAnswer,
+    Cloze { blank_count: usize },
+    Ordering { items: Vec<String> },
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuizResponse {
+    pub id: String,
+    pub document_id: String,
+    pub title: String,
+    pub total_questions: i32,
+    pub questions: Vec<QuizQuestionResponse>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuizSummaryResponse {
+    pub id: String,
+    pub document_id: String,
+    pub title: String,
+    pub total_questions: i32,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuizListResponse {
+    pub quizzes: Vec<QuizSummaryResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitAttemptRequest {
+    pub answers: Vec<Option<Answer>>, // one per question, null if skipped
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuestionResultResponse {
+    pub question_index: usize,
+    pub answer: Option<Answer>,
+    pub correct_answer: Answer,
+    pub is_correct: bool,
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttemptResponse {
+    pub id: String,
+    pub quiz_id: String,
+    pub score: i32,
+    pub total_questions: i32,
+    pub results: Vec<QuestionResultResponse>,
+    pub completed_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttemptSummaryResponse {
+    pub id: String,
+    pub quiz_id: String,
+    pub score: i32,
+    pub total_questions: i32,
+    pub completed_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttemptListResponse {
+    pub attempts: Vec<AttemptSummaryResponse>,
+}
@@ -1,6 +1,8 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::services::chunking::ChunkingConfig;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "document")]
 pub struct Model {
@@ -14,6 +16,10 @@ pub struct Model {
     pub page_count: Option<i32>,
     pub processing_status: String, // "pending", "processing", "completed", "failed"
     pub extracted_text: Option<String>,
+    pub processing_error: Option<String>, // why the last ingestion attempt failed
+    pub chunking: Option<ChunkingConfig>, // None on older documents until they're next processed
+    pub tags: Vec<String>,
+    pub folder: Option<String>, // e.g. "Physics/Mechanics"
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
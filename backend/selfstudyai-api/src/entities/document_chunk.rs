@@ -10,6 +10,11 @@ pub struct Model {
     pub chunk_index: i32,
     pub content: String,
     pub token_count: Option<i32>,
+    pub page_start: Option<i32>, // 1-based PDF pages the chunk spans
+    pub page_end: Option<i32>,
+    pub char_start: Option<i32>, // character range within document.extracted_text
+    pub char_end: Option<i32>,
+    pub section_path: Option<String>, // e.g. "Ch 3 > 3.2 Kinematics"
     pub created_at: DateTime,
 }
 
@@ -0,0 +1,20 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "embedding_cache")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub model: String, // embedding model id
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub text_hash: String, // hex SHA-256 of the normalized text
+    pub embedding: Vec<u8>, // little-endian f32s
+    pub hit_count: i32,
+    pub created_at: DateTime,
+    pub last_used_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
@@ -1,5 +1,12 @@
+pub mod conversation;
 pub mod document;
 pub mod document_chunk;
+pub mod embedding_cache;
+pub mod ingestion_job;
+pub mod message;
+pub mod question;
 pub mod quiz;
 pub mod quiz_attempt;
-pub mod user;
\ No newline at end of file
+pub mod search_history;
+pub mod user;
+pub mod vector_index;
\ No newline at end of file
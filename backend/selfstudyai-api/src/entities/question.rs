@@ -0,0 +1,745 @@
+use std::collections::HashSet;
+
+use sea_orm::FromJsonQueryResult;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Current version of the JSON stored in `quiz.questions` and `quiz_attempt.answers`.
+/// Version 0 is the untyped multiple-choice array written before the schema existed.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Marker for a blank in the prompt of a cloze question
+pub const CLOZE_BLANK: &str = "_____";
+
+/// A quiz question, tagged by `type` in JSON
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Question {
+    pub prompt: String,
+    #[serde(flatten)]
+    pub kind: QuestionKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuestionKind {
+    MultipleChoice {
+        options: Vec<String>,
+        correct_option: usize,
+    },
+    TrueFalse {
+        answer: bool,
+    },
+    MultiSelect {
+        options: Vec<String>,
+        correct_options: Vec<usize>,
+    },
+    ShortAnswer {
+        accepted_answers: Vec<String>,
+        #[serde(default)]
+        case_sensitive: bool,
+    },
+    /// Prompt contains one `CLOZE_BLANK` per entry of `blanks`, each listing accepted answers
+    Cloze {
+        blanks: Vec<Vec<String>>,
+    },
+    /// `items` are shown in the given order; `correct_order` lists item indices in the right order
+    Ordering {
+        items: Vec<String>,
+        correct_order: Vec<usize>,
+    },
+}
+
+/// A student's answer to one question, tagged by `type` in JSON.
+/// Variants mirror `QuestionKind` so the tags line up.
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Answer {
+    MultipleChoice { selected_option: usize },
+    TrueFalse { value: bool },
+    MultiSelect { selected_options: Vec<usize> },
+    ShortAnswer { text: String },
+    Cloze { blanks: Vec<String> },
+    Ordering { order: Vec<usize> },
+}
+
+/// The versioned contents of `quiz.questions`
+#[derive(Clone, Debug, PartialEq, Serialize, FromJsonQueryResult)]
+pub struct QuizQuestions {
+    pub schema_version: u32,
+    pub questions: Vec<Question>,
+}
+
+/// A graded answer as stored in `quiz_attempt.answers`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GradedAnswer {
+    pub question_index: usize,
+    pub answer: Option<Answer>,
+    pub is_correct: bool,
+}
+
+/// The versioned contents of `quiz_attempt.answers`
+#[derive(Clone, Debug, PartialEq, Serialize, FromJsonQueryResult)]
+pub struct AttemptAnswers {
+    pub schema_version: u32,
+    pub answers: Vec<GradedAnswer>,
+}
+
+impl Question {
+    /// Check that the question is well-formed
+    pub fn validate(&self) -> Result<(), String> {
+        if self.prompt.trim().is_empty() {
+            return Err("prompt must not be empty".to_string());
+        }
+
+        match &self.kind {
+            QuestionKind::MultipleChoice {
+                options,
+                correct_option,
+            } => {
+                validate_options(options)?;
+                if *correct_option >= options.len() {
+                    return Err("correct_option is out of range".to_string());
+                }
+            }
+            QuestionKind::TrueFalse { .. } => {}
+            QuestionKind::MultiSelect {
+                options,
+                correct_options,
+            } => {
+                validate_options(options)?;
+                if correct_options.is_empty() {
+                    return Err("multi-select needs at least one correct option".to_string());
+                }
+                validate_indices(correct_options, options.len(), "correct_options")?;
+            }
+            QuestionKind::ShortAnswer {
+                accepted_answers, ..
+            } => {
+                if accepted_answers.iter().all(|a| a.trim().is_empty()) {
+                    return Err("short answer needs at least one accepted answer".to_string());
+                }
+            }
+            QuestionKind::Cloze { blanks } => {
+                let markers = self.prompt.matches(CLOZE_BLANK).count();
+                if blanks.is_empty() || markers != blanks.len() {
+                    return Err(format!(
+                        "cloze prompt has {} blanks but {} answer sets",
+                        markers,
+                        blanks.len()
+                    ));
+                }
+                if blanks.iter().any(|b| b.iter().all(|a| a.trim().is_empty())) {
+                    return Err("every cloze blank needs an accepted answer".to_string());
+                }
+            }
+            QuestionKind::Ordering {
+                items,
+                correct_order,
+            } => {
+                if items.len() < 2 {
+                    return Err("ordering needs at least 2 items".to_string());
+                }
+                if correct_order.len() != items.len() {
+                    return Err("correct_order must list every item exactly once".to_string());
+                }
+                validate_indices(correct_order, items.len(), "correct_order")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that an answer has the right type and shape for this question
+    pub fn validate_answer(&self, answer: &Answer) -> Result<(), String> {
+        match (&self.kind, answer) {
+            (
+                QuestionKind::MultipleChoice { options, .. },
+                Answer::MultipleChoice { selected_option },
+            ) => {
+                if *selected_option >= options.len() {
+                    return Err("selected_option is out of range".to_string());
+                }
+            }
+            (QuestionKind::TrueFalse { .. }, Answer::TrueFalse { .. }) => {}
+            (
+                QuestionKind::MultiSelect { options, .. },
+                Answer::MultiSelect { selected_options },
+            ) => validate_indices(selected_options, options.len(), "selected_options")?,
+            (QuestionKind::ShortAnswer { .. }, Answer::ShortAnswer { .. }) => {}
+            (QuestionKind::Cloze { blanks }, Answer::Cloze { blanks: given }) => {
+                if given.len() != blanks.len() {
+                    return Err(format!(
+                        "expected {} blanks, got {}",
+                        blanks.len(),
+                        given.len()
+                    ));
+                }
+            }
+            (QuestionKind::Ordering { items, .. }, Answer::Ordering { order }) => {
+                if order.len() != items.len() {
+                    return Err("order must list every item exactly once".to_string());
+                }
+                validate_indices(order, items.len(), "order")?;
+            }
+            _ => return Err("answer type does not match question type".to_string()),
+        }
+
+        Ok(())
+    }
+
+    /// Grade an answer; missing or mismatched answers are incorrect
+    pub fn grade(&self, answer: Option<&Answer>) -> bool {
+        let Some(answer) = answer else {
+            return false;
+        };
+
+        match (&self.kind, answer) {
+            (
+                QuestionKind::MultipleChoice { correct_option, .. },
+                Answer::MultipleChoice { selected_option },
+            ) => selected_option == correct_option,
+            (QuestionKind::TrueFalse { answer }, Answer::TrueFalse { value }) => value == answer,
+            (
+                QuestionKind::MultiSelect {
+                    correct_options, ..
+                },
+                Answer::MultiSelect { selected_options },
+            ) => {
+                let expected: HashSet<_> = correct_options.iter().collect();
+                let given: HashSet<_> = selected_options.iter().collect();
+                expected == given
+            }
+            (
+                QuestionKind::ShortAnswer {
+                    accepted_answers,
+                    case_sensitive,
+                },
+                Answer::ShortAnswer { text },
+            ) => matches_any(text, accepted_answers, *case_sensitive),
+            (QuestionKind::Cloze { blanks }, Answer::Cloze { blanks: given }) => {
+                blanks.len() == given.len()
+                    && blanks
+                        .iter()
+                        .zip(given)
+                        .all(|(accepted, text)| matches_any(text, accepted, false))
+            }
+            (QuestionKind::Ordering { correct_order, .. }, Answer::Ordering { order }) => {
+                order == correct_order
+            }
+            _ => false,
+        }
+    }
+
+    /// The canonical correct answer, shown to students after grading
+    pub fn solution(&self) -> Answer {
+        match &self.kind {
+            QuestionKind::MultipleChoice { correct_option, .. } => Answer::MultipleChoice {
+                selected_option: *correct_option,
+            },
+            QuestionKind::TrueFalse { answer } => Answer::TrueFalse { value: *answer },
+            QuestionKind::MultiSelect {
+                correct_options, ..
+            } => Answer::MultiSelect {
+                selected_options: correct_options.clone(),
+            },
+            QuestionKind::ShortAnswer {
+                accepted_answers, ..
+            } => Answer::ShortAnswer {
+                text: accepted_answers.first().cloned().unwrap_or_default(),
+            },
+            QuestionKind::Cloze { blanks } => Answer::Cloze {
+                blanks: blanks
+                    .iter()
+                    .map(|b| b.first().cloned().unwrap_or_default())
+                    .collect(),
+            },
+            QuestionKind::Ordering { correct_order, .. } => Answer::Ordering {
+                order: correct_order.clone(),
+            },
+        }
+    }
+}
+
+impl QuizQuestions {
+    pub fn new(questions: Vec<Question>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            questions,
+        }
+    }
+
+    /// Validate every question, reporting the first problem with its index
+    pub fn validate(&self) -> Result<(), String> {
+        if self.questions.is_empty() {
+            return Err("quiz must contain at least one question".to_string());
+        }
+
+        for (index, question) in self.questions.iter().enumerate() {
+            question
+                .validate()
+                .map_err(|e| format!("question {}: {}", index, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Grade a full set of answers, one slot per question
+    pub fn grade(&self, answers: Vec<Option<Answer>>) -> AttemptAnswers {
+        let mut answers = answers.into_iter();
+
+        let graded = self
+            .questions
+            .iter()
+            .enumerate()
+            .map(|(index, question)| {
+                let answer = answers.next().flatten();
+                GradedAnswer {
+                    question_index: index,
+                    is_correct: question.grade(answer.as_ref()),
+                    answer,
+                }
+            })
+            .collect();
+
+        AttemptAnswers {
+            schema_version: SCHEMA_VERSION,
+            answers: graded,
+        }
+    }
+}
+
+impl AttemptAnswers {
+    pub fn score(&self) -> i32 {
+        self.answers.iter().filter(|a| a.is_correct).count() as i32
+    }
+
+    /// Check that there's one well-formed, correctly graded answer per question of the quiz
+    pub fn validate(&self, questions: &QuizQuestions) -> Result<(), String> {
+        if self.answers.len() != questions.questions.len() {
+            return Err(format!(
+                "expected {} answers, got {}",
+                questions.questions.len(),
+                self.answers.len()
+            ));
+        }
+
+        for (index, (graded, question)) in self.answers.iter().zip(&questions.questions).enumerate()
+        {
+            if graded.question_index != index {
+                return Err(format!(
+                    "answer {} has question_index {}",
+                    index, graded.question_index
+                ));
+            }
+            if let Some(answer) = &graded.answer {
+                question
+                    .validate_answer(answer)
+                    .map_err(|e| format!("answer {}: {}", index, e))?;
+            }
+            if graded.is_correct != question.grade(graded.answer.as_ref()) {
+                return Err(format!("answer {} is graded incorrectly", index));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_options(options: &[String]) -> Result<(), String> {
+    if options.len() < 2 {
+        return Err("needs at least 2 options".to_string());
+    }
+    if options.iter().any(|o| o.trim().is_empty()) {
+        return Err("options must not be empty".to_string());
+    }
+    Ok(())
+}
+
+fn validate_indices(indices: &[usize], len: usize, field: &str) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for index in indices {
+        if *index >= len {
+            return Err(format!("{} contains out-of-range index {}", field, index));
+        }
+        if !seen.insert(index) {
+            return Err(format!("{} contains duplicate index {}", field, index));
+        }
+    }
+    Ok(())
+}
+
+/// Compare free text ignoring surrounding whitespace, repeated spaces and trailing punctuation
+fn matches_any(text: &str, accepted: &[String], case_sensitive: bool) -> bool {
+    let normalize = |s: &str| {
+        let s = s
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end_matches(['.', ',', ';', ':', '!', '?'])
+            .to_string();
+        if case_sensitive {
+            s
+        } else {
+            s.to_lowercase()
+        }
+    };
+
+    let given = normalize(text);
+    !given.is_empty() && accepted.iter().any(|a| normalize(a) == given)
+}
+
+// Deserialization upgrades older stored versions to the current schema
+
+/// Version 0 question: untyped multiple choice
+#[derive(Deserialize)]
+struct LegacyQuestion {
+    question: String,
+    options: Vec<String>,
+    correct_option: usize,
+    explanation: Option<String>,
+}
+
+/// Version 0 answer: a graded multiple-choice selection
+#[derive(Deserialize)]
+struct LegacyResult {
+    question_index: usize,
+    selected_option: Option<usize>,
+    is_correct: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredQuestions {
+    Versioned {
+        schema_version: u32,
+        questions: Vec<Question>,
+    },
+    Legacy(Vec<LegacyQuestion>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredAnswers {
+    Versioned {
+        schema_version: u32,
+        answers: Vec<GradedAnswer>,
+    },
+    Legacy(Vec<LegacyResult>),
+}
+
+impl<'de> Deserialize<'de> for QuizQuestions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match StoredQuestions::deserialize(deserializer)? {
+            StoredQuestions::Versioned {
+                schema_version,
+                questions,
+            } => {
+                if schema_version > SCHEMA_VERSION {
+                    return Err(serde::de::Error::custom(format!(
+                        "unsupported quiz schema version {}",
+                        schema_version
+                    )));
+                }
+                Ok(Self::new(questions))
+            }
+            StoredQuestions::Legacy(legacy) => Ok(Self::new(
+                legacy
+                    .into_iter()
+                    .map(|q| Question {
+                        prompt: q.question,
+                        kind: QuestionKind::MultipleChoice {
+                            options: q.options,
+                            correct_option: q.correct_option,
+                        },
+                        explanation: q.explanation,
+                    })
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AttemptAnswers {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match StoredAnswers::deserialize(deserializer)? {
+            StoredAnswers::Versioned {
+                schema_version,
+                answers,
+            } => {
+                if schema_version > SCHEMA_VERSION {
+                    return Err(serde::de::Error::custom(format!(
+                        "unsupported attempt schema version {}",
+                        schema_version
+                    )));
+                }
+                Ok(Self {
+                    schema_version: SCHEMA_VERSION,
+                    answers,
+                })
+            }
+            StoredAnswers::Legacy(legacy) => Ok(Self {
+                schema_version: SCHEMA_VERSION,
+                answers: legacy
+                    .into_iter()
+                    .map(|r| GradedAnswer {
+                        question_index: r.question_index,
+                        answer: r
+                            .selected_option
+                            .map(|selected_option| Answer::MultipleChoice { selected_option }),
+                        is_correct: r.is_correct,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(kind: QuestionKind) -> Question {
+        Question {
+            prompt: format!("Fill in {} here", CLOZE_BLANK),
+            kind,
+            explanation: None,
+        }
+    }
+
+    fn multiple_choice() -> Question {
+        question(QuestionKind::MultipleChoice {
+            options: vec!["Newton".to_string(), "Joule".to_string()],
+            correct_option: 1,
+        })
+    }
+
+    #[test]
+    fn grades_multiple_choice() {
+        let q = multiple_choice();
+
+        assert!(q.grade(Some(&Answer::MultipleChoice { selected_option: 1 })));
+        assert!(!q.grade(Some(&Answer::MultipleChoice { selected_option: 0 })));
+    }
+
+    #[test]
+    fn grades_true_false() {
+        let q = question(QuestionKind::TrueFalse { answer: false });
+
+        assert!(q.grade(Some(&Answer::TrueFalse { value: false })));
+        assert!(!q.grade(Some(&Answer::TrueFalse { value: true })));
+    }
+
+    #[test]
+    fn grades_multi_select_regardless_of_order() {
+        let q = question(QuestionKind::MultiSelect {
+            options: vec!["mass".into(), "speed".into(), "force".into()],
+            correct_options: vec![0, 2],
+        });
+
+        assert!(q.grade(Some(&Answer::MultiSelect {
+            selected_options: vec![2, 0]
+        })));
+        assert!(!q.grade(Some(&Answer::MultiSelect {
+            selected_options: vec![0]
+        })));
+        assert!(!q.grade(Some(&Answer::MultiSelect {
+            selected_options: vec![0, 1, 2]
+        })));
+    }
+
+    #[test]
+    fn grades_short_answers_leniently() {
+        let q = question(QuestionKind::ShortAnswer {
+            accepted_answers: vec!["Kinetic energy".into()],
+            case_sensitive: false,
+        });
+
+        assert!(q.grade(Some(&Answer::ShortAnswer {
+            text: "  kinetic   ENERGY. ".into()
+        })));
+        assert!(!q.grade(Some(&Answer::ShortAnswer {
+            text: "potential energy".into()
+        })));
+        assert!(!q.grade(Some(&Answer::ShortAnswer { text: "  ".into() })));
+    }
+
+    #[test]
+    fn grades_case_sensitive_short_answers() {
+        let q = question(QuestionKind::ShortAnswer {
+            accepted_answers: vec!["NaCl".into()],
+            case_sensitive: true,
+        });
+
+        assert!(q.grade(Some(&Answer::ShortAnswer {
+            text: "NaCl".into()
+        })));
+        assert!(!q.grade(Some(&Answer::ShortAnswer {
+            text: "nacl".into()
+        })));
+    }
+
+    #[test]
+    fn grades_cloze_blanks() {
+        let q = question(QuestionKind::Cloze {
+            blanks: vec![vec!["inertia".into(), "mass".into()]],
+        });
+
+        assert!(q.grade(Some(&Answer::Cloze {
+            blanks: vec!["Mass".into()]
+        })));
+        assert!(!q.grade(Some(&Answer::Cloze {
+            blanks: vec!["weight".into()]
+        })));
+        assert!(!q.grade(Some(&Answer::Cloze { blanks: vec![] })));
+    }
+
+    #[test]
+    fn grades_ordering() {
+        let q = question(QuestionKind::Ordering {
+            items: vec!["b".into(), "a".into(), "c".into()],
+            correct_order: vec![1, 0, 2],
+        });
+
+        assert!(q.grade(Some(&Answer::Ordering {
+            order: vec![1, 0, 2]
+        })));
+        assert!(!q.grade(Some(&Answer::Ordering {
+            order: vec![0, 1, 2]
+        })));
+    }
+
+    #[test]
+    fn missing_or_mismatched_answers_are_incorrect() {
+        let q = multiple_choice();
+
+        assert!(!q.grade(None));
+        assert!(!q.grade(Some(&Answer::TrueFalse { value: true })));
+    }
+
+    #[test]
+    fn validates_answer_shapes() {
+        let q = multiple_choice();
+
+        assert!(q
+            .validate_answer(&Answer::MultipleChoice { selected_option: 1 })
+            .is_ok());
+        assert!(q
+            .validate_answer(&Answer::MultipleChoice { selected_option: 2 })
+            .is_err());
+        assert!(q
+            .validate_answer(&Answer::TrueFalse { value: true })
+            .is_err());
+    }
+
+    #[test]
+    fn validates_questions() {
+        assert!(multiple_choice().validate().is_ok());
+        assert!(question(QuestionKind::Cloze {
+            blanks: vec![vec!["a".into()], vec!["b".into()]],
+        })
+        .validate()
+        .is_err());
+        assert!(question(QuestionKind::Ordering {
+            items: vec!["a".into(), "b".into()],
+            correct_order: vec![0, 0],
+        })
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn upgrades_legacy_questions() {
+        let stored = serde_json::json!([{
+            "question": "Unit of energy?",
+            "options": ["Newton", "Joule"],
+            "correct_option": 1,
+            "explanation": "Energy is measured in joules"
+        }]);
+
+        let questions: QuizQuestions = serde_json::from_value(stored).unwrap();
+
+        assert_eq!(questions.schema_version, SCHEMA_VERSION);
+        assert_eq!(
+            questions.questions,
+            vec![Question {
+                prompt: "Unit of energy?".into(),
+                kind: QuestionKind::MultipleChoice {
+                    options: vec!["Newton".into(), "Joule".into()],
+                    correct_option: 1,
+                },
+                explanation: Some("Energy is measured in joules".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn upgrades_legacy_answers() {
+        let stored = serde_json::json!([
+            { "question_index": 0, "selected_option": 1, "is_correct": true },
+            { "question_index": 1, "selected_option": null, "is_correct": false }
+        ]);
+
+        let answers: AttemptAnswers = serde_json::from_value(stored).unwrap();
+
+        assert_eq!(answers.schema_version, SCHEMA_VERSION);
+        assert_eq!(
+            answers.answers,
+            vec![
+                GradedAnswer {
+                    question_index: 0,
+                    answer: Some(Answer::MultipleChoice { selected_option: 1 }),
+                    is_correct: true,
+                },
+                GradedAnswer {
+                    question_index: 1,
+                    answer: None,
+                    is_correct: false,
+                },
+            ]
+        );
+        assert_eq!(answers.score(), 1);
+    }
+
+    #[test]
+    fn round_trips_current_schema() {
+        let questions = QuizQuestions::new(vec![multiple_choice()]);
+
+        let stored = serde_json::to_value(&questions).unwrap();
+
+        assert_eq!(stored["schema_version"], SCHEMA_VERSION);
+        assert_eq!(stored["questions"][0]["type"], "multiple_choice");
+        assert_eq!(
+            serde_json::from_value::<QuizQuestions>(stored).unwrap(),
+            questions
+        );
+    }
+
+    #[test]
+    fn rejects_newer_schema_versions() {
+        let stored = serde_json::json!({ "schema_version": SCHEMA_VERSION + 1, "questions": [] });
+
+        assert!(serde_json::from_value::<QuizQuestions>(stored).is_err());
+    }
+
+    #[test]
+    fn validates_attempts_against_the_quiz() {
+        let questions = QuizQuestions::new(vec![multiple_choice()]);
+        let graded = questions.grade(vec![Some(Answer::MultipleChoice { selected_option: 1 })]);
+        assert!(graded.validate(&questions).is_ok());
+
+        let mut miscounted = graded.clone();
+        miscounted.answers.clear();
+        assert!(miscounted.validate(&questions).is_err());
+
+        let mut misgraded = graded.clone();
+        misgraded.answers[0].is_correct = false;
+        assert!(misgraded.validate(&questions).is_err());
+
+        let mut malformed = graded;
+        malformed.answers[0].answer = Some(Answer::MultipleChoice { selected_option: 5 });
+        malformed.answers[0].is_correct = false;
+        assert!(malformed.validate(&questions).is_err());
+    }
+}
\ No newline at end of file
@@ -1,6 +1,8 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::question::QuizQuestions;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "quiz")]
 pub struct Model {
@@ -9,7 +11,7 @@ pub struct Model {
     pub document_id: Uuid,
     pub user_id: Uuid,
     pub title: String,
-    pub questions: Json,
+    pub questions: QuizQuestions,
     pub total_questions: i32,
     pub created_at: DateTime,
 }
@@ -42,4 +44,27 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    /// Reject malformed questions before they are written
+    async fn before_save<C>(self, _db: &C, _insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if let Some(questions) = self.questions.try_as_ref() {
+            questions
+                .validate()
+                .map_err(|e| DbErr::Custom(format!("Invalid quiz questions: {}", e)))?;
+
+            if let Some(total) = self.total_questions.try_as_ref() {
+                if *total as usize != questions.questions.len() {
+                    return Err(DbErr::Custom(
+                        "total_questions does not match number of questions".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(self)
+    }
+}
\ No newline at end of file
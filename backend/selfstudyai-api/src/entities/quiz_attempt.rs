@@ -1,6 +1,8 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::question::AttemptAnswers;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "quiz_attempt")]
 pub struct Model {
@@ -8,7 +10,7 @@ pub struct Model {
     pub id: Uuid,
     pub quiz_id: Uuid,
     pub user_id: Uuid,
-    pub answers: Json,
+    pub answers: AttemptAnswers,
     pub score: i32,
     pub total_questions: i32,
     pub completed_at: DateTime,
@@ -42,4 +44,42 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    /// Reject answers that don't fit the quiz, or a score that doesn't match them
+    async fn before_save<C>(self, db: &C, _insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let (Some(answers), Some(quiz_id)) = (self.answers.try_as_ref(), self.quiz_id.try_as_ref())
+        else {
+            return Ok(self);
+        };
+
+        let quiz = super::quiz::Entity::find_by_id(*quiz_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::Custom("Quiz not found for attempt".to_string()))?;
+
+        answers
+            .validate(&quiz.questions)
+            .map_err(|e| DbErr::Custom(format!("Invalid attempt answers: {}", e)))?;
+
+        if let Some(score) = self.score.try_as_ref() {
+            if *score != answers.score() {
+                return Err(DbErr::Custom(
+                    "score does not match graded answers".to_string(),
+                ));
+            }
+        }
+        if let Some(total) = self.total_questions.try_as_ref() {
+            if *total as usize != answers.answers.len() {
+                return Err(DbErr::Custom(
+                    "total_questions does not match number of answers".to_string(),
+                ));
+            }
+        }
+
+        Ok(self)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A Qdrant collection holding the chunks embedded with one model
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "vector_index")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(unique)]
+    pub collection_name: String,
+    pub model: String,
+    pub dimension: i32,
+    pub status: String, // "building", "active", "retired" (kept for rollback), "failed"
+    pub total_chunks: i32,
+    pub indexed_chunks: i32,
+    pub error: Option<String>,
+    pub activated_at: Option<DateTime>, // when the search alias last pointed here
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
@@ -1,8 +1,12 @@
 use axum::{
+    extract::DefaultBodyLimit,
     middleware::from_fn_with_state,
-    routing::{get, post},
+    routing::{delete, get, patch, post},
     Router,
 };
+use std::sync::Arc;
+use std::time::Duration;
+
 use sea_orm::{Database, DatabaseConnection};
 use sea_orm_migration::prelude::*;
 use shuttle_runtime::SecretStore;
@@ -16,7 +20,17 @@ mod routes;
 mod services;
 
 use migrations::Migrator;
-use services::embeddings::EmbeddingsService;
+use services::document::{DocumentService, MAX_FILE_BYTES};
+use services::embedding_cache::EmbeddingCache;
+use services::embeddings::{EmbeddingOptions, EmbeddingsService};
+use services::fetcher::{RemoteFetcher, UrlPolicy};
+use services::chunking::TokenizerCache;
+use services::ingestion::IngestionService;
+use services::llm::LlmProvider;
+use services::progress::ProgressHub;
+use services::reindex::ReindexService;
+use services::reranker::Reranker;
+use services::storage::{BlobStore, S3Config};
 use services::vector_db::VectorDbService;
 
 #[derive(Clone)]
@@ -25,6 +39,24 @@ pub struct AppState {
     pub jwt_secret: String,
     pub embeddings_service: EmbeddingsService,
     pub vector_db: VectorDbService,
+    pub llm: Arc<dyn LlmProvider>,
+    pub progress: ProgressHub,
+    pub blob_store: Arc<dyn BlobStore>,
+    pub fetcher: RemoteFetcher,
+    pub tokenizers: TokenizerCache,
+    pub reranker: Arc<dyn Reranker>,
+}
+
+/// How often stale embedding cache entries are evicted
+const EMBEDDING_CACHE_CLEANUP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Parse a comma-separated secret
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
 }
 
 async fn hello_world() -> &'static str {
@@ -49,10 +81,6 @@ async fn main(
         .get("JWT_SECRET")
         .expect("JWT_SECRET must be set in Secrets.toml");
 
-    let huggingface_api_key = secrets
-        .get("HUGGINGFACE_API_KEY")
-        .expect("HUGGINGFACE_API_KEY must be set in Secrets.toml");
-
     let qdrant_url = secrets
         .get("QDRANT_URL")
         .expect("QDRANT_URL must be set in Secrets.toml");
@@ -61,6 +89,112 @@ async fn main(
         .get("QDRANT_API_KEY")
         .expect("QDRANT_API_KEY must be set in Secrets.toml");
 
+    // Embedding model: "huggingface", "openai_compatible", "ollama" or a local "onnx" model
+    let embedding_provider = secrets
+        .get("EMBEDDING_PROVIDER")
+        .unwrap_or_else(|| "huggingface".to_string());
+
+    let embedding_base_url = secrets.get("EMBEDDING_BASE_URL");
+    let embedding_api_key = secrets
+        .get("EMBEDDING_API_KEY")
+        .or_else(|| secrets.get("HUGGINGFACE_API_KEY"));
+    let embedding_model = secrets.get("EMBEDDING_MODEL");
+    let embedding_model_path = secrets.get("EMBEDDING_MODEL_PATH");
+
+    // Vector size; detected from the provider when unset
+    let embedding_dimension = secrets
+        .get("EMBEDDING_DIMENSION")
+        .and_then(|n| n.parse::<usize>().ok());
+
+    // Texts per embedding request, requests in flight, and retries of transient failures
+    let defaults = EmbeddingOptions::default();
+    let embedding_options = EmbeddingOptions {
+        batch_size: secrets
+            .get("EMBEDDING_BATCH_SIZE")
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(defaults.batch_size),
+        concurrency: secrets
+            .get("EMBEDDING_CONCURRENCY")
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(defaults.concurrency),
+        max_retries: secrets
+            .get("EMBEDDING_MAX_RETRIES")
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or(defaults.max_retries),
+        ..defaults
+    };
+
+    // Embedding cache entries unused for this long, or beyond this many, are evicted
+    let embedding_cache_max_age_days = secrets
+        .get("EMBEDDING_CACHE_MAX_AGE_DAYS")
+        .and_then(|n| n.parse::<u64>().ok())
+        .unwrap_or(90);
+
+    let embedding_cache_max_entries = secrets
+        .get("EMBEDDING_CACHE_MAX_ENTRIES")
+        .and_then(|n| n.parse::<u64>().ok())
+        .unwrap_or(1_000_000);
+
+    // LLM used for answering questions; any OpenAI-compatible server works
+    let llm_provider = secrets
+        .get("LLM_PROVIDER")
+        .unwrap_or_else(|| "openai_compatible".to_string());
+
+    let llm_base_url = secrets.get("LLM_BASE_URL");
+    let llm_api_key = secrets.get("LLM_API_KEY");
+    let llm_model = secrets.get("LLM_MODEL");
+
+    // Search re-ranking: "none", "cohere_compatible", "tei" or a local "onnx" cross-encoder
+    let reranker_provider = secrets
+        .get("RERANKER_PROVIDER")
+        .unwrap_or_else(|| "none".to_string());
+
+    let reranker_base_url = secrets.get("RERANKER_BASE_URL");
+    let reranker_api_key = secrets.get("RERANKER_API_KEY");
+    let reranker_model = secrets.get("RERANKER_MODEL");
+    let reranker_model_path = secrets.get("RERANKER_MODEL_PATH");
+
+    // Where uploaded PDFs are stored: "local" directory or an S3-compatible bucket
+    let blob_store_backend = secrets
+        .get("BLOB_STORE")
+        .unwrap_or_else(|| "local".to_string());
+
+    let blob_store_path = secrets
+        .get("BLOB_STORE_PATH")
+        .unwrap_or_else(|| "uploads".to_string());
+
+    let s3_config = match (
+        secrets.get("S3_BUCKET"),
+        secrets.get("S3_ACCESS_KEY_ID"),
+        secrets.get("S3_SECRET_ACCESS_KEY"),
+    ) {
+        (Some(bucket), Some(access_key_id), Some(secret_access_key)) => Some(S3Config {
+            endpoint: secrets.get("S3_ENDPOINT"),
+            bucket,
+            region: secrets
+                .get("S3_REGION")
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            access_key_id,
+            secret_access_key,
+        }),
+        _ => None,
+    };
+
+    // Remote file_url downloads are limited to these schemes and hosts (and their subdomains)
+    let file_url_allowed_schemes = secrets
+        .get("FILE_URL_ALLOWED_SCHEMES")
+        .unwrap_or_else(|| "https".to_string());
+
+    let file_url_allowed_hosts = secrets
+        .get("FILE_URL_ALLOWED_HOSTS")
+        .unwrap_or_else(|| "blob.vercel-storage.com".to_string());
+
+    // Background workers processing uploaded documents
+    let ingestion_workers = secrets
+        .get("INGESTION_WORKERS")
+        .and_then(|n| n.parse::<usize>().ok())
+        .unwrap_or(2);
+
     // Connect to database
     tracing::info!("Connecting to database...");
     let db: DatabaseConnection = Database::connect(&database_url)
@@ -78,28 +212,134 @@ async fn main(
 
     // Initialize embeddings service
     tracing::info!("Initializing embeddings service...");
-    let embeddings_service = EmbeddingsService::new(huggingface_api_key);
+    let embedding_cache = EmbeddingCache::new(
+        db.clone(),
+        Duration::from_secs(embedding_cache_max_age_days * 24 * 60 * 60),
+        embedding_cache_max_entries,
+    );
+    embedding_cache.spawn_cleanup(EMBEDDING_CACHE_CLEANUP_INTERVAL);
+
+    let embedding_provider = services::embeddings::from_config(
+        &embedding_provider,
+        embedding_base_url,
+        embedding_api_key,
+        embedding_model,
+        embedding_model_path,
+    )
+    .expect("Failed to initialize embedding provider");
+
+    let embeddings_service = EmbeddingsService::new(
+        embedding_provider,
+        embedding_dimension,
+        embedding_options,
+    )
+    .await
+    .expect("Failed to initialize embeddings service")
+    .with_cache(embedding_cache);
+    tracing::info!(
+        "Embedding model {} ({} dimensions)",
+        embeddings_service.model(),
+        embeddings_service.dimension()
+    );
 
     // Initialize vector database
     tracing::info!("Initializing vector database...");
-    let vector_db = VectorDbService::new(qdrant_url, qdrant_api_key)
-        .await
-        .expect("Failed to initialize vector database");
+    let vector_db = VectorDbService::new(
+        qdrant_url,
+        qdrant_api_key,
+        embeddings_service.model(),
+        embeddings_service.dimension(),
+    )
+    .await
+    .expect("Failed to initialize vector database");
 
     vector_db
         .initialize_collection()
         .await
         .expect("Failed to initialize Qdrant collection");
+
+    // Re-index into the collection for this embedding model if searches use another one
+    let needs_reindex = ReindexService::prepare(&db, &vector_db, &embeddings_service)
+        .await
+        .expect("Failed to prepare search index");
     tracing::info!("Vector database initialized!");
 
+    // One-off: tag vectors stored before per-user filtering existed with their owner
+    match DocumentService::backfill_vector_owners(&db, &vector_db).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Backfilled user_id on vectors of {} documents", count),
+        Err(e) => tracing::error!("Failed to backfill vector owners: {}", e),
+    }
+
+    // One-off: copy document tags, folders and upload times onto older vectors
+    match DocumentService::backfill_vector_labels(&db, &vector_db).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(
+            "Backfilled tags, folders and upload times on vectors of {} documents",
+            count
+        ),
+        Err(e) => tracing::error!("Failed to backfill vector labels: {}", e),
+    }
+
+    // Initialize LLM provider
+    tracing::info!("Initializing LLM provider: {}", llm_provider);
+    let llm = services::llm::from_config(&llm_provider, llm_base_url, llm_api_key, llm_model)
+        .expect("Failed to initialize LLM provider");
+
+    // Initialize search re-ranker
+    tracing::info!("Initializing reranker: {}", reranker_provider);
+    let reranker = services::reranker::from_config(
+        &reranker_provider,
+        reranker_base_url,
+        reranker_api_key,
+        reranker_model,
+        reranker_model_path,
+    )
+    .expect("Failed to initialize reranker");
+
+    // Pick up ingestion interrupted by a restart
+    match IngestionService::recover(&db).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Requeued {} interrupted ingestion jobs", count),
+        Err(e) => tracing::error!("Failed to recover ingestion jobs: {}", e),
+    }
+
+    // Initialize blob storage
+    tracing::info!("Initializing blob store: {}", blob_store_backend);
+    let blob_store =
+        services::storage::from_config(&blob_store_backend, blob_store_path, s3_config)
+            .expect("Failed to initialize blob store");
+
+    // Initialize remote file fetcher
+    let fetcher = RemoteFetcher::new(
+        UrlPolicy {
+            allowed_schemes: split_list(&file_url_allowed_schemes),
+            allowed_hosts: split_list(&file_url_allowed_hosts),
+        },
+        MAX_FILE_BYTES,
+    )
+    .expect("Failed to initialize remote file fetcher");
+
     // Create app state
     let state = AppState {
         db,
         jwt_secret,
         embeddings_service,
         vector_db,
+        llm,
+        progress: ProgressHub::new(),
+        blob_store,
+        fetcher,
+        tokenizers: TokenizerCache::new()?,
+        reranker,
     };
 
+    IngestionService::spawn_workers(state.clone(), ingestion_workers);
+
+    if needs_reindex {
+        ReindexService::spawn(state.clone());
+    }
+
     // CORS configuration
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -110,7 +350,60 @@ async fn main(
     let protected_routes = Router::new()
         .route("/api/documents", post(routes::document::upload_document))
         .route("/api/documents", get(routes::document::get_documents))
+        .route(
+            "/api/documents/upload",
+            post(routes::document::upload_document_file)
+                .layer(DefaultBodyLimit::max(routes::document::MAX_UPLOAD_BYTES)),
+        )
+        .route("/api/documents/{id}", get(routes::document::get_document))
+        .route("/api/documents/{id}", patch(routes::document::update_document))
+        .route("/api/documents/{id}", delete(routes::document::delete_document))
+        .route(
+            "/api/documents/{id}/reprocess",
+            post(routes::document::reprocess_document),
+        )
+        .route(
+            "/api/documents/{id}/events",
+            get(routes::document::document_events),
+        )
         .route("/api/search", post(routes::document::search_documents))
+        .route("/api/search/index", get(routes::document::get_search_index))
+        .route("/api/search/history", get(routes::document::get_search_history))
+        .route(
+            "/api/search/history",
+            delete(routes::document::clear_search_history),
+        )
+        .route(
+            "/api/search/history/{id}",
+            patch(routes::document::update_search_history_entry),
+        )
+        .route(
+            "/api/search/history/{id}",
+            delete(routes::document::delete_search_history_entry),
+        )
+        .route(
+            "/api/search/history/{id}/run",
+            post(routes::document::rerun_search),
+        )
+        .route("/api/ask", post(routes::document::ask_documents))
+        .route("/api/ask/stream", post(routes::document::ask_documents_stream))
+        .route("/api/conversations", post(routes::conversation::create_conversation))
+        .route("/api/conversations", get(routes::conversation::get_conversations))
+        .route("/api/conversations/{id}", get(routes::conversation::get_conversation))
+        .route(
+            "/api/conversations/{id}",
+            delete(routes::conversation::delete_conversation),
+        )
+        .route(
+            "/api/conversations/{id}/messages",
+            post(routes::conversation::send_message),
+        )
+        .route("/api/quizzes", post(routes::quiz::create_quiz))
+        .route("/api/quizzes", get(routes::quiz::get_quizzes))
+        .route("/api/quizzes/{id}", get(routes::quiz::get_quiz))
+        .route("/api/quizzes/{id}", delete(routes::quiz::delete_quiz))
+        .route("/api/quizzes/{id}/attempts", post(routes::quiz::submit_attempt))
+        .route("/api/quizzes/{id}/attempts", get(routes::quiz::get_attempts))
         .layer(from_fn_with_state(
             state.clone(),
             middleware::auth::auth_middleware,
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Message::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Message::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Message::ConversationId).uuid().not_null())
+                    .col(ColumnDef::new(Message::Role).string().not_null())
+                    .col(ColumnDef::new(Message::Content).text().not_null())
+                    .col(ColumnDef::new(Message::StandaloneQuery).text())
+                    .col(ColumnDef::new(Message::ChunkIds).json().not_null())
+                    .col(
+                        ColumnDef::new(Message::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_message_conversation")
+                            .from(Message::Table, Message::ConversationId)
+                            .to(Conversation::Table, Conversation::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create index for loading a conversation's history
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_message_conversation_id")
+                    .table(Message::Table)
+                    .col(Message::ConversationId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Message::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+    ConversationId,
+    Role,
+    Content,
+    StandaloneQuery,
+    ChunkIds,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Conversation {
+    Table,
+    Id,
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(IngestionJob::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(IngestionJob::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(IngestionJob::DocumentId).uuid().not_null())
+                    .col(ColumnDef::new(IngestionJob::Status).string().not_null())
+                    .col(
+                        ColumnDef::new(IngestionJob::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(IngestionJob::MaxAttempts)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(IngestionJob::RunAt).timestamp().not_null())
+                    .col(ColumnDef::new(IngestionJob::LockedAt).timestamp())
+                    .col(ColumnDef::new(IngestionJob::LastError).text())
+                    .col(
+                        ColumnDef::new(IngestionJob::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(IngestionJob::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_ingestion_job_document")
+                            .from(IngestionJob::Table, IngestionJob::DocumentId)
+                            .to(Document::Table, Document::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create index for workers looking for due jobs
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_ingestion_job_status_run_at")
+                    .table(IngestionJob::Table)
+                    .col(IngestionJob::Status)
+                    .col(IngestionJob::RunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_ingestion_job_document_id")
+                    .table(IngestionJob::Table)
+                    .col(IngestionJob::DocumentId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IngestionJob::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum IngestionJob {
+    Table,
+    Id,
+    DocumentId,
+    Status,
+    Attempts,
+    MaxAttempts,
+    RunAt,
+    LockedAt,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Document {
+    Table,
+    Id,
+}
\ No newline at end of file
@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Nullable: chunks created before positions were tracked have none
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DocumentChunk::Table)
+                    .add_column_if_not_exists(ColumnDef::new(DocumentChunk::PageStart).integer())
+                    .add_column_if_not_exists(ColumnDef::new(DocumentChunk::PageEnd).integer())
+                    .add_column_if_not_exists(ColumnDef::new(DocumentChunk::CharStart).integer())
+                    .add_column_if_not_exists(ColumnDef::new(DocumentChunk::CharEnd).integer())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DocumentChunk::Table)
+                    .drop_column(DocumentChunk::PageStart)
+                    .drop_column(DocumentChunk::PageEnd)
+                    .drop_column(DocumentChunk::CharStart)
+                    .drop_column(DocumentChunk::CharEnd)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DocumentChunk {
+    Table,
+    PageStart,
+    PageEnd,
+    CharStart,
+    CharEnd,
+}
\ No newline at end of file
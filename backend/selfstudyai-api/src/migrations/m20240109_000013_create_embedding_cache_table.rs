@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmbeddingCache::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(EmbeddingCache::Model).string().not_null())
+                    .col(ColumnDef::new(EmbeddingCache::TextHash).string().not_null())
+                    .col(ColumnDef::new(EmbeddingCache::Embedding).binary().not_null())
+                    .col(
+                        ColumnDef::new(EmbeddingCache::HitCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(EmbeddingCache::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(EmbeddingCache::LastUsedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(EmbeddingCache::Model)
+                            .col(EmbeddingCache::TextHash),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create index for evicting the least recently used entries
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_embedding_cache_last_used_at")
+                    .table(EmbeddingCache::Table)
+                    .col(EmbeddingCache::LastUsedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EmbeddingCache::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EmbeddingCache {
+    Table,
+    Model,
+    TextHash,
+    Embedding,
+    HitCount,
+    CreatedAt,
+    LastUsedAt,
+}
\ No newline at end of file
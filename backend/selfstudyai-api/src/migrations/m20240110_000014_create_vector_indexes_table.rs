@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(VectorIndex::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(VectorIndex::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(VectorIndex::CollectionName)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(VectorIndex::Model).string().not_null())
+                    .col(ColumnDef::new(VectorIndex::Dimension).integer().not_null())
+                    .col(ColumnDef::new(VectorIndex::Status).string().not_null())
+                    .col(
+                        ColumnDef::new(VectorIndex::TotalChunks)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(VectorIndex::IndexedChunks)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(VectorIndex::Error).text())
+                    .col(ColumnDef::new(VectorIndex::ActivatedAt).timestamp())
+                    .col(
+                        ColumnDef::new(VectorIndex::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(VectorIndex::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(VectorIndex::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum VectorIndex {
+    Table,
+    Id,
+    CollectionName,
+    Model,
+    Dimension,
+    Status,
+    TotalChunks,
+    IndexedChunks,
+    Error,
+    ActivatedAt,
+    CreatedAt,
+    UpdatedAt,
+}
\ No newline at end of file
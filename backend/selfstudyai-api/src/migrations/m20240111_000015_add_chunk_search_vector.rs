@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Generated by Postgres, so it stays in sync with content without the entity knowing
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "ALTER TABLE document_chunk ADD COLUMN IF NOT EXISTS content_tsv tsvector \
+                 GENERATED ALWAYS AS (to_tsvector('english', content)) STORED",
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE INDEX IF NOT EXISTS idx_chunk_content_tsv \
+                 ON document_chunk USING GIN (content_tsv)",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP INDEX IF EXISTS idx_chunk_content_tsv")
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("ALTER TABLE document_chunk DROP COLUMN IF EXISTS content_tsv")
+            .await?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
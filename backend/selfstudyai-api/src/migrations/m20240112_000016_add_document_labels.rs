@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Document::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Document::Tags)
+                            .array(ColumnType::Text)
+                            .not_null()
+                            .default(Expr::cust("'{}'")),
+                    )
+                    .add_column_if_not_exists(ColumnDef::new(Document::Folder).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE INDEX IF NOT EXISTS idx_document_tags ON document USING GIN (tags)",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP INDEX IF EXISTS idx_document_tags")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Document::Table)
+                    .drop_column(Document::Tags)
+                    .drop_column(Document::Folder)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Document {
+    Table,
+    Tags,
+    Folder,
+}
\ No newline at end of file
@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SearchHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SearchHistory::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SearchHistory::UserId).uuid().not_null())
+                    .col(ColumnDef::new(SearchHistory::Query).text().not_null())
+                    .col(ColumnDef::new(SearchHistory::Request).json().not_null())
+                    .col(ColumnDef::new(SearchHistory::ChunkIds).json().not_null())
+                    .col(
+                        ColumnDef::new(SearchHistory::Pinned)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(ColumnDef::new(SearchHistory::Name).string())
+                    .col(
+                        ColumnDef::new(SearchHistory::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_search_history_user")
+                            .from(SearchHistory::Table, SearchHistory::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create index for listing a user's searches, newest first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_search_history_user_created_at")
+                    .table(SearchHistory::Table)
+                    .col(SearchHistory::UserId)
+                    .col(SearchHistory::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SearchHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SearchHistory {
+    Table,
+    Id,
+    UserId,
+    Query,
+    Request,
+    ChunkIds,
+    Pinned,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
\ No newline at end of file
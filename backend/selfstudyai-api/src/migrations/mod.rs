@@ -5,6 +5,18 @@ pub mod m20240102_000002_create_documents_table;
 pub mod m20240102_000003_create_document_chunks_table;
 pub mod m20240103_000004_create_quizzes_table;
 pub mod m20240103_000005_create_quiz_attempts_table;
+pub mod m20240104_000006_create_conversations_table;
+pub mod m20240104_000007_create_messages_table;
+pub mod m20240105_000008_create_ingestion_jobs_table;
+pub mod m20240105_000009_add_document_processing_error;
+pub mod m20240106_000010_add_chunk_positions;
+pub mod m20240107_000011_add_chunk_section_path;
+pub mod m20240108_000012_add_document_chunking;
+pub mod m20240109_000013_create_embedding_cache_table;
+pub mod m20240110_000014_create_vector_indexes_table;
+pub mod m20240111_000015_add_chunk_search_vector;
+pub mod m20240112_000016_add_document_labels;
+pub mod m20240113_000017_create_search_history_table;
 
 pub struct Migrator;
 
@@ -17,6 +29,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20240102_000003_create_document_chunks_table::Migration),
             Box::new(m20240103_000004_create_quizzes_table::Migration),
             Box::new(m20240103_000005_create_quiz_attempts_table::Migration),
+            Box::new(m20240104_000006_create_conversations_table::Migration),
+            Box::new(m20240104_000007_create_messages_table::Migration),
+            Box::new(m20240105_000008_create_ingestion_jobs_table::Migration),
+            Box::new(m20240105_000009_add_document_processing_error::Migration),
+            Box::new(m20240106_000010_add_chunk_positions::Migration),
+            Box::new(m20240107_000011_add_chunk_section_path::Migration),
+            Box::new(m20240108_000012_add_document_chunking::Migration),
+            Box::new(m20240109_000013_create_embedding_cache_table::Migration),
+            Box::new(m20240110_000014_create_vector_indexes_table::Migration),
+            Box::new(m20240111_000015_add_chunk_search_vector::Migration),
+            Box::new(m20240112_000016_add_document_labels::Migration),
+            Box::new(m20240113_000017_create_search_history_table::Migration),
         ]
     }
 }
\ No newline at end of file
@@ -0,0 +1,216 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::dto::conversation::{
+    ConversationDetailResponse, ConversationListResponse, ConversationResponse,
+    CreateConversationRequest, MessageResponse, SendMessageRequest, SendMessageResponse,
+};
+use crate::entities::{conversation, message};
+use crate::routes::document::ask_response;
+use crate::routes::{error_response, retrieval_error};
+use crate::services::conversation::ConversationService;
+use crate::AppState;
+
+fn conversation_response(conversation: conversation::Model) -> ConversationResponse {
+    ConversationResponse {
+        document_ids: ConversationService::document_ids(&conversation)
+            .iter()
+            .map(|id| id.to_string())
+            .collect(),
+        id: conversation.id.to_string(),
+        title: conversation.title,
+        created_at: conversation.created_at.to_string(),
+        updated_at: conversation.updated_at.to_string(),
+    }
+}
+
+fn message_response(message: message::Model) -> MessageResponse {
+    MessageResponse {
+        chunk_ids: ConversationService::chunk_ids(&message),
+        id: message.id.to_string(),
+        role: message.role,
+        content: message.content,
+        standalone_query: message.standalone_query,
+        created_at: message.created_at.to_string(),
+    }
+}
+
+pub async fn create_conversation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<CreateConversationRequest>,
+) -> impl IntoResponse {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {}", errors),
+        );
+    }
+
+    let mut document_ids = Vec::with_capacity(payload.document_ids.len());
+    for id in &payload.document_ids {
+        match Uuid::parse_str(id) {
+            Ok(id) if !document_ids.contains(&id) => document_ids.push(id),
+            Ok(_) => {}
+            Err(_) => {
+                return error_response(StatusCode::BAD_REQUEST, "Invalid document_id format")
+            }
+        }
+    }
+
+    match ConversationService::create_conversation(&state.db, user_id, payload.title, document_ids)
+        .await
+    {
+        Ok(Some(conversation)) => (
+            StatusCode::CREATED,
+            Json(conversation_response(conversation)),
+        )
+            .into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Document not found"),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create conversation: {}", e),
+        ),
+    }
+}
+
+pub async fn get_conversations(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> impl IntoResponse {
+    match ConversationService::get_user_conversations(&state.db, user_id).await {
+        Ok(conversations) => {
+            let response = ConversationListResponse {
+                conversations: conversations
+                    .into_iter()
+                    .map(conversation_response)
+                    .collect(),
+            };
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch conversations: {}", e),
+        ),
+    }
+}
+
+pub async fn get_conversation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(conversation_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let conversation =
+        match ConversationService::get_conversation_by_id(&state.db, conversation_id, user_id)
+            .await
+        {
+            Ok(Some(conversation)) => conversation,
+            Ok(None) => return error_response(StatusCode::NOT_FOUND, "Conversation not found"),
+            Err(e) => {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch conversation: {}", e),
+                )
+            }
+        };
+
+    match ConversationService::get_messages(&state.db, conversation.id).await {
+        Ok(messages) => {
+            let response = ConversationDetailResponse {
+                conversation: conversation_response(conversation),
+                messages: messages.into_iter().map(message_response).collect(),
+            };
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch messages: {}", e),
+        ),
+    }
+}
+
+pub async fn delete_conversation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(conversation_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let conversation =
+        match ConversationService::get_conversation_by_id(&state.db, conversation_id, user_id)
+            .await
+        {
+            Ok(Some(conversation)) => conversation,
+            Ok(None) => return error_response(StatusCode::NOT_FOUND, "Conversation not found"),
+            Err(e) => {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch conversation: {}", e),
+                )
+            }
+        };
+
+    match ConversationService::delete_conversation(&state.db, conversation).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete conversation: {}", e),
+        ),
+    }
+}
+
+pub async fn send_message(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(conversation_id): Path<Uuid>,
+    Json(payload): Json<SendMessageRequest>,
+) -> impl IntoResponse {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {}", errors),
+        );
+    }
+
+    let conversation =
+        match ConversationService::get_conversation_by_id(&state.db, conversation_id, user_id)
+            .await
+        {
+            Ok(Some(conversation)) => conversation,
+            Ok(None) => return error_response(StatusCode::NOT_FOUND, "Conversation not found"),
+            Err(e) => {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch conversation: {}", e),
+                )
+            }
+        };
+
+    match ConversationService::send_message(
+        &state,
+        &conversation,
+        payload.content,
+        payload.limit,
+    )
+    .await
+    {
+        Ok((user_message, assistant_message, answer)) => {
+            let response = SendMessageResponse {
+                user_message: message_response(user_message),
+                assistant_message: message_response(assistant_message),
+                answer: ask_response(answer),
+            };
+
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(e) => retrieval_error(e, "Failed to send message"),
+    }
+}
\ No newline at end of file
@@ -1,75 +1,182 @@
 use axum::{
-    extract::{Extension, State},
+    extract::{Extension, Multipart, Path, Query, State},
     http::StatusCode,
     Json,
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
 };
+use futures_util::StreamExt;
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::dto::auth::ErrorResponse;
 use crate::dto::document::{
-    DocumentListResponse, DocumentResponse, SearchRequest, SearchResponse, SearchResultItem,
-    UploadDocumentRequest,
+    AnswerSentenceResponse, AskRequest, AskResponse, CitationResponse, ClearSearchHistoryQuery,
+    DocumentListResponse, DocumentResponse, HighlightSpan, SearchHistoryEntryResponse,
+    SearchHistoryQuery, SearchHistoryResponse, SearchIndexResponse, SearchRequest, SearchResponse,
+    SearchResultItem, SnippetResponse, SourceResponse, UpdateDocumentRequest,
+    UpdateSearchHistoryRequest, UploadDocumentRequest,
 };
-use crate::services::document::DocumentService;
+use crate::entities::{document, search_history};
+use crate::routes::{error_response, retrieval_error};
+use crate::services::chunking::ChunkingConfig;
+use crate::services::document::{
+    AlreadyProcessing, DocumentOptions, DocumentService, MAX_FILE_BYTES,
+};
+use crate::services::highlight::{Highlight, HighlightService, QueryTerms, SNIPPET_WORDS};
+use crate::services::ingestion::IngestionService;
+use crate::services::llm::StreamEvent;
+use crate::services::progress::ProgressEvent;
+use crate::services::rag::{RagAnswer, RagService, NO_SOURCES_ANSWER};
+use crate::services::reindex::ReindexService;
+use crate::services::search::{SearchOptions, SearchService, MAX_SEARCH_DEPTH};
+use crate::services::search_history::SearchHistoryService;
+use crate::services::vector_db::{SearchFilter, SearchResult};
 use crate::AppState;
 
+/// Request body limit for multipart uploads, leaving room for the form around the file
+pub const MAX_UPLOAD_BYTES: usize = MAX_FILE_BYTES + 1024 * 1024;
+
+/// Most neighbouring chunks a search may attach to each side of a result
+const MAX_CONTEXT_CHUNKS: u32 = 5;
+
+/// Most search history entries returned at once
+const MAX_HISTORY_PAGE: u64 = 200;
+
+fn highlight_spans(highlights: Vec<Highlight>) -> Vec<HighlightSpan> {
+    highlights
+        .into_iter()
+        .map(|h| HighlightSpan {
+            start: h.start,
+            end: h.end,
+        })
+        .collect()
+}
+
+/// A search hit with its query terms highlighted and a snippet around the best match
+fn search_result_item(result: SearchResult, terms: &QueryTerms) -> SearchResultItem {
+    let highlights = HighlightService::highlights(&result.content, terms);
+    let snippet = HighlightService::snippet(&result.content, terms, SNIPPET_WORDS);
+
+    SearchResultItem {
+        document_id: result.document_id,
+        chunk_id: result.chunk_id,
+        chunk_ids: result.chunk_ids,
+        content: result.content,
+        page_start: result.page_start,
+        page_end: result.page_end,
+        char_start: result.char_start,
+        char_end: result.char_end,
+        section_path: result.section_path,
+        score: result.score,
+        rerank_score: result.rerank_score,
+        highlights: highlight_spans(highlights),
+        snippet: SnippetResponse {
+            text: snippet.text,
+            highlights: highlight_spans(snippet.highlights),
+            truncated_start: snippet.truncated_start,
+            truncated_end: snippet.truncated_end,
+        },
+    }
+}
+
+fn document_response(document: document::Model) -> DocumentResponse {
+    DocumentResponse {
+        id: document.id.to_string(),
+        title: document.title,
+        file_name: document.file_name,
+        file_url: document.file_url,
+        file_size: document.file_size,
+        page_count: document.page_count,
+        processing_status: document.processing_status,
+        processing_error: document.processing_error,
+        chunking: document.chunking,
+        tags: document.tags,
+        folder: document.folder,
+        created_at: document.created_at.to_string(),
+    }
+}
+
+/// Reject document filters naming documents the caller doesn't own
+async fn verify_document_access(
+    state: &AppState,
+    user_id: Uuid,
+    document_ids: &[Uuid],
+) -> Result<(), Response> {
+    match DocumentService::owns_documents(&state.db, user_id, document_ids).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(error_response(StatusCode::NOT_FOUND, "Document not found")),
+        Err(e) => Err(error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to verify document access: {}", e),
+        )),
+    }
+}
+
 pub async fn upload_document(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Json(payload): Json<UploadDocumentRequest>,
 ) -> impl IntoResponse {
+    // Refuse URLs we won't fetch now rather than failing in the background later
+    if let Err(e) = state.fetcher.check_url(&payload.file_url) {
+        return error_response(StatusCode::BAD_REQUEST, e.to_string());
+    }
+
+    if payload.file_size <= 0 || payload.file_size as usize > MAX_FILE_BYTES {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("file_size must be between 1 and {} bytes", MAX_FILE_BYTES),
+        );
+    }
+
+    if let Some(Err(e)) = payload.chunking.as_ref().map(ChunkingConfig::validate) {
+        return error_response(StatusCode::BAD_REQUEST, format!("Validation error: {}", e));
+    }
+
+    let tags = match DocumentService::normalize_tags(payload.tags) {
+        Ok(tags) => tags,
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
+        }
+    };
+    let folder = match DocumentService::normalize_folder(payload.folder) {
+        Ok(folder) => folder,
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
+        }
+    };
+
     // Create document record
     match DocumentService::create_document(
         &state.db,
         user_id,
         payload.title,
         payload.file_name,
-        payload.file_url.clone(),
+        payload.file_url,
         payload.file_size,
+        DocumentOptions {
+            chunking: payload.chunking,
+            tags,
+            folder,
+        },
     )
     .await
     {
         Ok(document) => {
-            // Spawn background task to process PDF
-            let db = state.db.clone();
-            let embeddings_service = state.embeddings_service.clone();
-            let vector_db = state.vector_db.clone();
-            let document_id = document.id;
-            let file_url = payload.file_url.clone();
-
-            tokio::spawn(async move {
-                // Download PDF from Vercel Blob
-                if let Ok(response) = reqwest::get(&file_url).await {
-                    if let Ok(pdf_bytes) = response.bytes().await {
-                        // Process PDF
-                        if let Err(e) = DocumentService::process_pdf(
-                            &db,
-                            &embeddings_service,
-                            &vector_db,
-                            document_id,
-                            &pdf_bytes,
-                        )
-                        .await
-                        {
-                            tracing::error!("Failed to process PDF: {}", e);
-                        }
-                    }
-                }
-            });
-
-            let response = DocumentResponse {
-                id: document.id.to_string(),
-                title: document.title,
-                file_name: document.file_name,
-                file_url: document.file_url,
-                file_size: document.file_size,
-                page_count: document.page_count,
-                processing_status: document.processing_status,
-                created_at: document.created_at.to_string(),
-            };
+            // Processed by the ingestion workers; documents left pending are
+            // picked up again on startup if queueing fails here
+            if let Err(e) = IngestionService::enqueue(&state.db, document.id).await {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to queue document for processing: {}", e),
+                );
+            }
 
-            (StatusCode::CREATED, Json(response)).into_response()
+            (StatusCode::CREATED, Json(document_response(document))).into_response()
         }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -81,6 +188,221 @@ pub async fn upload_document(
     }
 }
 
+/// Upload a PDF directly as multipart form data: a `file` part, an optional `title`,
+/// an optional `chunking` part holding the chunking configuration as JSON, and optional
+/// `tags` (comma-separated) and `folder` parts
+pub async fn upload_document_file(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let mut title = None;
+    let mut chunking = None;
+    let mut tags = Vec::new();
+    let mut folder = None;
+    let mut file = None;
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return error_response(e.status(), e.body_text()),
+        };
+
+        match field.name() {
+            Some("title") => match field.text().await {
+                Ok(text) => title = Some(text),
+                Err(e) => return error_response(e.status(), e.body_text()),
+            },
+            Some("tags") => match field.text().await {
+                Ok(text) => tags = text.split(',').map(str::to_string).collect(),
+                Err(e) => return error_response(e.status(), e.body_text()),
+            },
+            Some("folder") => match field.text().await {
+                Ok(text) => folder = Some(text),
+                Err(e) => return error_response(e.status(), e.body_text()),
+            },
+            Some("chunking") => {
+                let text = match field.text().await {
+                    Ok(text) => text,
+                    Err(e) => return error_response(e.status(), e.body_text()),
+                };
+                let config: ChunkingConfig = match serde_json::from_str(&text) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        return error_response(
+                            StatusCode::BAD_REQUEST,
+                            format!("Invalid chunking configuration: {}", e),
+                        )
+                    }
+                };
+                if let Err(e) = config.validate() {
+                    return error_response(
+                        StatusCode::BAD_REQUEST,
+                        format!("Validation error: {}", e),
+                    );
+                }
+                chunking = Some(config);
+            }
+            Some("file") => {
+                let file_name = field.file_name().unwrap_or("document.pdf").to_string();
+                match field.bytes().await {
+                    Ok(bytes) => file = Some((file_name, bytes)),
+                    Err(e) => return error_response(e.status(), e.body_text()),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some((file_name, bytes)) = file else {
+        return error_response(StatusCode::BAD_REQUEST, "Missing file");
+    };
+
+    let tags = match DocumentService::normalize_tags(tags) {
+        Ok(tags) => tags,
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
+        }
+    };
+    let folder = match DocumentService::normalize_folder(folder) {
+        Ok(folder) => folder,
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
+        }
+    };
+
+    if bytes.len() > MAX_FILE_BYTES {
+        return error_response(StatusCode::PAYLOAD_TOO_LARGE, "File is too large");
+    }
+
+    if !bytes.starts_with(b"%PDF-") {
+        return error_response(StatusCode::UNSUPPORTED_MEDIA_TYPE, "File is not a PDF");
+    }
+
+    // Default the title to the file name without its extension
+    let title = title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| {
+            file_name
+                .strip_suffix(".pdf")
+                .unwrap_or(&file_name)
+                .to_string()
+        });
+
+    let file_size = bytes.len() as i32;
+    let key = format!("uploads/{}/{}.pdf", user_id, Uuid::new_v4());
+
+    if let Err(e) = state.blob_store.put(&key, bytes).await {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to store file: {}", e),
+        );
+    }
+
+    let document = match DocumentService::create_document(
+        &state.db,
+        user_id,
+        title,
+        file_name,
+        key.clone(),
+        file_size,
+        DocumentOptions {
+            chunking,
+            tags,
+            folder,
+        },
+    )
+    .await
+    {
+        Ok(document) => document,
+        Err(e) => {
+            if let Err(e) = state.blob_store.delete(&key).await {
+                tracing::warn!("Failed to remove orphaned upload {}: {}", key, e);
+            }
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create document: {}", e),
+            );
+        }
+    };
+
+    // Processed by the ingestion workers; documents left pending are
+    // picked up again on startup if queueing fails here
+    if let Err(e) = IngestionService::enqueue(&state.db, document.id).await {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to queue document for processing: {}", e),
+        );
+    }
+
+    (StatusCode::CREATED, Json(document_response(document))).into_response()
+}
+
+/// Stream a document's processing progress as SSE events named after each stage,
+/// ending with "completed" or a "failed" that won't be retried
+pub async fn document_events(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(document_id): Path<Uuid>,
+) -> impl IntoResponse {
+    // Subscribe before reading the status so nothing published in between is missed
+    let (last, mut receiver) = state.progress.subscribe(document_id);
+
+    let document =
+        match DocumentService::get_document_by_id(&state.db, document_id, user_id).await {
+            Ok(Some(document)) => document,
+            Ok(None) => return error_response(StatusCode::NOT_FOUND, "Document not found"),
+            Err(e) => {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch document: {}", e),
+                )
+            }
+        };
+
+    // Already done, report the outcome and close
+    let outcome = match document.processing_status.as_str() {
+        "completed" => Some(ProgressEvent::Completed),
+        "failed" => Some(ProgressEvent::Failed {
+            error: document.processing_error.unwrap_or_default(),
+            retrying: false,
+        }),
+        _ => None,
+    };
+
+    let events = async_stream::stream! {
+        if let Some(event) = outcome {
+            yield Event::default().event(event.name()).json_data(event);
+            return;
+        }
+
+        if let Some(event) = last {
+            yield Event::default().event(event.name()).json_data(event);
+        }
+
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let is_final = event.is_final();
+                    yield Event::default().event(event.name()).json_data(event);
+                    if is_final {
+                        return;
+                    }
+                }
+                // Skipped events are superseded by later ones
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            }
+        }
+    };
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
 pub async fn get_documents(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -90,16 +412,7 @@ pub async fn get_documents(
             let response = DocumentListResponse {
                 documents: documents
                     .into_iter()
-                    .map(|doc| DocumentResponse {
-                        id: doc.id.to_string(),
-                        title: doc.title,
-                        file_name: doc.file_name,
-                        file_url: doc.file_url,
-                        file_size: doc.file_size,
-                        page_count: doc.page_count,
-                        processing_status: doc.processing_status,
-                        created_at: doc.created_at.to_string(),
-                    })
+                    .map(document_response)
                     .collect(),
             };
 
@@ -115,74 +428,736 @@ pub async fn get_documents(
     }
 }
 
-pub async fn search_documents(
+pub async fn get_document(
     State(state): State<AppState>,
-    Extension(_user_id): Extension<Uuid>,
-    Json(payload): Json<SearchRequest>,
+    Extension(user_id): Extension<Uuid>,
+    Path(document_id): Path<Uuid>,
 ) -> impl IntoResponse {
-    // Generate embedding for query
-    let query_embedding = match state
-        .embeddings_service
-        .generate_embedding(payload.query.clone())
-        .await
+    match DocumentService::get_document_by_id(&state.db, document_id, user_id).await {
+        Ok(Some(document)) => (StatusCode::OK, Json(document_response(document))).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Document not found"),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch document: {}", e),
+        ),
+    }
+}
+
+pub async fn update_document(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(document_id): Path<Uuid>,
+    Json(payload): Json<UpdateDocumentRequest>,
+) -> impl IntoResponse {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {}", errors),
+        );
+    }
+
+    let tags = match payload.tags.map(DocumentService::normalize_tags).transpose() {
+        Ok(tags) => tags,
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
+        }
+    };
+    // An empty folder normalizes to None, taking the document out of its folder
+    let folder = match payload
+        .folder
+        .map(|folder| DocumentService::normalize_folder(Some(folder)))
+        .transpose()
     {
-        Ok(emb) => emb,
+        Ok(folder) => folder,
         Err(e) => {
-            return (
+            return error_response(StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
+        }
+    };
+
+    let document = match DocumentService::get_document_by_id(&state.db, document_id, user_id).await
+    {
+        Ok(Some(document)) => document,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Document not found"),
+        Err(e) => {
+            return error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to generate query embedding: {}", e),
-                }),
+                format!("Failed to fetch document: {}", e),
             )
-                .into_response();
         }
     };
 
-    // Parse document_id if provided
-    let document_id = if let Some(doc_id_str) = payload.document_id {
-        match Uuid::parse_str(&doc_id_str) {
-            Ok(id) => Some(id),
+    match DocumentService::update_document(
+        &state.db,
+        &state.vector_db,
+        document,
+        payload.title,
+        tags,
+        folder,
+    )
+    .await
+    {
+        Ok(document) => (StatusCode::OK, Json(document_response(document))).into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update document: {}", e),
+        ),
+    }
+}
+
+pub async fn delete_document(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(document_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match DocumentService::get_document_by_id(&state.db, document_id, user_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Document not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch document: {}", e),
+            )
+        }
+    }
+
+    match DocumentService::delete_document(
+        &state.db,
+        &state.vector_db,
+        state.blob_store.as_ref(),
+        document_id,
+        user_id,
+    )
+    .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete document: {}", e),
+        ),
+    }
+}
+
+/// Throw away a document's chunks and vectors and run ingestion again
+pub async fn reprocess_document(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(document_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let document = match DocumentService::get_document_by_id(&state.db, document_id, user_id).await
+    {
+        Ok(Some(document)) => document,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Document not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch document: {}", e),
+            )
+        }
+    };
+
+    match DocumentService::reprocess_document(&state.db, &state.vector_db, document).await {
+        Ok(document) => (StatusCode::ACCEPTED, Json(document_response(document))).into_response(),
+        Err(e) if e.is::<AlreadyProcessing>() => {
+            error_response(StatusCode::CONFLICT, "Document is already being processed")
+        }
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to reprocess document: {}", e),
+        ),
+    }
+}
+
+/// Validate and run a search, answering with the response body or the error to send
+async fn run_search(
+    state: &AppState,
+    user_id: Uuid,
+    payload: SearchRequest,
+) -> Result<SearchResponse, Response> {
+    if payload
+        .mmr_lambda
+        .is_some_and(|lambda| !(0.0..=1.0).contains(&lambda))
+    {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Validation error: mmr_lambda must be between 0 and 1",
+        ));
+    }
+
+    let reranked = payload.rerank && state.reranker.is_enabled();
+    if payload.min_score.is_some() && !payload.mode.supports_min_score(reranked) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Validation error: min_score needs rerank in hybrid mode, as fused scores only reflect rank",
+        ));
+    }
+
+    if payload.context_chunks > MAX_CONTEXT_CHUNKS {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Validation error: context_chunks must be at most {}",
+                MAX_CONTEXT_CHUNKS
+            ),
+        ));
+    }
+
+    if let (Some(from), Some(to)) = (payload.page_from, payload.page_to) {
+        if from > to {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "Validation error: page_from must not be after page_to",
+            ));
+        }
+    }
+
+    if let (Some(after), Some(before)) = (payload.uploaded_after, payload.uploaded_before) {
+        if after > before {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "Validation error: uploaded_after must not be after uploaded_before",
+            ));
+        }
+    }
+
+    // A cursor from a previous page takes precedence over an explicit offset
+    let offset = match &payload.cursor {
+        Some(cursor) => match SearchService::decode_cursor(cursor) {
+            Some(offset) => offset,
+            None => return Err(error_response(StatusCode::BAD_REQUEST, "Invalid cursor")),
+        },
+        None => payload.offset,
+    };
+
+    if payload.limit == 0 {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Validation error: limit must be at least 1",
+        ));
+    }
+
+    if offset
+        .checked_add(payload.limit)
+        .is_none_or(|depth| depth > MAX_SEARCH_DEPTH)
+    {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Validation error: offset + limit must be at most {}",
+                MAX_SEARCH_DEPTH
+            ),
+        ));
+    }
+
+    // Parse document_id and document_ids if provided
+    let mut document_ids = Vec::new();
+    for doc_id_str in payload.document_id.iter().chain(&payload.document_ids) {
+        match Uuid::parse_str(doc_id_str) {
+            Ok(id) if !document_ids.contains(&id) => document_ids.push(id),
+            Ok(_) => {}
             Err(_) => {
-                return (
+                return Err((
                     StatusCode::BAD_REQUEST,
                     Json(ErrorResponse {
                         error: "Invalid document_id format".to_string(),
                     }),
                 )
-                    .into_response();
+                    .into_response());
             }
         }
-    } else {
-        None
+    }
+
+    // Requested documents must belong to the caller
+    verify_document_access(state, user_id, &document_ids).await?;
+
+    let folders = match payload
+        .folders
+        .into_iter()
+        .filter_map(|folder| DocumentService::normalize_folder(Some(folder)).transpose())
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(folders) => folders,
+        Err(e) => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!("Validation error: {}", e),
+            ))
+        }
     };
 
-    // Search in vector database
-    match state
-        .vector_db
-        .search(query_embedding, payload.limit, document_id)
-        .await
+    // Search in the requested mode
+    match SearchService::search(
+        state,
+        &payload.query,
+        &SearchOptions {
+            mode: payload.mode,
+            limit: payload.limit,
+            rerank: payload.rerank,
+            mmr_lambda: payload.mmr_lambda,
+            collapse_adjacent: payload.collapse_adjacent,
+            context_chunks: payload.context_chunks,
+            min_score: payload.min_score,
+            offset,
+        },
+        user_id,
+        &SearchFilter {
+            document_ids,
+            section: payload.section,
+            tags: payload.tags,
+            folders,
+            uploaded_after: payload.uploaded_after.map(|t| t.naive_utc()),
+            uploaded_before: payload.uploaded_before.map(|t| t.naive_utc()),
+            page_from: payload.page_from,
+            page_to: payload.page_to,
+        },
+    )
+    .await
     {
         Ok(results) => {
-            let response = SearchResponse {
+            // A full page may have more behind it, up to the deepest we page
+            let next_offset = offset + payload.limit;
+            let next_cursor = (results.len() as u64 == payload.limit
+                && next_offset < MAX_SEARCH_DEPTH)
+                .then(|| SearchService::encode_cursor(next_offset));
+
+            let terms = HighlightService::query_terms(&payload.query);
+
+            Ok(SearchResponse {
                 results: results
                     .into_iter()
-                    .map(|r| SearchResultItem {
-                        document_id: r.document_id,
-                        chunk_id: r.chunk_id,
-                        content: r.content,
-                        score: r.score,
-                    })
+                    .map(|r| search_result_item(r, &terms))
                     .collect(),
+                next_cursor,
+            })
+        }
+        Err(e) => Err(retrieval_error(e, "Search failed")),
+    }
+}
+
+/// Remember a first-page search in the user's history; later pages of the same search
+/// aren't recorded again. Failing to record doesn't fail the search.
+async fn record_search(
+    state: &AppState,
+    user_id: Uuid,
+    request: &SearchRequest,
+    response: &SearchResponse,
+) {
+    if request.offset > 0 || request.cursor.is_some() {
+        return;
+    }
+
+    let chunk_ids: Vec<String> = response.results.iter().map(|r| r.chunk_id.clone()).collect();
+    let recorded = match serde_json::to_value(request) {
+        Ok(value) => {
+            SearchHistoryService::record(
+                &state.db,
+                user_id,
+                request.query.clone(),
+                value,
+                &chunk_ids,
+            )
+            .await
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record search history: {}", e);
+    }
+}
+
+pub async fn search_documents(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<SearchRequest>,
+) -> impl IntoResponse {
+    match run_search(&state, user_id, payload.clone()).await {
+        Ok(response) => {
+            record_search(&state, user_id, &payload, &response).await;
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+fn search_history_response(entry: search_history::Model) -> SearchHistoryEntryResponse {
+    SearchHistoryEntryResponse {
+        chunk_ids: SearchHistoryService::chunk_ids(&entry),
+        id: entry.id.to_string(),
+        query: entry.query,
+        request: entry.request,
+        pinned: entry.pinned,
+        name: entry.name,
+        created_at: entry.created_at.to_string(),
+    }
+}
+
+/// The caller's past searches, newest first; `pinned=true` lists saved searches only
+pub async fn get_search_history(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(params): Query<SearchHistoryQuery>,
+) -> impl IntoResponse {
+    if params.limit == 0 || params.limit > MAX_HISTORY_PAGE {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Validation error: limit must be between 1 and {}",
+                MAX_HISTORY_PAGE
+            ),
+        );
+    }
+
+    match SearchHistoryService::get_user_history(&state.db, user_id, params.pinned, params.limit)
+        .await
+    {
+        Ok(entries) => {
+            let response = SearchHistoryResponse {
+                entries: entries.into_iter().map(search_history_response).collect(),
             };
 
             (StatusCode::OK, Json(response)).into_response()
         }
-        Err(e) => (
+        Err(e) => error_response(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Search failed: {}", e),
-            }),
-        )
-            .into_response(),
+            format!("Failed to fetch search history: {}", e),
+        ),
+    }
+}
+
+/// Forget past searches, keeping saved searches unless `include_pinned=true`
+pub async fn clear_search_history(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(params): Query<ClearSearchHistoryQuery>,
+) -> impl IntoResponse {
+    match SearchHistoryService::clear_history(&state.db, user_id, params.include_pinned).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to clear search history: {}", e),
+        ),
+    }
+}
+
+/// Pin a past search as a saved search, or unpin or rename it
+pub async fn update_search_history_entry(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(entry_id): Path<Uuid>,
+    Json(payload): Json<UpdateSearchHistoryRequest>,
+) -> impl IntoResponse {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {}", errors),
+        );
+    }
+
+    let entry = match SearchHistoryService::get_entry_by_id(&state.db, entry_id, user_id).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Search not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch search: {}", e),
+            )
+        }
+    };
+
+    // An empty name removes it
+    let name = payload
+        .name
+        .map(|name| Some(name.trim().to_string()).filter(|name| !name.is_empty()));
+
+    match SearchHistoryService::update_entry(&state.db, entry, payload.pinned, name).await {
+        Ok(entry) => (StatusCode::OK, Json(search_history_response(entry))).into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update search: {}", e),
+        ),
+    }
+}
+
+pub async fn delete_search_history_entry(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(entry_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let entry = match SearchHistoryService::get_entry_by_id(&state.db, entry_id, user_id).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Search not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch search: {}", e),
+            )
+        }
+    };
+
+    match SearchHistoryService::delete_entry(&state.db, entry).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete search: {}", e),
+        ),
+    }
+}
+
+/// Run a past or saved search again against the current documents
+pub async fn rerun_search(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(entry_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let entry = match SearchHistoryService::get_entry_by_id(&state.db, entry_id, user_id).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Search not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch search: {}", e),
+            )
+        }
+    };
+
+    let request: SearchRequest = match serde_json::from_value(entry.request) {
+        Ok(request) => request,
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read saved search: {}", e),
+            )
+        }
+    };
+
+    match run_search(&state, user_id, request.clone()).await {
+        Ok(response) => {
+            record_search(&state, user_id, &request, &response).await;
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Progress of the search index for the configured embedding model. Every user shares the
+/// index, so this only says whether search is usable and how far a re-index has come.
+pub async fn get_search_index(State(state): State<AppState>) -> impl IntoResponse {
+    match ReindexService::status(&state.db, &state.vector_db).await {
+        Ok(Some(index)) => {
+            let progress = match index.total_chunks {
+                0 => 100.0,
+                total => index.indexed_chunks as f32 * 100.0 / total as f32,
+            };
+            let response = SearchIndexResponse {
+                status: index.status,
+                progress,
+                ready: state.vector_db.is_ready(),
+                updated_at: index.updated_at.to_string(),
+            };
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Search index not found"),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch search index: {}", e),
+        ),
+    }
+}
+
+pub fn ask_response(answer: RagAnswer) -> AskResponse {
+    let citation = |number: usize| {
+        answer
+            .sources
+            .iter()
+            .find(|s| s.number == number)
+            .map(|s| CitationResponse {
+                source: s.number,
+                chunk_id: s.chunk_id.clone(),
+                document_id: s.document_id.clone(),
+                page: s.page,
+            })
+    };
+
+    AskResponse {
+        sentences: answer
+            .sentences
+            .iter()
+            .map(|sentence| AnswerSentenceResponse {
+                text: sentence.text.clone(),
+                citations: sentence.sources.iter().filter_map(|n| citation(*n)).collect(),
+            })
+            .collect(),
+        sources: answer
+            .sources
+            .iter()
+            .map(|s| SourceResponse {
+                number: s.number,
+                chunk_id: s.chunk_id.clone(),
+                document_id: s.document_id.clone(),
+                document_title: s.document_title.clone(),
+                page: s.page,
+                section_path: s.section_path.clone(),
+                content: s.content.clone(),
+                score: s.score,
+            })
+            .collect(),
+        answer: answer.answer,
+        usage: answer.usage,
     }
+}
+
+pub async fn ask_documents(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<AskRequest>,
+) -> impl IntoResponse {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {}", errors),
+        );
+    }
+
+    // Parse document_id if provided
+    let document_id = match payload.document_id.as_deref().map(Uuid::parse_str) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => {
+            return error_response(StatusCode::BAD_REQUEST, "Invalid document_id format")
+        }
+        None => None,
+    };
+
+    // Requested document must belong to the caller
+    if let Err(response) = verify_document_access(&state, user_id, document_id.as_slice()).await {
+        return response;
+    }
+
+    match RagService::answer(
+        &state,
+        user_id,
+        &payload.question,
+        payload.limit,
+        document_id.as_slice(),
+    )
+    .await
+    {
+        Ok(answer) => (StatusCode::OK, Json(ask_response(answer))).into_response(),
+        Err(e) => retrieval_error(e, "Failed to answer question"),
+    }
+}
+
+/// Logs when the client goes away mid-answer. The upstream LLM request lives
+/// inside the response stream, so it is cancelled when that stream is dropped.
+struct UpstreamGuard {
+    finished: bool,
+}
+
+impl Drop for UpstreamGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            tracing::info!("Client disconnected, cancelled upstream LLM request");
+        }
+    }
+}
+
+pub async fn ask_documents_stream(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<AskRequest>,
+) -> impl IntoResponse {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {}", errors),
+        );
+    }
+
+    // Parse document_id if provided
+    let document_id = match payload.document_id.as_deref().map(Uuid::parse_str) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => {
+            return error_response(StatusCode::BAD_REQUEST, "Invalid document_id format")
+        }
+        None => None,
+    };
+
+    // Requested document must belong to the caller
+    if let Err(response) = verify_document_access(&state, user_id, document_id.as_slice()).await {
+        return response;
+    }
+
+    // Retrieve before opening the stream so failures still get a proper status code
+    let sources = match RagService::retrieve(
+        &state,
+        user_id,
+        &payload.question,
+        payload.limit,
+        document_id.as_slice(),
+    )
+    .await
+    {
+        Ok(sources) => sources,
+        Err(e) => return retrieval_error(e, "Failed to retrieve sources"),
+    };
+
+    let llm = state.llm.clone();
+    let question = payload.question;
+
+    // Events: "token" per generated piece, then "done" with citations and usage, or "error"
+    let events = async_stream::stream! {
+        let mut guard = UpstreamGuard { finished: false };
+        let mut answer = String::new();
+        let mut usage = None;
+
+        if sources.is_empty() {
+            answer.push_str(NO_SOURCES_ANSWER);
+            yield Event::default()
+                .event("token")
+                .json_data(serde_json::json!({ "text": NO_SOURCES_ANSWER }));
+        } else {
+            let messages = RagService::build_messages(&[], &question, &sources);
+            let mut upstream = match llm.stream(messages).await {
+                Ok(upstream) => upstream,
+                Err(e) => {
+                    guard.finished = true;
+                    yield Event::default().event("error").json_data(ErrorResponse {
+                        error: format!("Failed to generate answer: {}", e),
+                    });
+                    return;
+                }
+            };
+
+            while let Some(event) = upstream.next().await {
+                match event {
+                    Ok(StreamEvent::Token(text)) => {
+                        answer.push_str(&text);
+                        yield Event::default()
+                            .event("token")
+                            .json_data(serde_json::json!({ "text": text }));
+                    }
+                    Ok(StreamEvent::Usage(u)) => usage = Some(u),
+                    Err(e) => {
+                        guard.finished = true;
+                        yield Event::default().event("error").json_data(ErrorResponse {
+                            error: format!("Failed to generate answer: {}", e),
+                        });
+                        return;
+                    }
+                }
+            }
+        }
+
+        guard.finished = true;
+        let response = ask_response(RagService::finish(answer, sources, usage));
+        yield Event::default().event("done").json_data(response);
+    };
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
 }
\ No newline at end of file
@@ -1,2 +1,30 @@
+use axum::{
+    http::StatusCode,
+    Json,
+    response::{IntoResponse, Response},
+};
+
+use crate::dto::auth::ErrorResponse;
+use crate::services::vector_db::IndexUnavailable;
+
 pub mod auth;
-pub mod document;
\ No newline at end of file
+pub mod conversation;
+pub mod document;
+pub mod quiz;
+
+/// Build a JSON error response with the given status
+pub fn error_response(status: StatusCode, error: impl Into<String>) -> Response {
+    (status, Json(ErrorResponse { error: error.into() })).into_response()
+}
+
+/// Error response for a failed retrieval: 503 while the search index is being
+/// rebuilt for a new embedding model, 500 otherwise
+pub fn retrieval_error(error: anyhow::Error, context: &str) -> Response {
+    if error.downcast_ref::<IndexUnavailable>().is_some() {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, error.to_string());
+    }
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("{}: {}", context, error),
+    )
+}
\ No newline at end of file
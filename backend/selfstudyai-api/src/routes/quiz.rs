@@ -0,0 +1,301 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::dto::quiz::{
+    AttemptListResponse, AttemptResponse, AttemptSummaryResponse, CreateQuizRequest,
+    QuestionResultResponse, QuestionViewKind, QuizListQuery, QuizListResponse,
+    QuizQuestionResponse, QuizResponse, QuizSummaryResponse, SubmitAttemptRequest,
+};
+use crate::entities::question::QuestionKind;
+use crate::entities::quiz;
+use crate::routes::error_response;
+use crate::services::document::DocumentService;
+use crate::services::quiz::QuizService;
+use crate::AppState;
+
+fn quiz_response(quiz: quiz::Model) -> QuizResponse {
+    let questions = quiz
+        .questions
+        .questions
+        .into_iter()
+        .enumerate()
+        .map(|(index, q)| QuizQuestionResponse {
+            index,
+            kind: match q.kind {
+                QuestionKind::MultipleChoice { options, .. } => {
+                    QuestionViewKind::MultipleChoice { options }
+                }
+                QuestionKind::TrueFalse { .. } => QuestionViewKind::TrueFalse,
+                QuestionKind::MultiSelect { options, .. } => {
+                    QuestionViewKind::MultiSelect { options }
+                }
+                QuestionKind::ShortAnswer { .. } => QuestionViewKind::ShortAnswer,
+                QuestionKind::Cloze { blanks } => QuestionViewKind::Cloze {
+                    blank_count: blanks.len(),
+                },
+                QuestionKind::Ordering { items, .. } => QuestionViewKind::Ordering { items },
+            },
+            prompt: q.prompt,
+        })
+        .collect();
+
+    QuizResponse {
+        id: quiz.id.to_string(),
+        document_id: quiz.document_id.to_string(),
+        title: quiz.title,
+        total_questions: quiz.total_questions,
+        questions,
+        created_at: quiz.created_at.to_string(),
+    }
+}
+
+pub async fn create_quiz(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<CreateQuizRequest>,
+) -> impl IntoResponse {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {}", errors),
+        );
+    }
+
+    let Ok(document_id) = Uuid::parse_str(&payload.document_id) else {
+        return error_response(StatusCode::BAD_REQUEST, "Invalid document_id format");
+    };
+
+    // Verify ownership and that the document has been processed
+    let document = match DocumentService::get_document_by_id(&state.db, document_id, user_id).await
+    {
+        Ok(Some(doc)) => doc,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Document not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch document: {}", e),
+            )
+        }
+    };
+
+    if document.processing_status != "completed" {
+        return error_response(
+            StatusCode::CONFLICT,
+            "Document has not finished processing yet",
+        );
+    }
+
+    let questions =
+        match QuizService::generate_questions(&state.db, document.id, payload.num_questions).await
+        {
+            Ok(questions) if questions.is_empty() => {
+                return error_response(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Not enough content in document to generate a quiz",
+                )
+            }
+            Ok(questions) => questions,
+            Err(e) => {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to generate quiz: {}", e),
+                )
+            }
+        };
+
+    match QuizService::create_quiz(&state.db, user_id, &document, payload.title, questions).await {
+        Ok(quiz) => (StatusCode::CREATED, Json(quiz_response(quiz))).into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create quiz: {}", e),
+        ),
+    }
+}
+
+pub async fn get_quizzes(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(params): Query<QuizListQuery>,
+) -> impl IntoResponse {
+    // Parse document_id if provided
+    let document_id = match params.document_id.as_deref().map(Uuid::parse_str) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => {
+            return error_response(StatusCode::BAD_REQUEST, "Invalid document_id format")
+        }
+        None => None,
+    };
+
+    match QuizService::get_user_quizzes(&state.db, user_id, document_id).await {
+        Ok(quizzes) => {
+            let response = QuizListResponse {
+                quizzes: quizzes
+                    .into_iter()
+                    .map(|quiz| QuizSummaryResponse {
+                        id: quiz.id.to_string(),
+                        document_id: quiz.document_id.to_string(),
+                        title: quiz.title,
+                        total_questions: quiz.total_questions,
+                        created_at: quiz.created_at.to_string(),
+                    })
+                    .collect(),
+            };
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch quizzes: {}", e),
+        ),
+    }
+}
+
+pub async fn get_quiz(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(quiz_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match QuizService::get_quiz_by_id(&state.db, quiz_id, user_id).await {
+        Ok(Some(quiz)) => (StatusCode::OK, Json(quiz_response(quiz))).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Quiz not found"),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch quiz: {}", e),
+        ),
+    }
+}
+
+pub async fn delete_quiz(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(quiz_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let quiz = match QuizService::get_quiz_by_id(&state.db, quiz_id, user_id).await {
+        Ok(Some(quiz)) => quiz,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Quiz not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch quiz: {}", e),
+            )
+        }
+    };
+
+    match QuizService::delete_quiz(&state.db, quiz).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete quiz: {}", e),
+        ),
+    }
+}
+
+pub async fn submit_attempt(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(quiz_id): Path<Uuid>,
+    Json(payload): Json<SubmitAttemptRequest>,
+) -> impl IntoResponse {
+    let quiz = match QuizService::get_quiz_by_id(&state.db, quiz_id, user_id).await {
+        Ok(Some(quiz)) => quiz,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Quiz not found"),
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch quiz: {}", e),
+            )
+        }
+    };
+
+    let questions = &quiz.questions.questions;
+
+    if payload.answers.len() != questions.len() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Expected {} answers, got {}",
+                questions.len(),
+                payload.answers.len()
+            ),
+        );
+    }
+
+    // Reject answers that don't fit their question before grading
+    for (index, (question, answer)) in questions.iter().zip(&payload.answers).enumerate() {
+        if let Some(answer) = answer {
+            if let Err(e) = question.validate_answer(answer) {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid answer {}: {}", index, e),
+                );
+            }
+        }
+    }
+
+    match QuizService::submit_attempt(&state.db, &quiz, user_id, payload.answers).await {
+        Ok(attempt) => {
+            let response = AttemptResponse {
+                id: attempt.id.to_string(),
+                quiz_id: attempt.quiz_id.to_string(),
+                score: attempt.score,
+                total_questions: attempt.total_questions,
+                results: attempt
+                    .answers
+                    .answers
+                    .into_iter()
+                    .zip(&quiz.questions.questions)
+                    .map(|(graded, question)| QuestionResultResponse {
+                        question_index: graded.question_index,
+                        answer: graded.answer,
+                        correct_answer: question.solution(),
+                        is_correct: graded.is_correct,
+                        explanation: question.explanation.clone(),
+                    })
+                    .collect(),
+                completed_at: attempt.completed_at.to_string(),
+            };
+
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to submit attempt: {}", e),
+        ),
+    }
+}
+
+pub async fn get_attempts(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(quiz_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match QuizService::get_attempts(&state.db, quiz_id, user_id).await {
+        Ok(attempts) => {
+            let response = AttemptListResponse {
+                attempts: attempts
+                    .into_iter()
+                    .map(|a| AttemptSummaryResponse {
+                        id: a.id.to_string(),
+                        quiz_id: a.quiz_id.to_string(),
+                        score: a.score,
+                        total_questions: a.total_questions,
+                        completed_at: a.completed_at.to_string(),
+                    })
+                    .collect(),
+            };
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch attempts: {}", e),
+        ),
+    }
+}
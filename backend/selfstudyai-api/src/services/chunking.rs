@@ -0,0 +1,1148 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use sea_orm::FromJsonQueryResult;
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+
+use crate::services::embeddings::EmbeddingsService;
+use crate::services::pdf::{ExtractedText, Heading, SECTION_SEPARATOR};
+
+/// Abbreviations whose trailing period doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "e.g.", "i.e.", "etc.", "vs.", "cf.", "fig.", "figs.", "eq.", "eqs.", "no.", "vol.", "ch.",
+    "sec.", "p.", "pp.", "dr.", "mr.", "mrs.", "ms.", "prof.", "st.", "approx.",
+];
+
+/// Tried in order by the recursive-character chunker: paragraphs, lines, sentences, words
+const SEPARATORS: &[&str] = &["\n\n", "\n", ". ", " "];
+
+const MAX_CHUNK_WORDS: usize = 2_000;
+const MAX_CHUNK_CHARS: usize = 20_000;
+const MAX_CHUNK_TOKENS: usize = 8_192;
+
+const TOKENIZER_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const TOKENIZER_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A piece of text to embed, with its `[char_start, char_end)` range in the source text
+#[derive(Debug, Clone)]
+pub struct TextChunk {
+    pub content: String,
+    pub char_start: usize,
+    pub char_end: usize,
+    pub section_path: Option<String>,
+}
+
+/// Splits extracted text into chunks for embedding
+pub trait Chunker: Send + Sync {
+    fn chunk(&self, text: &ExtractedText) -> Result<Vec<TextChunk>>;
+}
+
+/// How a document's text is chunked. Stored on the document so reprocessing
+/// produces the same chunks; missing sizes take the strategy's defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromJsonQueryResult)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ChunkingConfig {
+    /// Sentences packed within sections from the PDF outline or detected headings
+    Structured {
+        #[serde(default = "default_max_words")]
+        max_words: usize,
+        #[serde(default = "default_overlap_words")]
+        overlap_words: usize,
+    },
+    /// Fixed windows of words
+    WordWindow {
+        #[serde(default = "default_max_words")]
+        chunk_size: usize,
+        #[serde(default = "default_overlap_words")]
+        overlap: usize,
+    },
+    /// Sentences packed regardless of sections
+    Sentence {
+        #[serde(default = "default_max_words")]
+        max_words: usize,
+        #[serde(default = "default_overlap_words")]
+        overlap_words: usize,
+    },
+    /// Whole paragraphs packed together, only oversized ones are split
+    Paragraph {
+        #[serde(default = "default_max_words")]
+        max_words: usize,
+    },
+    /// Split on paragraphs, then lines, sentences and words until pieces fit; sizes in characters
+    RecursiveCharacter {
+        #[serde(default = "default_chunk_chars")]
+        chunk_size: usize,
+        #[serde(default = "default_overlap_chars")]
+        overlap: usize,
+    },
+    /// Windows of tokens from the embedding model's own tokenizer
+    Token {
+        #[serde(default = "default_max_tokens")]
+        max_tokens: usize,
+        #[serde(default = "default_overlap_tokens")]
+        overlap_tokens: usize,
+    },
+}
+
+fn default_max_words() -> usize {
+    500
+}
+
+fn default_overlap_words() -> usize {
+    50
+}
+
+fn default_chunk_chars() -> usize {
+    2_000
+}
+
+fn default_overlap_chars() -> usize {
+    200
+}
+
+fn default_max_tokens() -> usize {
+    256
+}
+
+fn default_overlap_tokens() -> usize {
+    32
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        ChunkingConfig::Structured {
+            max_words: default_max_words(),
+            overlap_words: default_overlap_words(),
+        }
+    }
+}
+
+impl ChunkingConfig {
+    /// Check sizes are usable: positive, within limits, and larger than their overlap
+    pub fn validate(&self) -> Result<(), String> {
+        let (size, overlap, max, unit) = match *self {
+            ChunkingConfig::Structured {
+                max_words,
+                overlap_words,
+            }
+            | ChunkingConfig::Sentence {
+                max_words,
+                overlap_words,
+            } => (max_words, overlap_words, MAX_CHUNK_WORDS, "words"),
+            ChunkingConfig::WordWindow {
+                chunk_size,
+                overlap,
+            } => (chunk_size, overlap, MAX_CHUNK_WORDS, "words"),
+            ChunkingConfig::Paragraph { max_words } => (max_words, 0, MAX_CHUNK_WORDS, "words"),
+            ChunkingConfig::RecursiveCharacter {
+                chunk_size,
+                overlap,
+            } => (chunk_size, overlap, MAX_CHUNK_CHARS, "characters"),
+            ChunkingConfig::Token {
+                max_tokens,
+                overlap_tokens,
+            } => (max_tokens, overlap_tokens, MAX_CHUNK_TOKENS, "tokens"),
+        };
+
+        if size == 0 || size > max {
+            return Err(format!("Chunk size must be between 1 and {} {}", max, unit));
+        }
+        if overlap >= size {
+            return Err("Chunk overlap must be smaller than the chunk size".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether building the chunker needs the embedding model's tokenizer
+    pub fn needs_tokenizer(&self) -> bool {
+        matches!(self, ChunkingConfig::Token { .. })
+    }
+
+    pub fn chunker(&self, tokenizer: Option<Arc<Tokenizer>>) -> Result<Box<dyn Chunker>> {
+        let chunker: Box<dyn Chunker> = match *self {
+            ChunkingConfig::Structured {
+                max_words,
+                overlap_words,
+            } => Box::new(StructuredChunker {
+                max_words,
+                overlap_words,
+            }),
+            ChunkingConfig::WordWindow {
+                chunk_size,
+                overlap,
+            } => Box::new(WordWindowChunker {
+                chunk_size,
+                overlap,
+            }),
+            ChunkingConfig::Sentence {
+                max_words,
+                overlap_words,
+            } => Box::new(SentenceChunker {
+                max_words,
+                overlap_words,
+            }),
+            ChunkingConfig::Paragraph { max_words } => Box::new(ParagraphChunker { max_words }),
+            ChunkingConfig::RecursiveCharacter {
+                chunk_size,
+                overlap,
+            } => Box::new(RecursiveCharacterChunker {
+                chunk_size,
+                overlap,
+            }),
+            ChunkingConfig::Token {
+                max_tokens,
+                overlap_tokens,
+            } => Box::new(TokenChunker {
+                tokenizer: tokenizer.context("Token chunking needs a tokenizer")?,
+                max_tokens,
+                overlap_tokens,
+            }),
+        };
+        Ok(chunker)
+    }
+}
+
+pub struct StructuredChunker {
+    pub max_words: usize,
+    pub overlap_words: usize,
+}
+
+impl Chunker for StructuredChunker {
+    fn chunk(&self, extracted: &ExtractedText) -> Result<Vec<TextChunk>> {
+        let words = word_spans(&extracted.text);
+        let mut chunks = Vec::new();
+
+        for (range, section_path) in section_ranges(&extracted.headings, &words) {
+            let sentences = sentence_ranges(&extracted.text, &words, range);
+            chunks.extend(pack(
+                &words,
+                &sentences,
+                self.max_words,
+                self.overlap_words,
+                section_path,
+            ));
+        }
+
+        Ok(chunks)
+    }
+}
+
+pub struct WordWindowChunker {
+    pub chunk_size: usize,
+    pub overlap: usize,
+}
+
+impl Chunker for WordWindowChunker {
+    fn chunk(&self, extracted: &ExtractedText) -> Result<Vec<TextChunk>> {
+        let words = word_spans(&extracted.text);
+        Ok(word_windows(
+            &words,
+            0..words.len(),
+            self.chunk_size,
+            self.overlap,
+            None,
+        ))
+    }
+}
+
+pub struct SentenceChunker {
+    pub max_words: usize,
+    pub overlap_words: usize,
+}
+
+impl Chunker for SentenceChunker {
+    fn chunk(&self, extracted: &ExtractedText) -> Result<Vec<TextChunk>> {
+        let words = word_spans(&extracted.text);
+        let sentences = sentence_ranges(&extracted.text, &words, 0..words.len());
+        Ok(pack(
+            &words,
+            &sentences,
+            self.max_words,
+            self.overlap_words,
+            None,
+        ))
+    }
+}
+
+pub struct ParagraphChunker {
+    pub max_words: usize,
+}
+
+impl Chunker for ParagraphChunker {
+    fn chunk(&self, extracted: &ExtractedText) -> Result<Vec<TextChunk>> {
+        let words = word_spans(&extracted.text);
+
+        // Oversized paragraphs fall back to their sentences
+        let mut units = Vec::new();
+        for paragraph in paragraph_ranges(&extracted.text, &words) {
+            if paragraph.len() > self.max_words {
+                units.extend(sentence_ranges(&extracted.text, &words, paragraph));
+            } else {
+                units.push(paragraph);
+            }
+        }
+
+        Ok(pack(&words, &units, self.max_words, 0, None))
+    }
+}
+
+pub struct RecursiveCharacterChunker {
+    pub chunk_size: usize,
+    pub overlap: usize,
+}
+
+impl Chunker for RecursiveCharacterChunker {
+    fn chunk(&self, extracted: &ExtractedText) -> Result<Vec<TextChunk>> {
+        let text = &extracted.text;
+        let offsets = CharOffsets::new(text);
+
+        let mut pieces = Vec::new();
+        split_recursive(
+            text,
+            0..text.len(),
+            SEPARATORS,
+            self.chunk_size,
+            &mut pieces,
+        );
+        let sizes: Vec<usize> = pieces
+            .iter()
+            .map(|p| text[p.clone()].chars().count())
+            .collect();
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+
+        while start < pieces.len() {
+            // Take pieces while they fit, always at least one
+            let mut end = start;
+            let mut size = 0;
+            while end < pieces.len() && (end == start || size + sizes[end] <= self.chunk_size) {
+                size += sizes[end];
+                end += 1;
+            }
+
+            let range = pieces[start].start..pieces[end - 1].end;
+            chunks.extend(chunk_from_bytes(text, &offsets, range));
+
+            if end >= pieces.len() {
+                break;
+            }
+
+            // Step back over trailing pieces that fit in the overlap
+            let mut next = end;
+            let mut overlap = 0;
+            while next > start + 1 && overlap + sizes[next - 1] <= self.overlap {
+                overlap += sizes[next - 1];
+                next -= 1;
+            }
+            start = next;
+        }
+
+        Ok(chunks)
+    }
+}
+
+pub struct TokenChunker {
+    pub tokenizer: Arc<Tokenizer>,
+    pub max_tokens: usize,
+    pub overlap_tokens: usize,
+}
+
+impl Chunker for TokenChunker {
+    fn chunk(&self, extracted: &ExtractedText) -> Result<Vec<TextChunk>> {
+        let text = &extracted.text;
+        let offsets = CharOffsets::new(text);
+
+        let encoding = self
+            .tokenizer
+            .encode(text.as_str(), false)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize text: {}", e))?;
+        let tokens = encoding.get_offsets(); // byte ranges in `text`
+
+        let mut chunks = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let end = (i + self.max_tokens).min(tokens.len());
+            chunks.extend(chunk_from_bytes(
+                text,
+                &offsets,
+                tokens[i].0..tokens[end - 1].1,
+            ));
+
+            if end >= tokens.len() {
+                break;
+            }
+            i += self.max_tokens - self.overlap_tokens;
+        }
+
+        Ok(chunks)
+    }
+}
+
+/// Tokenizers of embedding models, taken from the provider when it runs the model
+/// locally and otherwise fetched from the Hugging Face Hub on first use
+#[derive(Clone)]
+pub struct TokenizerCache {
+    client: Client,
+    tokenizers: Arc<Mutex<HashMap<String, Arc<Tokenizer>>>>,
+}
+
+impl TokenizerCache {
+    pub fn new() -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(TOKENIZER_CONNECT_TIMEOUT)
+            .timeout(TOKENIZER_DOWNLOAD_TIMEOUT)
+            .build()
+            .context("Failed to build tokenizer HTTP client")?;
+
+        Ok(Self {
+            client,
+            tokenizers: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub async fn get(&self, embeddings: &EmbeddingsService) -> Result<Arc<Tokenizer>> {
+        let model = embeddings.model();
+        if let Some(tokenizer) = self.tokenizers.lock().unwrap().get(model) {
+            return Ok(tokenizer.clone());
+        }
+
+        let mut tokenizer = match embeddings.tokenizer() {
+            Some(tokenizer) => (*tokenizer).clone(),
+            None => self.download(model).await?,
+        };
+
+        // We window the tokens ourselves, so keep every one of them
+        tokenizer
+            .with_truncation(None)
+            .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer: {}", e))?;
+        tokenizer.with_padding(None);
+
+        let tokenizer = Arc::new(tokenizer);
+        self.tokenizers
+            .lock()
+            .unwrap()
+            .insert(model.to_string(), tokenizer.clone());
+        Ok(tokenizer)
+    }
+
+    async fn download(&self, model: &str) -> Result<Tokenizer> {
+        let url = format!(
+            "https://huggingface.co/{}/resolve/main/tokenizer.json",
+            model
+        );
+        let bytes = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Failed to download tokenizer for {}", model))?
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to download tokenizer for {}", model))?;
+
+        Tokenizer::from_bytes(&bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to load tokenizer for {}: {}", model, e))
+    }
+}
+
+struct WordSpan<'a> {
+    text: &'a str,
+    char_start: usize,
+    char_end: usize,
+    byte_start: usize,
+    byte_end: usize,
+}
+
+/// Whitespace-separated words with their character and byte ranges
+fn word_spans(text: &str) -> Vec<WordSpan<'_>> {
+    let mut words = Vec::new();
+    let mut start: Option<(usize, usize)> = None; // (byte, char) where the current word began
+
+    for (char_index, (byte_index, c)) in text.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((byte_index, char_index)),
+            (true, Some((byte_start, char_start))) => {
+                words.push(WordSpan {
+                    text: &text[byte_start..byte_index],
+                    char_start,
+                    char_end: char_index,
+                    byte_start,
+                    byte_end: byte_index,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((byte_start, char_start)) = start {
+        words.push(WordSpan {
+            text: &text[byte_start..],
+            char_start,
+            char_end: char_start + text[byte_start..].chars().count(),
+            byte_start,
+            byte_end: text.len(),
+        });
+    }
+
+    words
+}
+
+/// Split words into one range per section, each with its heading path
+fn section_ranges(headings: &[Heading], words: &[WordSpan]) -> Vec<(Range<usize>, Option<String>)> {
+    let mut sections = Vec::new();
+    let mut stack: Vec<&Heading> = Vec::new();
+    let mut start = 0;
+    let mut path = None;
+
+    for heading in headings {
+        let boundary = words.partition_point(|w| w.char_start < heading.char_start);
+        if boundary > start {
+            sections.push((start..boundary, path.clone()));
+            start = boundary;
+        }
+
+        while stack.last().is_some_and(|h| h.level >= heading.level) {
+            stack.pop();
+        }
+        stack.push(heading);
+        path = Some(
+            stack
+                .iter()
+                .map(|h| h.title.as_str())
+                .collect::<Vec<_>>()
+                .join(SECTION_SEPARATOR),
+        );
+    }
+
+    if start < words.len() {
+        sections.push((start..words.len(), path));
+    }
+
+    sections
+}
+
+/// Whether the whitespace between two words contains a blank line
+fn is_paragraph_break(text: &str, word: &WordSpan, next: &WordSpan) -> bool {
+    text[word.byte_end..next.byte_start].matches('\n').count() >= 2
+}
+
+/// Split words into paragraphs at blank lines
+fn paragraph_ranges(text: &str, words: &[WordSpan]) -> Vec<Range<usize>> {
+    let mut paragraphs = Vec::new();
+    let mut start = 0;
+
+    for i in 1..words.len() {
+        if is_paragraph_break(text, &words[i - 1], &words[i]) {
+            paragraphs.push(start..i);
+            start = i;
+        }
+    }
+
+    if start < words.len() {
+        paragraphs.push(start..words.len());
+    }
+
+    paragraphs
+}
+
+/// Split a range of words into sentences, also breaking at blank lines
+fn sentence_ranges(text: &str, words: &[WordSpan], range: Range<usize>) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = range.start;
+
+    for i in range.start..range.end.saturating_sub(1) {
+        let (word, next) = (&words[i], &words[i + 1]);
+        if is_paragraph_break(text, word, next) || ends_sentence(word.text, next.text) {
+            sentences.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+
+    if start < range.end {
+        sentences.push(start..range.end);
+    }
+
+    sentences
+}
+
+fn ends_sentence(word: &str, next: &str) -> bool {
+    let stripped = word.trim_end_matches(['"', '\'', ')', ']', '”', '’']);
+    if !stripped.ends_with(['.', '?', '!']) {
+        return false;
+    }
+    if ABBREVIATIONS.contains(&stripped.to_lowercase().as_str()) {
+        return false;
+    }
+    // Initials like "J." in "J. Smith"
+    if stripped.len() == 2 && stripped.starts_with(char::is_uppercase) {
+        return false;
+    }
+    next.chars()
+        .find(|c| c.is_alphanumeric())
+        .is_some_and(|c| c.is_uppercase() || c.is_ascii_digit())
+}
+
+/// Pack consecutive units (sentences, paragraphs) into chunks of up to `max_words`,
+/// overlapping by whole units of up to `overlap_words`. Units longer than
+/// `max_words` are split into word windows.
+fn pack(
+    words: &[WordSpan],
+    units: &[Range<usize>],
+    max_words: usize,
+    overlap_words: usize,
+    section_path: Option<String>,
+) -> Vec<TextChunk> {
+    let mut chunks = Vec::new();
+    let mut start = 0; // index into `units` of the current chunk's first unit
+
+    while start < units.len() {
+        let first = &units[start];
+
+        if first.len() > max_words {
+            chunks.extend(word_windows(
+                words,
+                first.clone(),
+                max_words,
+                overlap_words,
+                section_path.clone(),
+            ));
+            start += 1;
+            continue;
+        }
+
+        // Take units while they fit
+        let mut end = start;
+        let mut count = 0;
+        while end < units.len() && count + units[end].len() <= max_words {
+            count += units[end].len();
+            end += 1;
+        }
+
+        let range = units[start].start..units[end - 1].end;
+        chunks.push(chunk_from(words, range, section_path.clone()));
+
+        if end >= units.len() {
+            break;
+        }
+
+        // Step back over trailing units that fit in the overlap
+        let mut next = end;
+        let mut overlap = 0;
+        while next > start + 1 && overlap + units[next - 1].len() <= overlap_words {
+            overlap += units[next - 1].len();
+            next -= 1;
+        }
+        start = next;
+    }
+
+    chunks
+}
+
+/// Fixed-size word windows over a range of words
+fn word_windows(
+    words: &[WordSpan],
+    range: Range<usize>,
+    chunk_size: usize,
+    overlap: usize,
+    section_path: Option<String>,
+) -> Vec<TextChunk> {
+    let mut chunks = Vec::new();
+
+    let mut i = range.start;
+    while i < range.end {
+        let end = (i + chunk_size).min(range.end);
+        chunks.push(chunk_from(words, i..end, section_path.clone()));
+
+        // Move forward by chunk_size - overlap
+        if end >= range.end {
+            break;
+        }
+        i += chunk_size.saturating_sub(overlap).max(1);
+    }
+
+    chunks
+}
+
+fn chunk_from(words: &[WordSpan], range: Range<usize>, section_path: Option<String>) -> TextChunk {
+    TextChunk {
+        content: words[range.clone()]
+            .iter()
+            .map(|w| w.text)
+            .collect::<Vec<_>>()
+            .join(" "),
+        char_start: words[range.start].char_start,
+        char_end: words[range.end - 1].char_end,
+        section_path,
+    }
+}
+
+/// Build a chunk from a byte range, trimming surrounding whitespace. None if blank.
+fn chunk_from_bytes(text: &str, offsets: &CharOffsets, range: Range<usize>) -> Option<TextChunk> {
+    let slice = &text[range.clone()];
+    let trimmed = slice.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let byte_start = range.start + (slice.len() - slice.trim_start().len());
+    let byte_end = byte_start + trimmed.len();
+
+    Some(TextChunk {
+        content: trimmed.split_whitespace().collect::<Vec<_>>().join(" "),
+        char_start: offsets.char_at(byte_start),
+        char_end: offsets.char_at(byte_end),
+        section_path: None,
+    })
+}
+
+/// Recursively split a byte range on the first separator present until every piece
+/// is at most `chunk_size` characters. Pieces are contiguous and keep their separators.
+fn split_recursive(
+    text: &str,
+    range: Range<usize>,
+    separators: &[&str],
+    chunk_size: usize,
+    pieces: &mut Vec<Range<usize>>,
+) {
+    let slice = &text[range.clone()];
+    if slice.chars().count() <= chunk_size {
+        pieces.push(range);
+        return;
+    }
+
+    let Some((separator, rest)) = separators.split_first() else {
+        // Nothing left to split on, cut every `chunk_size` characters
+        let mut start = range.start;
+        for (count, (byte, _)) in slice.char_indices().enumerate() {
+            if count > 0 && count % chunk_size == 0 {
+                pieces.push(start..range.start + byte);
+                start = range.start + byte;
+            }
+        }
+        pieces.push(start..range.end);
+        return;
+    };
+
+    let mut start = range.start;
+    for (byte, _) in slice.match_indices(separator) {
+        let end = range.start + byte + separator.len();
+        split_recursive(text, start..end, rest, chunk_size, pieces);
+        start = end;
+    }
+    if start < range.end {
+        split_recursive(text, start..range.end, rest, chunk_size, pieces);
+    }
+}
+
+/// Converts byte offsets in a text to character offsets
+struct CharOffsets(Vec<usize>); // byte offset of every character
+
+impl CharOffsets {
+    fn new(text: &str) -> Self {
+        Self(text.char_indices().map(|(byte, _)| byte).collect())
+    }
+
+    fn char_at(&self, byte: usize) -> usize {
+        self.0.partition_point(|b| *b < byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn extracted(text: &str) -> ExtractedText {
+        ExtractedText {
+            text: text.to_string(),
+            pages: Vec::new(),
+            headings: Vec::new(),
+        }
+    }
+
+    fn contents(chunks: &[TextChunk]) -> Vec<&str> {
+        chunks.iter().map(|c| c.content.as_str()).collect()
+    }
+
+    /// The source text a chunk's character range covers
+    fn source(text: &str, chunk: &TextChunk) -> String {
+        text.chars()
+            .skip(chunk.char_start)
+            .take(chunk.char_end - chunk.char_start)
+            .collect()
+    }
+
+    /// Splits on whitespace and punctuation, so every token is a word or symbol
+    fn whitespace_tokenizer() -> Arc<Tokenizer> {
+        let json = r#"{
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": { "type": "Whitespace" },
+            "post_processor": null,
+            "decoder": null,
+            "model": { "type": "WordLevel", "vocab": { "[UNK]": 0 }, "unk_token": "[UNK]" }
+        }"#;
+        Arc::new(Tokenizer::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn word_windows_overlap() {
+        let text = "w0 w1 w2 w3 w4 w5 w6 w7 w8 w9";
+        let chunker = WordWindowChunker {
+            chunk_size: 4,
+            overlap: 1,
+        };
+
+        let chunks = chunker.chunk(&extracted(text)).unwrap();
+
+        assert_eq!(
+            contents(&chunks),
+            vec!["w0 w1 w2 w3", "w3 w4 w5 w6", "w6 w7 w8 w9"]
+        );
+        assert_eq!((chunks[1].char_start, chunks[1].char_end), (9, 20));
+    }
+
+    #[test]
+    fn word_windows_end_without_a_repeated_tail() {
+        let words = word_spans("a b c d e f g h");
+
+        let chunks = word_windows(&words, 2..8, 3, 0, Some("Intro".to_string()));
+
+        assert_eq!(contents(&chunks), vec!["c d e", "f g h"]);
+        assert!(chunks
+            .iter()
+            .all(|c| c.section_path.as_deref() == Some("Intro")));
+    }
+
+    #[test]
+    fn pack_overlaps_by_whole_units() {
+        let text = "One two three. Four five. Six seven eight. Nine.";
+        let chunker = SentenceChunker {
+            max_words: 5,
+            overlap_words: 2,
+        };
+
+        let chunks = chunker.chunk(&extracted(text)).unwrap();
+
+        assert_eq!(
+            contents(&chunks),
+            vec![
+                "One two three. Four five.",
+                "Four five. Six seven eight.",
+                "Nine."
+            ]
+        );
+    }
+
+    #[test]
+    fn pack_splits_oversized_units_into_windows() {
+        let words = word_spans("a b c d e. F g.");
+        let units = vec![0..5, 5..7];
+
+        let chunks = pack(&words, &units, 2, 0, None);
+
+        assert_eq!(contents(&chunks), vec!["a b", "c d", "e.", "F g."]);
+    }
+
+    #[test]
+    fn paragraphs_pack_whole() {
+        let text = "First para here.\n\nSecond one.\n\nThird paragraph is rather long.";
+        let chunker = ParagraphChunker { max_words: 5 };
+
+        let chunks = chunker.chunk(&extracted(text)).unwrap();
+
+        assert_eq!(
+            contents(&chunks),
+            vec![
+                "First para here. Second one.",
+                "Third paragraph is rather long."
+            ]
+        );
+    }
+
+    #[test]
+    fn split_recursive_prefers_the_coarsest_separator() {
+        let text = "aaaa bbbb\n\ncccc dddd";
+        let mut pieces = Vec::new();
+
+        split_recursive(text, 0..text.len(), SEPARATORS, 10, &mut pieces);
+
+        let pieces: Vec<&str> = pieces.into_iter().map(|p| &text[p]).collect();
+        assert_eq!(pieces, vec!["aaaa bbbb\n", "\n", "cccc dddd"]);
+    }
+
+    #[test]
+    fn split_recursive_cuts_on_character_boundaries() {
+        let text = "ééééé";
+        let mut pieces = Vec::new();
+
+        split_recursive(text, 0..text.len(), SEPARATORS, 2, &mut pieces);
+
+        let pieces: Vec<&str> = pieces.into_iter().map(|p| &text[p]).collect();
+        assert_eq!(pieces, vec!["éé", "éé", "é"]);
+    }
+
+    #[test]
+    fn split_recursive_pieces_cover_the_text() {
+        let text = "Größe. Maß und Zahl.\nÜber alles. Ende";
+        let mut pieces = Vec::new();
+
+        split_recursive(text, 0..text.len(), SEPARATORS, 8, &mut pieces);
+
+        assert_eq!(pieces.first().unwrap().start, 0);
+        assert_eq!(pieces.last().unwrap().end, text.len());
+        assert!(pieces.windows(2).all(|p| p[0].end == p[1].start));
+        assert!(pieces.iter().all(|p| text[p.clone()].chars().count() <= 8));
+    }
+
+    #[test]
+    fn recursive_chunks_overlap_and_map_to_character_offsets() {
+        let text = "Größe eins. Maß zwei. Über drei. Ende vier.";
+        let chunker = RecursiveCharacterChunker {
+            chunk_size: 24,
+            overlap: 12,
+        };
+
+        let chunks = chunker.chunk(&extracted(text)).unwrap();
+
+        assert_eq!(
+            contents(&chunks),
+            vec![
+                "Größe eins. Maß zwei.",
+                "Maß zwei. Über drei.",
+                "Über drei. Ende vier."
+            ]
+        );
+        for chunk in &chunks {
+            assert_eq!(source(text, chunk), chunk.content);
+        }
+    }
+
+    #[test]
+    fn char_offsets_count_multi_byte_characters() {
+        let offsets = CharOffsets::new("aé€𝄞b");
+
+        let chars: Vec<usize> = [0, 1, 3, 6, 10, 11]
+            .into_iter()
+            .map(|byte| offsets.char_at(byte))
+            .collect();
+
+        assert_eq!(chars, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn token_windows_overlap() {
+        let text = "α β, γ δ";
+        let chunker = TokenChunker {
+            tokenizer: whitespace_tokenizer(),
+            max_tokens: 3,
+            overlap_tokens: 1,
+        };
+
+        let chunks = chunker.chunk(&extracted(text)).unwrap();
+
+        assert_eq!(contents(&chunks), vec!["α β,", ", γ δ"]);
+        for chunk in &chunks {
+            assert_eq!(source(text, chunk), chunk.content);
+        }
+    }
+
+    #[test]
+    fn token_chunking_needs_a_tokenizer() {
+        let config = ChunkingConfig::Token {
+            max_tokens: 10,
+            overlap_tokens: 2,
+        };
+
+        assert!(config.needs_tokenizer());
+        assert!(config.chunker(None).is_err());
+        assert!(config.chunker(Some(whitespace_tokenizer())).is_ok());
+    }
+
+    #[test]
+    fn validates_sizes() {
+        assert!(ChunkingConfig::default().validate().is_ok());
+        assert!(ChunkingConfig::WordWindow {
+            chunk_size: 0,
+            overlap: 0
+        }
+        .validate()
+        .is_err());
+        assert!(ChunkingConfig::Sentence {
+            max_words: 50,
+            overlap_words: 50
+        }
+        .validate()
+        .is_err());
+        assert!(ChunkingConfig::RecursiveCharacter {
+            chunk_size: MAX_CHUNK_CHARS + 1,
+            overlap: 0
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn missing_sizes_take_defaults() {
+        let config: ChunkingConfig = serde_json::from_str(r#"{"strategy": "token"}"#).unwrap();
+
+        assert_eq!(
+            config,
+            ChunkingConfig::Token {
+                max_tokens: 256,
+                overlap_tokens: 32
+            }
+        );
+    }
+
+    fn heading(level: usize, title: &str, char_start: usize) -> Heading {
+        Heading {
+            level,
+            title: title.to_string(),
+            char_start,
+        }
+    }
+
+    /// Each sentence's words joined back together
+    fn sentences(text: &str) -> Vec<String> {
+        let words = word_spans(text);
+        sentence_ranges(text, &words, 0..words.len())
+            .into_iter()
+            .map(|range| {
+                words[range]
+                    .iter()
+                    .map(|w| w.text)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sentences_skip_abbreviations_initials_and_decimals() {
+        let text = "Forces, e.g. gravity, act at a distance. J. Maxwell wrote eq. 4 in 1865. \
+                    It gives c = 2.998 m/s. See Fig. 2 and Dr. Who.";
+
+        assert_eq!(
+            sentences(text),
+            vec![
+                "Forces, e.g. gravity, act at a distance.",
+                "J. Maxwell wrote eq. 4 in 1865.",
+                "It gives c = 2.998 m/s.",
+                "See Fig. 2 and Dr. Who."
+            ]
+        );
+    }
+
+    #[test]
+    fn sentences_end_at_quotes_numbers_and_blank_lines() {
+        let text = "He said \"stop.\" Then left! 42 came next? maybe not\n\nA heading\nmore";
+
+        assert_eq!(
+            sentences(text),
+            vec![
+                "He said \"stop.\"",
+                "Then left!",
+                "42 came next? maybe not",
+                "A heading more"
+            ]
+        );
+    }
+
+    #[test]
+    fn sentences_within_a_range() {
+        let text = "Skip this. Keep one. Keep two. Skip too.";
+        let words = word_spans(text);
+
+        let ranges = sentence_ranges(text, &words, 2..6);
+
+        assert_eq!(ranges, vec![2..4, 4..6]);
+        assert!(sentence_ranges(text, &words, 3..3).is_empty());
+    }
+
+    #[test]
+    fn sections_follow_the_heading_hierarchy() {
+        let text = "Preface words 1 Motion Intro 1.1 Speed fast 2 Energy work";
+        let words = word_spans(text);
+        let headings = vec![
+            heading(1, "1 Motion", 14),
+            heading(2, "1.1 Speed", 29),
+            heading(1, "2 Energy", 44),
+        ];
+
+        let sections = section_ranges(&headings, &words);
+
+        assert_eq!(
+            sections,
+            vec![
+                (0..2, None),
+                (2..5, Some("1 Motion".to_string())),
+                (5..8, Some("1 Motion > 1.1 Speed".to_string())),
+                (8..11, Some("2 Energy".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_sections_are_dropped_but_still_nest() {
+        let text = "Chapter 1 Section 1.1 Body text";
+        let words = word_spans(text);
+        let headings = vec![heading(1, "Chapter 1", 0), heading(2, "Section 1.1", 10)];
+
+        let sections = section_ranges(&headings, &words);
+
+        assert_eq!(
+            sections,
+            vec![
+                (0..2, Some("Chapter 1".to_string())),
+                (2..6, Some("Chapter 1 > Section 1.1".to_string())),
+            ]
+        );
+        assert_eq!(section_ranges(&headings, &[]), vec![]);
+        assert_eq!(section_ranges(&[], &words), vec![(0..6, None)]);
+    }
+
+    #[test]
+    fn structured_chunks_keep_character_offsets_in_multi_byte_text() {
+        let text = "Größen und Maße. Ärger über Öl.\n\nKapitel Zwei\nWärme fließt. Kälte bleibt.";
+        let mut source_text = extracted(text);
+        source_text.headings = vec![heading(1, "Kapitel Zwei", 33)];
+        let chunker = StructuredChunker {
+            max_words: 4,
+            overlap_words: 0,
+        };
+
+        let chunks = chunker.chunk(&source_text).unwrap();
+
+        assert_eq!(
+            contents(&chunks),
+            vec![
+                "Größen und Maße.",
+                "Ärger über Öl.",
+                "Kapitel Zwei Wärme fließt.",
+                "Kälte bleibt."
+            ]
+        );
+        assert_eq!(chunks[0].section_path, None);
+        assert_eq!(chunks[3].section_path.as_deref(), Some("Kapitel Zwei"));
+        for chunk in &chunks {
+            let covered = source(text, chunk);
+            assert_eq!(
+                covered.split_whitespace().collect::<Vec<_>>().join(" "),
+                chunk.content
+            );
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,255 @@
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::sea_query::{Expr, SimpleExpr};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, Order, PaginatorTrait,
+    QueryFilter, QueryOrder, Set,
+};
+use uuid::Uuid;
+
+use crate::entities::{conversation, message};
+use crate::services::document::DocumentService;
+use crate::services::llm::{ChatMessage, LlmProvider};
+use crate::services::rag::{RagAnswer, RagService, NO_SOURCES_ANSWER};
+use crate::AppState;
+
+/// Title of conversations created without one, replaced by the first question
+const DEFAULT_TITLE: &str = "New conversation";
+
+/// How many earlier messages are replayed to the model
+const HISTORY_MESSAGES: u64 = 8;
+
+/// Earlier messages are cut to this many characters when rewriting queries
+const REWRITE_MESSAGE_CHARS: usize = 500;
+
+const REWRITE_PROMPT: &str = "Rewrite the student's latest question as a standalone search query \
+that can be understood without the conversation, resolving pronouns and references to earlier \
+turns. Reply with the query only.";
+
+pub struct ConversationService;
+
+/// Orders a question before its answer; both are stored with the same timestamp
+fn turn_order() -> SimpleExpr {
+    Expr::cust("CASE \"message\".\"role\" WHEN 'user' THEN 0 ELSE 1 END")
+}
+
+impl ConversationService {
+    /// Create a conversation grounded in the given documents.
+    /// Returns `None` if any document doesn't exist or isn't owned by the user.
+    pub async fn create_conversation(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        title: Option<String>,
+        document_ids: Vec<Uuid>,
+    ) -> Result<Option<conversation::Model>> {
+        if !DocumentService::owns_documents(db, user_id, &document_ids).await? {
+            return Ok(None);
+        }
+
+        let now = Utc::now().naive_utc();
+
+        let new_conversation = conversation::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            title: Set(title.unwrap_or_else(|| DEFAULT_TITLE.to_string())),
+            document_ids: Set(serde_json::to_value(&document_ids)?),
+            created_at: Set(now),
+            updated_at: Set(now),
+        };
+
+        let conversation = new_conversation.insert(db).await?;
+        Ok(Some(conversation))
+    }
+
+    /// Get user's conversations, most recently active first
+    pub async fn get_user_conversations(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+    ) -> Result<Vec<conversation::Model>> {
+        let conversations = conversation::Entity::find()
+            .filter(conversation::Column::UserId.eq(user_id))
+            .order_by_desc(conversation::Column::UpdatedAt)
+            .all(db)
+            .await?;
+
+        Ok(conversations)
+    }
+
+    /// Get conversation by ID
+    pub async fn get_conversation_by_id(
+        db: &DatabaseConnection,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<conversation::Model>> {
+        let conversation = conversation::Entity::find()
+            .filter(conversation::Column::Id.eq(conversation_id))
+            .filter(conversation::Column::UserId.eq(user_id))
+            .one(db)
+            .await?;
+
+        Ok(conversation)
+    }
+
+    /// Get a conversation's messages in order
+    pub async fn get_messages(
+        db: &DatabaseConnection,
+        conversation_id: Uuid,
+    ) -> Result<Vec<message::Model>> {
+        let messages = message::Entity::find()
+            .filter(message::Column::ConversationId.eq(conversation_id))
+            .order_by_asc(message::Column::CreatedAt)
+            .order_by(turn_order(), Order::Asc)
+            .all(db)
+            .await?;
+
+        Ok(messages)
+    }
+
+    /// Delete a conversation (cascades to its messages)
+    pub async fn delete_conversation(
+        db: &DatabaseConnection,
+        conversation: conversation::Model,
+    ) -> Result<()> {
+        let conversation: conversation::ActiveModel = conversation.into();
+        conversation.delete(db).await?;
+        Ok(())
+    }
+
+    /// Parse the documents a conversation is grounded in
+    pub fn document_ids(conversation: &conversation::Model) -> Vec<Uuid> {
+        serde_json::from_value(conversation.document_ids.clone()).unwrap_or_default()
+    }
+
+    /// Parse the chunk ids stored on a message
+    pub fn chunk_ids(message: &message::Model) -> Vec<String> {
+        serde_json::from_value(message.chunk_ids.clone()).unwrap_or_default()
+    }
+
+    /// Answer a new student message using the conversation's history and documents,
+    /// storing both the question and the reply
+    pub async fn send_message(
+        state: &AppState,
+        conversation: &conversation::Model,
+        content: String,
+        limit: u64,
+    ) -> Result<(message::Model, message::Model, RagAnswer)> {
+        let db = &state.db;
+
+        // Most recent messages, back in chronological order
+        let mut history = message::Entity::find()
+            .filter(message::Column::ConversationId.eq(conversation.id))
+            .order_by_desc(message::Column::CreatedAt)
+            .order_by(turn_order(), Order::Desc)
+            .paginate(db, HISTORY_MESSAGES)
+            .fetch_page(0)
+            .await?;
+        history.reverse();
+
+        let standalone_query = if history.is_empty() {
+            content.clone()
+        } else {
+            Self::rewrite_query(state.llm.as_ref(), &history, &content).await?
+        };
+
+        let sources = RagService::retrieve(
+            state,
+            conversation.user_id,
+            &standalone_query,
+            limit,
+            &Self::document_ids(conversation),
+        )
+        .await?;
+
+        let answer = if sources.is_empty() {
+            RagService::finish(NO_SOURCES_ANSWER.to_string(), sources, None)
+        } else {
+            let history: Vec<ChatMessage> = history
+                .iter()
+                .map(|m| match m.role.as_str() {
+                    "assistant" => ChatMessage::assistant(m.content.clone()),
+                    _ => ChatMessage::user(m.content.clone()),
+                })
+                .collect();
+
+            let completion = state
+                .llm
+                .complete(RagService::build_messages(&history, &content, &sources))
+                .await?;
+
+            RagService::finish(completion.content, sources, completion.usage)
+        };
+
+        let now = Utc::now().naive_utc();
+
+        let user_message = message::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            conversation_id: Set(conversation.id),
+            role: Set("user".to_string()),
+            content: Set(content.clone()),
+            standalone_query: Set(Some(standalone_query)),
+            chunk_ids: Set(serde_json::json!([])),
+            created_at: Set(now),
+        }
+        .insert(db)
+        .await?;
+
+        // Keep chunk ids in source order so `[n]` markers in the content stay resolvable
+        let chunk_ids: Vec<&String> = answer.sources.iter().map(|s| &s.chunk_id).collect();
+
+        let assistant_message = message::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            conversation_id: Set(conversation.id),
+            role: Set("assistant".to_string()),
+            content: Set(answer.answer.clone()),
+            standalone_query: Set(None),
+            chunk_ids: Set(serde_json::to_value(&chunk_ids)?),
+            created_at: Set(now),
+        }
+        .insert(db)
+        .await?;
+
+        // Bump activity, naming untitled conversations after their first question
+        let mut active: conversation::ActiveModel = conversation.clone().into();
+        if history.is_empty() && conversation.title == DEFAULT_TITLE {
+            active.title = Set(content.chars().take(80).collect());
+        }
+        active.updated_at = Set(Utc::now().naive_utc());
+        active.update(db).await?;
+
+        Ok((user_message, assistant_message, answer))
+    }
+
+    /// Turn a follow-up like "what about its second law?" into a self-contained query
+    async fn rewrite_query(
+        llm: &dyn LlmProvider,
+        history: &[message::Model],
+        question: &str,
+    ) -> Result<String> {
+        let transcript = history
+            .iter()
+            .map(|m| {
+                let speaker = if m.role == "assistant" { "Assistant" } else { "Student" };
+                let content: String = m.content.chars().take(REWRITE_MESSAGE_CHARS).collect();
+                format!("{}: {}", speaker, content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let completion = llm
+            .complete(vec![
+                ChatMessage::system(REWRITE_PROMPT),
+                ChatMessage::user(format!(
+                    "Conversation:\n{}\n\nLatest question: {}",
+                    transcript, question
+                )),
+            ])
+            .await?;
+
+        let rewritten = completion.content.trim().trim_matches('"').trim();
+        if rewritten.is_empty() {
+            Ok(question.to_string())
+        } else {
+            Ok(rewritten.to_string())
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use crate::services::vector_db::SearchResult;
+
+/// Spreads search results over distinct passages, since overlapping chunks of the same
+/// passage otherwise crowd the top results
+pub struct DiversityService;
+
+impl DiversityService {
+    /// Reorder results by maximal marginal relevance: each pick maximizes
+    /// `lambda * relevance - (1 - lambda) * (similarity to the closest earlier pick)`.
+    /// Lambda 1 keeps the relevance order, 0 favours the most different passages.
+    /// Results without an embedding count as dissimilar to everything.
+    pub fn mmr(results: Vec<SearchResult>, lambda: f32) -> Vec<SearchResult> {
+        let relevance = Self::normalized_relevance(&results);
+
+        let mut remaining: Vec<(SearchResult, f32)> = results.into_iter().zip(relevance).collect();
+        let mut selected: Vec<SearchResult> = Vec::with_capacity(remaining.len());
+
+        while !remaining.is_empty() {
+            let mut best = 0;
+            let mut best_score = f32::NEG_INFINITY;
+
+            for (index, (candidate, relevance)) in remaining.iter().enumerate() {
+                let redundancy = selected
+                    .iter()
+                    .filter_map(|s| {
+                        cosine_similarity(candidate.embedding.as_ref()?, s.embedding.as_ref()?)
+                    })
+                    .fold(0.0f32, f32::max);
+                let score = lambda * relevance - (1.0 - lambda) * redundancy;
+
+                // Strictly greater, so ties keep the incoming order
+                if score > best_score {
+                    best = index;
+                    best_score = score;
+                }
+            }
+
+            selected.push(remaining.remove(best).0);
+        }
+
+        selected
+    }
+
+    /// Relevance scaled to 0..1, from re-ranker scores when present. Scores differ in range
+    /// by search mode, so they're normalized before being weighed against similarity.
+    fn normalized_relevance(results: &[SearchResult]) -> Vec<f32> {
+        let scores: Vec<f32> = results
+            .iter()
+            .map(|r| r.rerank_score.unwrap_or(r.score))
+            .collect();
+
+        let min = scores.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let range = max - min;
+
+        scores
+            .into_iter()
+            .map(|s| if range > 0.0 { (s - min) / range } else { 1.0 })
+            .collect()
+    }
+
+    /// Walk results in rank order, folding each into an earlier hit from the same document
+    /// it sits next to, until `limit` hits are formed. Merged hits take the place and
+    /// scores of their best-ranked chunk.
+    pub fn collapse_adjacent(results: Vec<SearchResult>, limit: usize) -> Vec<SearchResult> {
+        let mut groups: Vec<Vec<SearchResult>> = Vec::new();
+
+        for result in results {
+            let adjacent = groups.iter().position(|group| {
+                group
+                    .iter()
+                    .any(|member| Self::are_adjacent(member, &result))
+            });
+
+            match adjacent {
+                Some(index) => groups[index].push(result),
+                None if groups.len() < limit => groups.push(vec![result]),
+                None => break,
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|mut group| {
+                let best = group.remove(0);
+                Self::merge(best, group)
+            })
+            .collect()
+    }
+
+    /// Neighbouring chunks of one document, or ones whose text ranges overlap
+    fn are_adjacent(a: &SearchResult, b: &SearchResult) -> bool {
+        if a.document_id != b.document_id {
+            return false;
+        }
+
+        if let (Some(a), Some(b)) = (a.chunk_index, b.chunk_index) {
+            return a.abs_diff(b) <= 1;
+        }
+
+        matches!(
+            (a.char_start, a.char_end, b.char_start, b.char_end),
+            (Some(a_start), Some(a_end), Some(b_start), Some(b_end))
+                if a_start <= b_end && b_start <= a_end
+        )
+    }
+
+    /// Join `best` and other chunks of its document into a single hit, in document order
+    /// and without repeating the text neighbouring chunks share. The hit keeps `best`'s
+    /// id and scores.
+    pub fn merge(best: SearchResult, others: Vec<SearchResult>) -> SearchResult {
+        if others.is_empty() {
+            return best;
+        }
+
+        let best_id = best.chunk_id.clone();
+        let scores = (best.score, best.rerank_score);
+
+        let mut chunks = others;
+        chunks.push(best);
+        chunks.sort_by_key(|c| (c.chunk_index, c.char_start));
+        chunks.dedup_by(|a, b| a.chunk_id == b.chunk_id);
+
+        let mut chunks = chunks.into_iter();
+        let mut merged = chunks.next().expect("best is always present");
+
+        for part in chunks {
+            merged.content = join_overlapping(&merged, &part);
+            merged.chunk_ids.extend(part.chunk_ids);
+            merged.page_start = min_option(merged.page_start, part.page_start);
+            merged.page_end = merged.page_end.max(part.page_end);
+            merged.char_start = min_option(merged.char_start, part.char_start);
+            merged.char_end = merged.char_end.max(part.char_end);
+        }
+
+        merged.chunk_id = best_id;
+        (merged.score, merged.rerank_score) = scores;
+        merged.embedding = None;
+        merged
+    }
+}
+
+/// `earlier`'s content followed by whatever of `later`'s it doesn't already contain.
+/// Content is whitespace-normalized, so character ranges can't index into it directly;
+/// they only bound how long the shared text can be. Without ranges, the longest run of
+/// words that ends `earlier` and starts `later` is taken as shared.
+fn join_overlapping(earlier: &SearchResult, later: &SearchResult) -> String {
+    let mut content = earlier.content.clone();
+
+    if let (Some(earlier_end), Some(later_start), Some(later_end)) =
+        (earlier.char_end, later.char_start, later.char_end)
+    {
+        // Already covered, as when a neighbour lies inside an earlier merged span
+        if later_end <= earlier_end {
+            return content;
+        }
+
+        // Normalizing only ever shrinks text, so the raw overlap is an upper bound
+        let max_overlap = (earlier_end - later_start).max(0) as usize;
+        let overlap = shared_chars(&earlier.content, &later.content, max_overlap);
+        let remainder: String = later.content.chars().skip(overlap).collect();
+        if !remainder.trim().is_empty() {
+            if overlap == 0 && !remainder.starts_with(char::is_whitespace) {
+                content.push(' ');
+            }
+            content.push_str(&remainder);
+        }
+        return content;
+    }
+
+    let earlier_words: Vec<&str> = earlier.content.split_whitespace().collect();
+    let later_words: Vec<&str> = later.content.split_whitespace().collect();
+    let overlap = (1..=earlier_words.len().min(later_words.len()))
+        .rev()
+        .find(|&n| earlier_words[earlier_words.len() - n..] == later_words[..n])
+        .unwrap_or(0);
+
+    if overlap < later_words.len() {
+        content.push(' ');
+        content.push_str(&later_words[overlap..].join(" "));
+    }
+    content
+}
+
+/// Length in characters of the longest suffix of `earlier` that starts `later`, up to `max`
+fn shared_chars(earlier: &str, later: &str, max: usize) -> usize {
+    let earlier: Vec<char> = earlier.chars().collect();
+    let later: Vec<char> = later.chars().collect();
+
+    (1..=max.min(earlier.len()).min(later.len()))
+        .rev()
+        .find(|&n| earlier[earlier.len() - n..] == later[..n])
+        .unwrap_or(0)
+}
+
+fn min_option(a: Option<i32>, b: Option<i32>) -> Option<i32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    (norm_a > 0.0 && norm_b > 0.0).then(|| dot / (norm_a * norm_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(chunk_id: &str, score: f32, embedding: Option<Vec<f32>>) -> SearchResult {
+        SearchResult {
+            chunk_id: chunk_id.to_string(),
+            chunk_ids: vec![chunk_id.to_string()],
+            chunk_index: None,
+            document_id: "doc".to_string(),
+            content: String::new(),
+            page_start: None,
+            page_end: None,
+            char_start: None,
+            char_end: None,
+            section_path: None,
+            score,
+            rerank_score: None,
+            embedding,
+        }
+    }
+
+    fn chunk(chunk_id: &str, index: i32, range: Option<(i32, i32)>, content: &str) -> SearchResult {
+        SearchResult {
+            chunk_index: Some(index),
+            char_start: range.map(|(start, _)| start),
+            char_end: range.map(|(_, end)| end),
+            page_start: Some(index + 1),
+            page_end: Some(index + 1),
+            content: content.to_string(),
+            ..result(chunk_id, 0.5, None)
+        }
+    }
+
+    fn ids(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.chunk_id.as_str()).collect()
+    }
+
+    /// Two near-duplicates ranked first, then a less relevant but different passage
+    fn near_duplicates() -> Vec<SearchResult> {
+        vec![
+            result("a", 0.9, Some(vec![1.0, 0.0])),
+            result("b", 0.8, Some(vec![1.0, 0.01])),
+            result("c", 0.1, Some(vec![0.0, 1.0])),
+        ]
+    }
+
+    #[test]
+    fn mmr_with_lambda_one_keeps_relevance_order() {
+        let results = DiversityService::mmr(near_duplicates(), 1.0);
+
+        assert_eq!(ids(&results), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn mmr_with_lambda_zero_prefers_different_passages() {
+        let results = DiversityService::mmr(near_duplicates(), 0.0);
+
+        assert_eq!(ids(&results), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn mmr_demotes_near_duplicates_at_balanced_lambda() {
+        let results = DiversityService::mmr(near_duplicates(), 0.5);
+
+        assert_eq!(ids(&results), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn mmr_keeps_incoming_order_on_ties_and_without_embeddings() {
+        let tied = vec![
+            result("x", 0.5, None),
+            result("y", 0.5, None),
+            result("z", 0.5, None),
+        ];
+
+        assert_eq!(ids(&DiversityService::mmr(tied, 0.3)), vec!["x", "y", "z"]);
+        assert!(DiversityService::mmr(vec![], 0.5).is_empty());
+    }
+
+    #[test]
+    fn mmr_prefers_rerank_scores() {
+        let mut results = vec![result("a", 0.9, None), result("b", 0.1, None)];
+        results[0].rerank_score = Some(0.2);
+        results[1].rerank_score = Some(0.7);
+
+        assert_eq!(ids(&DiversityService::mmr(results, 1.0)), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn collapses_neighbouring_chunks_into_the_best_hit() {
+        let mut other = chunk("other", 0, None, "Elsewhere.");
+        other.document_id = "other-doc".to_string();
+        let results = vec![
+            chunk("c2", 2, None, "two"),
+            other,
+            chunk("c1", 1, None, "one"),
+            chunk("c5", 5, None, "five"),
+        ];
+
+        let collapsed = DiversityService::collapse_adjacent(results, 2);
+
+        assert_eq!(ids(&collapsed), vec!["c2", "other"]);
+        assert_eq!(collapsed[0].chunk_ids, vec!["c1", "c2"]);
+        assert_eq!(collapsed[0].content, "one two");
+        assert_eq!(
+            (collapsed[0].page_start, collapsed[0].page_end),
+            (Some(2), Some(3))
+        );
+    }
+
+    #[test]
+    fn merge_drops_text_shared_by_overlapping_chunks() {
+        let first = chunk("c0", 0, Some((0, 22)), "The quick brown fox");
+        let second = chunk("c1", 1, Some((14, 40)), "n fox jumps over");
+
+        let merged = DiversityService::merge(second, vec![first]);
+
+        assert_eq!(merged.chunk_id, "c1");
+        assert_eq!(merged.content, "The quick brown fox jumps over");
+        assert_eq!((merged.char_start, merged.char_end), (Some(0), Some(40)));
+    }
+
+    #[test]
+    fn merge_counts_overlap_in_characters_on_non_ascii_content() {
+        let first = chunk("c0", 0, Some((0, 12)), "Größe über");
+        let second = chunk("c1", 1, Some((8, 20)), "über Maß");
+
+        let merged = DiversityService::merge(first, vec![second]);
+
+        assert_eq!(merged.content, "Größe über Maß");
+    }
+
+    #[test]
+    fn merge_skips_chunks_inside_the_merged_span() {
+        let outer = chunk("c0", 0, Some((0, 50)), "All of it");
+        let inner = chunk("c1", 1, Some((10, 20)), "of");
+
+        let merged = DiversityService::merge(outer, vec![inner]);
+
+        assert_eq!(merged.content, "All of it");
+        assert_eq!(merged.chunk_ids, vec!["c0", "c1"]);
+    }
+
+    #[test]
+    fn merge_without_ranges_joins_on_shared_words() {
+        let first = chunk("c0", 0, None, "one two three");
+        let second = chunk("c1", 1, None, "two three four");
+
+        let merged = DiversityService::merge(first, vec![second]);
+
+        assert_eq!(merged.content, "one two three four");
+    }
+}
\ No newline at end of file
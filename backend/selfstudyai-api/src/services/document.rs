@@ -1,12 +1,41 @@
 use anyhow::Result;
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set, QueryFilter, ColumnTrait};
+use sea_orm::{
+    ActiveModelTrait, DatabaseConnection, EntityTrait, PaginatorTrait, Set, QueryFilter, ColumnTrait,
+    ConnectionTrait, QuerySelect, TransactionTrait,
+};
 use uuid::Uuid;
 
 use crate::entities::{document, document_chunk};
-use crate::services::embeddings::EmbeddingsService;
+use crate::services::chunking::ChunkingConfig;
+use crate::services::ingestion::IngestionService;
 use crate::services::pdf::PdfService;
+use crate::services::progress::ProgressEvent;
+use crate::services::storage::{is_remote_url, BlobStore};
 use crate::services::vector_db::VectorDbService;
+use crate::AppState;
+
+/// Largest PDF accepted, whether uploaded or fetched from a URL
+pub const MAX_FILE_BYTES: usize = 50 * 1024 * 1024;
+
+/// Separates the folders in a document's folder path
+pub const FOLDER_SEPARATOR: &str = "/";
+
+const MAX_TAGS: usize = 20;
+const MAX_LABEL_LENGTH: usize = 100; // characters in a tag or folder name
+
+/// Returned when reprocessing a document that's queued or being processed
+#[derive(Debug, thiserror::Error)]
+#[error("Document {0} is already being processed")]
+pub struct AlreadyProcessing(pub Uuid);
+
+/// Chosen when a document is created: how it's chunked and where it's filed
+#[derive(Debug, Clone, Default)]
+pub struct DocumentOptions {
+    pub chunking: Option<ChunkingConfig>, // None uses the default strategy
+    pub tags: Vec<String>,
+    pub folder: Option<String>,
+}
 
 pub struct DocumentService;
 
@@ -19,6 +48,7 @@ impl DocumentService {
         file_name: String,
         file_url: String,
         file_size: i32,
+        options: DocumentOptions,
     ) -> Result<document::Model> {
         let document_id = Uuid::new_v4();
         let now = Utc::now().naive_utc();
@@ -33,6 +63,10 @@ impl DocumentService {
             page_count: Set(None),
             processing_status: Set("pending".to_string()),
             extracted_text: Set(None),
+            processing_error: Set(None),
+            chunking: Set(Some(options.chunking.unwrap_or_default())),
+            tags: Set(options.tags),
+            folder: Set(options.folder),
             created_at: Set(now),
             updated_at: Set(now),
         };
@@ -42,16 +76,19 @@ impl DocumentService {
     }
 
     /// Process PDF: extract text, create chunks, and generate embeddings
-    pub async fn process_pdf(
-        db: &DatabaseConnection,
-        embeddings_service: &EmbeddingsService,
-        vector_db: &VectorDbService,
-        document_id: Uuid,
-        pdf_bytes: &[u8],
-    ) -> Result<()> {
-        // Extract text
-        let text = PdfService::extract_text(pdf_bytes)?;
-        let page_count = PdfService::get_page_count(pdf_bytes)?;
+    pub async fn process_pdf(state: &AppState, document_id: Uuid, pdf_bytes: &[u8]) -> Result<()> {
+        let db = &state.db;
+
+        // Extract text off the async runtime, reporting pages as they're done
+        let progress = state.progress.clone();
+        let bytes = pdf_bytes.to_vec();
+        let extracted = tokio::task::spawn_blocking(move || {
+            PdfService::extract_text(&bytes, |done, total| {
+                progress.publish(document_id, ProgressEvent::PagesExtracted { done, total })
+            })
+        })
+        .await??;
+        let page_count = extracted.pages.len() as i32;
 
         // Update document with extracted text
         let doc = document::Entity::find_by_id(document_id)
@@ -59,44 +96,74 @@ impl DocumentService {
             .await?
             .ok_or_else(|| anyhow::anyhow!("Document not found"))?;
 
+        // Older documents recorded no strategy; pin the default they're processed with now
+        let chunking = doc.chunking.clone().unwrap_or_default();
         let mut doc: document::ActiveModel = doc.into();
-        doc.extracted_text = Set(Some(text.clone()));
+        doc.chunking = Set(Some(chunking.clone()));
+        doc.extracted_text = Set(Some(extracted.text.clone()));
         doc.page_count = Set(Some(page_count));
         doc.processing_status = Set("processing".to_string());
         doc.updated_at = Set(Utc::now().naive_utc());
-        doc.update(db).await?;
+        let document = doc.update(db).await?;
+
+        // Drop anything left behind by an earlier, interrupted attempt
+        Self::clear_chunks(db, &state.vector_db, document_id).await?;
 
-        // Chunk text (500 words per chunk, 50 word overlap)
-        let chunks = PdfService::chunk_text(&text, 500, 50);
+        // Chunk with the strategy chosen at upload (sentences within sections by default)
+        let tokenizer = if chunking.needs_tokenizer() {
+            Some(state.tokenizers.get(&state.embeddings_service).await?)
+        } else {
+            None
+        };
+        let chunks = chunking.chunker(tokenizer)?.chunk(&extracted)?;
 
-        // Generate embeddings for all chunks
+        // Generate embeddings, reporting progress as batches finish
         tracing::info!("Generating embeddings for {} chunks", chunks.len());
-        let embeddings = embeddings_service.generate_embeddings(chunks.clone()).await?;
+        let total = chunks.len();
+        let progress = &state.progress;
+        let embeddings = state
+            .embeddings_service
+            .generate_embeddings_with_progress(
+                chunks.iter().map(|c| c.content.clone()).collect(),
+                |done| progress.publish(document_id, ProgressEvent::ChunksEmbedded { done, total }),
+            )
+            .await?;
 
         // Save chunks to database and prepare for vector storage
         let mut chunk_data = Vec::new();
 
-        for (index, (chunk_content, embedding)) in chunks.iter().zip(embeddings.iter()).enumerate() {
-            let token_count = PdfService::estimate_tokens(chunk_content);
-            let chunk_id = Uuid::new_v4();
+        for (index, (chunk, embedding)) in chunks.into_iter().zip(embeddings).enumerate() {
+            let token_count = PdfService::estimate_tokens(&chunk.content);
 
             let new_chunk = document_chunk::ActiveModel {
-                id: Set(chunk_id),
+                id: Set(Uuid::new_v4()),
                 document_id: Set(document_id),
                 chunk_index: Set(index as i32),
-                content: Set(chunk_content.clone()),
                 token_count: Set(Some(token_count)),
+                page_start: Set(extracted.page_at(chunk.char_start)),
+                page_end: Set(extracted.page_at(chunk.char_end.saturating_sub(1))),
+                char_start: Set(Some(chunk.char_start as i32)),
+                char_end: Set(Some(chunk.char_end as i32)),
+                section_path: Set(chunk.section_path),
+                content: Set(chunk.content),
                 created_at: Set(Utc::now().naive_utc()),
             };
 
-            new_chunk.insert(db).await?;
+            let new_chunk = new_chunk.insert(db).await?;
 
-            chunk_data.push((chunk_id, chunk_content.clone(), embedding.clone()));
+            chunk_data.push((new_chunk, embedding));
         }
 
         // Store embeddings in Qdrant
         tracing::info!("Storing embeddings in Qdrant");
-        vector_db.store_chunks(document_id, chunk_data).await?;
+        let count = chunk_data.len();
+        state
+            .vector_db
+            .store_chunks(&document, chunk_data)
+            .await?;
+        state
+            .progress
+            .publish(document_id, ProgressEvent::VectorsStored { count });
 
         // Mark as completed
         let doc = document::Entity::find_by_id(document_id)
@@ -106,14 +173,32 @@ impl DocumentService {
 
         let mut doc: document::ActiveModel = doc.into();
         doc.processing_status = Set("completed".to_string());
+        doc.processing_error = Set(None);
         doc.updated_at = Set(Utc::now().naive_utc());
         doc.update(db).await?;
 
+        state.progress.publish(document_id, ProgressEvent::Completed);
         tracing::info!("Document processing completed successfully");
 
         Ok(())
     }
 
+    /// Remove a document's chunks from PostgreSQL and Qdrant
+    pub async fn clear_chunks(
+        db: &impl ConnectionTrait,
+        vector_db: &VectorDbService,
+        document_id: Uuid,
+    ) -> Result<()> {
+        vector_db.delete_document_chunks(document_id).await?;
+
+        document_chunk::Entity::delete_many()
+            .filter(document_chunk::Column::DocumentId.eq(document_id))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
     /// Get user's documents
     pub async fn get_user_documents(
         db: &DatabaseConnection,
@@ -142,10 +227,184 @@ impl DocumentService {
         Ok(document)
     }
 
+    /// Check that every given document exists and belongs to the user
+    pub async fn owns_documents(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        document_ids: &[Uuid],
+    ) -> Result<bool> {
+        if document_ids.is_empty() {
+            return Ok(true);
+        }
+
+        let owned = document::Entity::find()
+            .filter(document::Column::Id.is_in(document_ids.to_vec()))
+            .filter(document::Column::UserId.eq(user_id))
+            .count(db)
+            .await?;
+
+        Ok(owned as usize == document_ids.len())
+    }
+
+    /// One-off backfill: tag vectors stored before per-user filtering with their
+    /// owner, and drop vectors whose document no longer exists. Safe to re-run.
+    pub async fn backfill_vector_owners(
+        db: &DatabaseConnection,
+        vector_db: &VectorDbService,
+    ) -> Result<usize> {
+        let document_ids = vector_db.documents_missing("user_id").await?;
+
+        for document_id in &document_ids {
+            let Ok(document_id) = Uuid::parse_str(document_id) else {
+                tracing::warn!("Skipping vectors with invalid document_id: {}", document_id);
+                continue;
+            };
+
+            match document::Entity::find_by_id(document_id).one(db).await? {
+                Some(doc) => vector_db.set_document_owner(document_id, doc.user_id).await?,
+                None => vector_db.delete_document_chunks(document_id).await?,
+            }
+        }
+
+        Ok(document_ids.len())
+    }
+
+    /// One-off backfill: copy tags, folder and upload time onto vectors stored before
+    /// searches could filter on them. Safe to re-run.
+    pub async fn backfill_vector_labels(
+        db: &DatabaseConnection,
+        vector_db: &VectorDbService,
+    ) -> Result<usize> {
+        let document_ids = vector_db.documents_missing("uploaded_at").await?;
+
+        for document_id in &document_ids {
+            let Ok(document_id) = Uuid::parse_str(document_id) else {
+                tracing::warn!("Skipping vectors with invalid document_id: {}", document_id);
+                continue;
+            };
+
+            // Vectors of deleted documents are removed by the owner backfill
+            if let Some(doc) = document::Entity::find_by_id(document_id).one(db).await? {
+                vector_db.set_document_labels(&doc).await?;
+            }
+        }
+
+        Ok(document_ids.len())
+    }
+
+    /// Rename a document and/or refile it, keeping its vectors' tags and folder in step
+    pub async fn update_document(
+        db: &DatabaseConnection,
+        vector_db: &VectorDbService,
+        document: document::Model,
+        title: Option<String>,
+        tags: Option<Vec<String>>,
+        folder: Option<Option<String>>,
+    ) -> Result<document::Model> {
+        let relabel = tags.is_some() || folder.is_some();
+
+        let mut document: document::ActiveModel = document.into();
+        if let Some(title) = title {
+            document.title = Set(title);
+        }
+        if let Some(tags) = tags {
+            document.tags = Set(tags);
+        }
+        if let Some(folder) = folder {
+            document.folder = Set(folder);
+        }
+        document.updated_at = Set(Utc::now().naive_utc());
+
+        let document = document.update(db).await?;
+
+        if relabel {
+            vector_db.set_document_labels(&document).await?;
+        }
+
+        Ok(document)
+    }
+
+    /// Trimmed, de-duplicated tags, or why they're invalid
+    pub fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, String> {
+        let mut normalized: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim().to_string();
+            if tag.is_empty() || normalized.contains(&tag) {
+                continue;
+            }
+            if tag.chars().count() > MAX_LABEL_LENGTH {
+                return Err(format!("Tags must be at most {} characters", MAX_LABEL_LENGTH));
+            }
+            normalized.push(tag);
+        }
+
+        if normalized.len() > MAX_TAGS {
+            return Err(format!("A document can have at most {} tags", MAX_TAGS));
+        }
+
+        Ok(normalized)
+    }
+
+    /// A folder path with empty segments and surrounding whitespace removed, e.g.
+    /// " Physics//Mechanics/ " -> "Physics/Mechanics"; None when nothing is left
+    pub fn normalize_folder(folder: Option<String>) -> Result<Option<String>, String> {
+        let Some(folder) = folder else {
+            return Ok(None);
+        };
+
+        let parts: Vec<&str> = folder
+            .split(FOLDER_SEPARATOR)
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        if parts.iter().any(|part| part.chars().count() > MAX_LABEL_LENGTH) {
+            return Err(format!(
+                "Folder names must be at most {} characters",
+                MAX_LABEL_LENGTH
+            ));
+        }
+
+        Ok(Some(parts.join(FOLDER_SEPARATOR)).filter(|path| !path.is_empty()))
+    }
+
+    /// Clear a document's chunks and vectors and queue it for processing again.
+    /// Fails with `AlreadyProcessing` while it has a queued or running job, as
+    /// clearing chunks under a worker would leave the document half-indexed.
+    pub async fn reprocess_document(
+        db: &DatabaseConnection,
+        vector_db: &VectorDbService,
+        document: document::Model,
+    ) -> Result<document::Model> {
+        let txn = db.begin().await?;
+
+        // Concurrent requests wait on the row lock, then see the job queued by the first
+        document::Entity::find_by_id(document.id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+        if IngestionService::has_active_job(&txn, document.id).await? {
+            return Err(AlreadyProcessing(document.id).into());
+        }
+
+        Self::clear_chunks(&txn, vector_db, document.id).await?;
+
+        let mut document: document::ActiveModel = document.into();
+        document.processing_status = Set("pending".to_string());
+        document.processing_error = Set(None);
+        document.updated_at = Set(Utc::now().naive_utc());
+        let document = document.update(&txn).await?;
+
+        IngestionService::enqueue(&txn, document.id).await?;
+        txn.commit().await?;
+
+        Ok(document)
+    }
+
     /// Delete a document and its chunks
     pub async fn delete_document(
         db: &DatabaseConnection,
         vector_db: &VectorDbService,
+        blob_store: &dyn BlobStore,
         document_id: Uuid,
         user_id: Uuid,
     ) -> Result<()> {
@@ -158,9 +417,23 @@ impl DocumentService {
         vector_db.delete_document_chunks(document_id).await?;
 
         // Delete from PostgreSQL (cascades to chunks)
+        let file_url = document.file_url.clone();
         let doc: document::ActiveModel = document.into();
         doc.delete(db).await?;
 
+        // The file goes last; an orphaned file is better than a document without one.
+        // The document is already gone, so a failure here is only worth a warning.
+        if !is_remote_url(&file_url) {
+            if let Err(e) = blob_store.delete(&file_url).await {
+                tracing::warn!(
+                    "Failed to delete file {} of document {}: {}",
+                    file_url,
+                    document_id,
+                    e
+                );
+            }
+        }
+
         Ok(())
     }
 }
\ No newline at end of file
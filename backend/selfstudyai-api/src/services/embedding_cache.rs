@@ -0,0 +1,277 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, PaginatorTrait,
+    QueryFilter, Set, Statement,
+};
+use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::entities::embedding_cache;
+
+/// Hashes per lookup and rows per insert, well under Postgres' bind parameter limit
+const QUERY_BATCH_SIZE: usize = 1_000;
+
+/// Drop the least recently used entries beyond the limit, oldest first
+const EVICT_LRU_SQL: &str = r#"
+DELETE FROM embedding_cache
+WHERE (model, text_hash) IN (
+    SELECT model, text_hash FROM embedding_cache
+    ORDER BY last_used_at
+    LIMIT $1
+)
+"#;
+
+/// Hit and miss counts since startup
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Embeddings stored in Postgres by (model, SHA-256 of normalized text), so identical
+/// text is only sent to the provider once per model
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    db: DatabaseConnection,
+    max_age: Duration, // entries unused for this long are evicted
+    max_entries: u64,  // least recently used entries beyond this are evicted
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl EmbeddingCache {
+    pub fn new(db: DatabaseConnection, max_age: Duration, max_entries: u64) -> Self {
+        Self {
+            db,
+            max_age,
+            max_entries,
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Cache key of a text: whitespace collapsed and Unicode NFC-normalized, then hashed
+    pub fn hash(text: &str) -> String {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let normalized: String = normalized.nfc().collect();
+        hex::encode(Sha256::digest(normalized.as_bytes()))
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Look up cached embeddings by hash, counting hits and misses and marking hits as used.
+    /// Entries of the wrong dimension are treated as misses.
+    pub async fn get_many(
+        &self,
+        model: &str,
+        hashes: &[String],
+        dimension: usize,
+    ) -> Result<HashMap<String, Vec<f32>>> {
+        let mut found = HashMap::new();
+
+        for batch in hashes.chunks(QUERY_BATCH_SIZE) {
+            let entries = embedding_cache::Entity::find()
+                .filter(embedding_cache::Column::Model.eq(model))
+                .filter(embedding_cache::Column::TextHash.is_in(batch.to_vec()))
+                .all(&self.db)
+                .await?;
+
+            for entry in entries {
+                let embedding = decode(&entry.embedding);
+                if embedding.len() == dimension {
+                    found.insert(entry.text_hash, embedding);
+                }
+            }
+        }
+
+        if !found.is_empty() {
+            let used: Vec<&String> = found.keys().collect();
+            for batch in used.chunks(QUERY_BATCH_SIZE) {
+                embedding_cache::Entity::update_many()
+                    .col_expr(
+                        embedding_cache::Column::HitCount,
+                        Expr::col(embedding_cache::Column::HitCount).add(1),
+                    )
+                    .col_expr(
+                        embedding_cache::Column::LastUsedAt,
+                        Expr::value(Utc::now().naive_utc()),
+                    )
+                    .filter(embedding_cache::Column::Model.eq(model))
+                    .filter(
+                        embedding_cache::Column::TextHash.is_in(batch.iter().map(|h| h.as_str())),
+                    )
+                    .exec(&self.db)
+                    .await?;
+            }
+        }
+
+        // Duplicate texts within one request count once
+        let requested = hashes.iter().collect::<HashSet<_>>().len();
+        self.hits.fetch_add(found.len() as u64, Ordering::Relaxed);
+        self.misses
+            .fetch_add((requested - found.len()) as u64, Ordering::Relaxed);
+
+        Ok(found)
+    }
+
+    /// Store freshly generated embeddings; entries already present are left alone
+    pub async fn put_many(&self, model: &str, entries: Vec<(String, Vec<f32>)>) -> Result<()> {
+        let now = Utc::now().naive_utc();
+
+        for batch in entries.chunks(QUERY_BATCH_SIZE) {
+            let models = batch
+                .iter()
+                .map(|(hash, embedding)| embedding_cache::ActiveModel {
+                    model: Set(model.to_string()),
+                    text_hash: Set(hash.clone()),
+                    embedding: Set(encode(embedding)),
+                    hit_count: Set(0),
+                    created_at: Set(now),
+                    last_used_at: Set(now),
+                });
+
+            embedding_cache::Entity::insert_many(models)
+                .on_conflict(
+                    OnConflict::columns([
+                        embedding_cache::Column::Model,
+                        embedding_cache::Column::TextHash,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .exec_without_returning(&self.db)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete entries unused for `max_age`, then the least recently used ones beyond
+    /// `max_entries`. Returns how many were removed.
+    pub async fn evict(&self) -> Result<u64> {
+        let (max_age, max_entries) = (self.max_age, self.max_entries);
+        let cutoff = Utc::now().naive_utc() - chrono::Duration::from_std(max_age)?;
+
+        let expired = embedding_cache::Entity::delete_many()
+            .filter(embedding_cache::Column::LastUsedAt.lt(cutoff))
+            .exec(&self.db)
+            .await?
+            .rows_affected;
+
+        let count = embedding_cache::Entity::find().count(&self.db).await?;
+        let overflow = if count > max_entries {
+            self.db
+                .execute(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    EVICT_LRU_SQL,
+                    [((count - max_entries) as i64).into()],
+                ))
+                .await?
+                .rows_affected()
+        } else {
+            0
+        };
+
+        Ok(expired + overflow)
+    }
+
+    /// Evict on startup and then every `interval`, logging the hit rate as it goes
+    pub fn spawn_cleanup(&self, interval: Duration) {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match cache.evict().await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::info!("Evicted {} embedding cache entries", count),
+                    Err(e) => tracing::error!("Failed to evict embedding cache entries: {}", e),
+                }
+
+                let stats = cache.stats();
+                tracing::info!(
+                    "Embedding cache: {} hits, {} misses",
+                    stats.hits,
+                    stats.misses
+                );
+
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_ignores_whitespace_differences() {
+        let hash = EmbeddingCache::hash("Newton's second law");
+
+        assert_eq!(hash, EmbeddingCache::hash("  Newton's\tsecond\n\nlaw "));
+        assert_ne!(hash, EmbeddingCache::hash("Newton's second laws"));
+        assert_ne!(hash, EmbeddingCache::hash("newton's second law"));
+    }
+
+    #[test]
+    fn hash_normalizes_unicode_composition() {
+        // "é" precomposed and as "e" plus a combining acute accent
+        assert_eq!(
+            EmbeddingCache::hash("caf\u{e9}"),
+            EmbeddingCache::hash("cafe\u{301}")
+        );
+    }
+
+    #[test]
+    fn hash_is_hex_sha256() {
+        let hash = EmbeddingCache::hash("");
+
+        assert_eq!(
+            hash,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(EmbeddingCache::hash(" \n "), hash);
+    }
+
+    #[test]
+    fn embeddings_round_trip_through_bytes() {
+        let embedding = vec![0.0, -1.5, f32::MIN_POSITIVE, 3.25e7, f32::MAX];
+
+        let bytes = encode(&embedding);
+
+        assert_eq!(bytes.len(), embedding.len() * 4);
+        assert_eq!(&bytes[4..8], &(-1.5f32).to_le_bytes());
+        assert_eq!(decode(&bytes), embedding);
+        assert!(decode(&encode(&[])).is_empty());
+    }
+
+    #[test]
+    fn decode_ignores_trailing_partial_values() {
+        let mut bytes = encode(&[1.0, 2.0]);
+        bytes.push(0xff);
+
+        assert_eq!(decode(&bytes), vec![1.0, 2.0]);
+    }
+}
\ No newline at end of file
@@ -1,63 +1,290 @@
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use reqwest::Client;
+use async_trait::async_trait;
+use futures_util::stream::{self, StreamExt};
+use ort::session::Session;
+use ort::value::Tensor;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
 
-#[derive(Debug, Serialize)]
-struct EmbeddingRequest {
-    inputs: Vec<String>,
+use crate::services::embedding_cache::EmbeddingCache;
+
+/// Longest input, in tokens, fed to local models
+const ONNX_MAX_TOKENS: usize = 512;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whole embedding request, so a stalled provider fails and gets retried
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A backend that turns texts into embedding vectors
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Name or path of the model the vectors come from
+    fn model(&self) -> &str;
+
+    /// Embed each text, returning one vector per input in the same order
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
+
+    /// Length of the vectors the model produces. By default found by embedding a probe text.
+    async fn dimension(&self) -> Result<usize> {
+        let embeddings = self.embed(vec!["dimension probe".to_string()]).await?;
+        embeddings
+            .first()
+            .map(Vec::len)
+            .filter(|len| *len > 0)
+            .ok_or_else(|| anyhow::anyhow!("Embedding provider returned no vector"))
+    }
+
+    /// The model's own tokenizer, if the provider has one loaded locally
+    fn tokenizer(&self) -> Option<Arc<Tokenizer>> {
+        None
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct EmbeddingResponse(Vec<Vec<f32>>);
+/// Build the provider selected by `EMBEDDING_PROVIDER`
+/// ("huggingface", "openai_compatible", "ollama" or "onnx")
+pub fn from_config(
+    provider: &str,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    model: Option<String>,
+    model_path: Option<String>,
+) -> Result<Arc<dyn EmbeddingProvider>> {
+    match provider {
+        "huggingface" => {
+            let api_key = api_key.context("HUGGINGFACE_API_KEY must be set for huggingface")?;
+            let model =
+                model.unwrap_or_else(|| "sentence-transformers/all-MiniLM-L6-v2".to_string());
+            Ok(Arc::new(HuggingFaceProvider::new(api_key, model)?))
+        }
+        "openai_compatible" => {
+            let base_url =
+                base_url.context("EMBEDDING_BASE_URL must be set for openai_compatible")?;
+            let model = model.context("EMBEDDING_MODEL must be set for openai_compatible")?;
+            Ok(Arc::new(OpenAiCompatibleEmbeddingProvider::new(
+                base_url, api_key, model,
+            )?))
+        }
+        "ollama" => {
+            let base_url = base_url.unwrap_or_else(|| "http://localhost:11434".to_string());
+            let model = model.context("EMBEDDING_MODEL must be set for ollama")?;
+            Ok(Arc::new(OllamaEmbeddingProvider::new(base_url, model)?))
+        }
+        "onnx" => {
+            let model_path = model_path.context("EMBEDDING_MODEL_PATH must be set for onnx")?;
+            Ok(Arc::new(OnnxEmbeddingProvider::load(&model_path, model)?))
+        }
+        other => anyhow::bail!("Unknown embedding provider: {}", other),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmbeddingError {
+    #[error("{provider} responded with {status}: {message}")]
+    Status {
+        provider: String,
+        status: StatusCode,
+        retry_after: Option<Duration>, // from Retry-After or the provider's own estimate
+        message: String,
+    },
+    #[error("Embedding request failed: {0}")]
+    Request(reqwest::Error),
+    #[error("Embedding provider still failing after {attempts} attempts: {last_error}")]
+    Unavailable { attempts: u32, last_error: String },
+}
+
+impl EmbeddingError {
+    /// Transient failures worth retrying; everything else will fail the same way again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            EmbeddingError::Status { status, .. } => {
+                status.is_server_error()
+                    || *status == StatusCode::TOO_MANY_REQUESTS
+                    || *status == StatusCode::REQUEST_TIMEOUT
+            }
+            EmbeddingError::Request(_) | EmbeddingError::Unavailable { .. } => true,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            EmbeddingError::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// How requests to the embedding provider are split up and retried
+#[derive(Debug, Clone)]
+pub struct EmbeddingOptions {
+    pub batch_size: usize,  // texts per request
+    pub concurrency: usize, // requests in flight at once
+    pub max_retries: u32,   // retries of a transient failure before giving up
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for EmbeddingOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 32,
+            concurrency: 4,
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct EmbeddingsService {
-    client: Arc<Client>,
-    api_key: String,
-    model: String,
+    provider: Arc<dyn EmbeddingProvider>,
+    dimension: usize,
+    options: EmbeddingOptions,
+    cache: Option<EmbeddingCache>,
 }
 
 impl EmbeddingsService {
-    pub fn new(api_key: String) -> Self {
-        // create a reqwest client here
-        let client = Client::new();
+    /// Wrap a provider, asking it for its vector dimension unless one is configured
+    pub async fn new(
+        provider: Arc<dyn EmbeddingProvider>,
+        dimension: Option<usize>,
+        options: EmbeddingOptions,
+    ) -> Result<Self> {
+        let mut service = Self {
+            provider,
+            dimension: dimension.unwrap_or_default(),
+            options,
+            cache: None,
+        };
 
-        Self {
-            client: Arc::new(client),
-            api_key,
-            model: "sentence-transformers/all-MiniLM-L6-v2".to_string(),
+        if dimension.is_none() {
+            service.dimension = service
+                .with_retries(|| service.provider.dimension())
+                .await
+                .context("Failed to detect embedding dimension")?;
         }
+
+        Ok(service)
+    }
+
+    /// Consult the cache before calling the provider, and fill it afterwards
+    pub fn with_cache(mut self, cache: EmbeddingCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// The model the embeddings come from
+    pub fn model(&self) -> &str {
+        self.provider.model()
+    }
+
+    /// Length of every embedding vector
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// The provider's own tokenizer, if it has one loaded locally
+    pub fn tokenizer(&self) -> Option<Arc<Tokenizer>> {
+        self.provider.tokenizer()
     }
 
-    /// Generate embeddings for a list of texts using HuggingFace API
+    /// Generate embeddings for a list of texts
     pub async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
-        let url = format!(
-            "https://router.huggingface.co/hf-inference/models/{}/pipeline/feature-extraction",
-            self.model
-        );
+        self.generate_embeddings_with_progress(texts, |_| {}).await
+    }
+
+    /// Generate embeddings, calling `on_progress` with the number of texts done so far.
+    /// Cached embeddings are reused; the rest are generated and cached.
+    pub async fn generate_embeddings_with_progress(
+        &self,
+        texts: Vec<String>,
+        on_progress: impl Fn(usize),
+    ) -> Result<Vec<Vec<f32>>> {
+        let Some(cache) = &self.cache else {
+            return self.embed_batches(texts, on_progress).await;
+        };
 
-        let request_body = EmbeddingRequest { inputs: texts };
+        let total = texts.len();
+        let hashes: Vec<String> = texts.iter().map(|t| EmbeddingCache::hash(t)).collect();
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request_body)
-            .send()
+        // The cache only saves work; if it's unavailable, embed everything
+        let mut embeddings = cache
+            .get_many(self.model(), &hashes, self.dimension)
             .await
-            .context("Failed to send request to HuggingFace API")?;
+            .unwrap_or_else(|e| {
+                tracing::warn!("Embedding cache lookup failed: {}", e);
+                HashMap::new()
+            });
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("HuggingFace API error: {}", error_text);
+        // Each distinct missing text is embedded once
+        let mut missing = Vec::new();
+        let mut seen = HashSet::new();
+        for (hash, text) in hashes.iter().zip(texts) {
+            if !embeddings.contains_key(hash) && seen.insert(hash) {
+                missing.push((hash.clone(), text));
+            }
         }
 
-        let embeddings: Vec<Vec<f32>> = response
-            .json()
-            .await
-            .context("Failed to parse HuggingFace API response")?;
+        let cached = total - missing.len();
+        if cached > 0 {
+            on_progress(cached);
+        }
+
+        if !missing.is_empty() {
+            let (missing_hashes, missing_texts): (Vec<String>, Vec<String>) =
+                missing.into_iter().unzip();
+            let fresh = self
+                .embed_batches(missing_texts, |done| on_progress(cached + done))
+                .await?;
+
+            let entries: Vec<(String, Vec<f32>)> = missing_hashes.into_iter().zip(fresh).collect();
+            if let Err(e) = cache.put_many(self.model(), entries.clone()).await {
+                tracing::warn!("Failed to store embeddings in cache: {}", e);
+            }
+            embeddings.extend(entries);
+        }
+
+        hashes
+            .iter()
+            .map(|hash| {
+                embeddings
+                    .get(hash)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("No embedding returned"))
+            })
+            .collect()
+    }
+
+    /// Generate embeddings in batches, several at once, calling `on_progress` with the
+    /// number of texts embedded so far as batches finish (in order)
+    async fn embed_batches(
+        &self,
+        texts: Vec<String>,
+        on_progress: impl Fn(usize),
+    ) -> Result<Vec<Vec<f32>>> {
+        let batches: Vec<Vec<String>> = texts
+            .chunks(self.options.batch_size.max(1))
+            .map(<[String]>::to_vec)
+            .collect();
+
+        let mut results = stream::iter(batches)
+            .map(|batch| self.embed_batch(batch))
+            .buffered(self.options.concurrency.max(1));
+
+        let mut embeddings = Vec::with_capacity(texts.len());
+        while let Some(batch) = results.next().await {
+            embeddings.extend(batch?);
+            on_progress(embeddings.len());
+        }
 
         Ok(embeddings)
     }
@@ -70,4 +297,732 @@ impl EmbeddingsService {
             .next()
             .ok_or_else(|| anyhow::anyhow!("No embedding returned"))
     }
+
+    /// Embed one batch, retrying transient failures, and check what came back
+    async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let count = texts.len();
+        let embeddings = self
+            .with_retries(|| self.provider.embed(texts.clone()))
+            .await?;
+
+        if embeddings.len() != count {
+            anyhow::bail!(
+                "Embedding provider returned {} vectors for {} texts",
+                embeddings.len(),
+                count
+            );
+        }
+        if let Some(embedding) = embeddings.iter().find(|e| e.len() != self.dimension) {
+            anyhow::bail!(
+                "Embedding provider returned a {}-dimensional vector, expected {}",
+                embedding.len(),
+                self.dimension
+            );
+        }
+
+        Ok(embeddings)
+    }
+
+    /// Run a provider call, retrying transient failures with jittered exponential backoff.
+    /// A `Retry-After` from the provider is waited out instead, unless it's longer than
+    /// `max_delay`. Gives up with `EmbeddingError::Unavailable`.
+    async fn with_retries<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let error = match call().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            let Some(embedding_error) = error.downcast_ref::<EmbeddingError>() else {
+                return Err(error);
+            };
+            if !embedding_error.is_retryable() {
+                return Err(error);
+            }
+
+            attempt += 1;
+            let delay = match embedding_error.retry_after() {
+                Some(retry_after) => retry_after,
+                None => {
+                    // Full jitter: anywhere up to the exponential backoff
+                    let ceiling = self
+                        .options
+                        .base_delay
+                        .saturating_mul(1 << (attempt - 1).min(16))
+                        .min(self.options.max_delay);
+                    ceiling.mul_f64(rand::random::<f64>())
+                }
+            };
+
+            if attempt > self.options.max_retries || delay > self.options.max_delay {
+                return Err(EmbeddingError::Unavailable {
+                    attempts: attempt,
+                    last_error: embedding_error.to_string(),
+                }
+                .into());
+            }
+
+            tracing::warn!(
+                "Embedding request failed (attempt {}), retrying in {:?}: {}",
+                attempt,
+                delay,
+                embedding_error
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Send a JSON request, turning error statuses into `EmbeddingError`s naming the provider
+async fn post_json<T: Serialize + ?Sized>(
+    request: reqwest::RequestBuilder,
+    body: &T,
+    provider: &str,
+) -> Result<reqwest::Response, EmbeddingError> {
+    let response = request
+        .json(body)
+        .send()
+        .await
+        .map_err(EmbeddingError::Request)?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let headers = response.headers().clone();
+        let message = response.text().await.unwrap_or_default();
+
+        return Err(EmbeddingError::Status {
+            provider: provider.to_string(),
+            status,
+            retry_after: retry_after(&headers, &message),
+            message,
+        });
+    }
+
+    Ok(response)
+}
+
+/// How long an error response asks us to wait, in seconds, from its `Retry-After` header
+/// or, as Hugging Face answers 503 while a model loads, an `estimated_time` in its body
+fn retry_after(headers: &HeaderMap, body: &str) -> Option<Duration> {
+    let header = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<f64>().ok());
+    let estimated_time = serde_json::from_str::<ProviderErrorBody>(body)
+        .ok()
+        .and_then(|body| body.estimated_time);
+
+    header
+        .or(estimated_time)
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+fn http_client() -> Result<Client> {
+    Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("Failed to build embedding HTTP client")
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderErrorBody {
+    estimated_time: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct HuggingFaceRequest {
+    inputs: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HuggingFaceResponse(Vec<Vec<f32>>);
+
+/// Hugging Face Inference feature-extraction pipeline
+pub struct HuggingFaceProvider {
+    client: Client,
+    api_key: String,
+    model: String, // e.g. sentence-transformers/all-MiniLM-L6-v2
+}
+
+impl HuggingFaceProvider {
+    pub fn new(api_key: String, model: String) -> Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            api_key,
+            model,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for HuggingFaceProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let url = format!(
+            "https://router.huggingface.co/hf-inference/models/{}/pipeline/feature-extraction",
+            self.model
+        );
+
+        let response = post_json(
+            self.client.post(&url).bearer_auth(&self.api_key),
+            &HuggingFaceRequest { inputs: texts },
+            "HuggingFace API",
+        )
+        .await?;
+
+        let HuggingFaceResponse(embeddings) = response
+            .json()
+            .await
+            .context("Failed to parse HuggingFace API response")?;
+
+        Ok(embeddings)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiEmbeddingRequest<'a> {
+    model: &'a str,
+    input: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Any server speaking the OpenAI `/v1/embeddings` API (OpenAI, vLLM, TEI, LM Studio...)
+pub struct OpenAiCompatibleEmbeddingProvider {
+    client: Client,
+    base_url: String, // e.g. http://localhost:8080/v1
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAiCompatibleEmbeddingProvider {
+    pub fn new(base_url: String, api_key: Option<String>, model: String) -> Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+        })
+    }
 }
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiCompatibleEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/embeddings", self.base_url);
+
+        let mut request = self.client.post(&url);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = post_json(
+            request,
+            &OpenAiEmbeddingRequest {
+                model: &self.model,
+                input: texts,
+            },
+            "Embedding provider",
+        )
+        .await?;
+
+        let mut response: OpenAiEmbeddingResponse = response
+            .json()
+            .await
+            .context("Failed to parse embedding provider response")?;
+
+        // The API doesn't promise to keep input order
+        response.data.sort_by_key(|e| e.index);
+        Ok(response.data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaEmbedRequest<'a> {
+    model: &'a str,
+    input: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Ollama's native `/api/embed` endpoint
+pub struct OllamaEmbeddingProvider {
+    client: Client,
+    base_url: String, // e.g. http://localhost:11434
+    model: String,    // e.g. nomic-embed-text
+}
+
+impl OllamaEmbeddingProvider {
+    pub fn new(base_url: String, model: String) -> Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.base_url);
+
+        let response = post_json(
+            self.client.post(&url),
+            &OllamaEmbedRequest {
+                model: &self.model,
+                input: texts,
+            },
+            "Ollama",
+        )
+        .await?;
+
+        let response: OllamaEmbedResponse = response
+            .json()
+            .await
+            .context("Failed to parse Ollama response")?;
+
+        Ok(response.embeddings)
+    }
+}
+
+/// Load `model.onnx` and `tokenizer.json` from a directory, with the tokenizer set to pad
+/// batches and truncate to what local models accept
+pub fn load_onnx_model(model_path: &str) -> Result<(Session, Tokenizer)> {
+    let dir = Path::new(model_path);
+
+    let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+        .map_err(|e| anyhow::anyhow!("Failed to load tokenizer from {}: {}", model_path, e))?;
+    tokenizer.with_padding(Some(PaddingParams::default()));
+    tokenizer
+        .with_truncation(Some(TruncationParams {
+            max_length: ONNX_MAX_TOKENS,
+            ..Default::default()
+        }))
+        .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer: {}", e))?;
+
+    let session = Session::builder()
+        .and_then(|builder| builder.commit_from_file(dir.join("model.onnx")))
+        .with_context(|| format!("Failed to load ONNX model from {}", model_path))?;
+
+    Ok((session, tokenizer))
+}
+
+/// A sentence-transformers model exported to ONNX, run in-process. The model directory
+/// holds `model.onnx` and `tokenizer.json`; the ONNX Runtime library is found through
+/// `ORT_DYLIB_PATH` or the system library path.
+pub struct OnnxEmbeddingProvider {
+    session: Arc<Mutex<Session>>,
+    tokenizer: Arc<Tokenizer>,
+    model: String,
+    token_type_ids: bool, // whether the model takes a token_type_ids input
+}
+
+impl OnnxEmbeddingProvider {
+    /// Load the model from a directory. `model` names it, defaulting to the directory path.
+    pub fn load(model_path: &str, model: Option<String>) -> Result<Self> {
+        let (session, tokenizer) = load_onnx_model(model_path)?;
+
+        let token_type_ids = session.inputs.iter().any(|i| i.name == "token_type_ids");
+
+        Ok(Self {
+            session: Arc::new(Mutex::new(session)),
+            tokenizer: Arc::new(tokenizer),
+            model: model.unwrap_or_else(|| model_path.to_string()),
+            token_type_ids,
+        })
+    }
+
+    /// Run the model and mean-pool its token embeddings into normalized sentence vectors
+    fn run(
+        session: &Mutex<Session>,
+        tokenizer: &Tokenizer,
+        token_type_ids: bool,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>> {
+        let encodings = tokenizer
+            .encode_batch(texts, true)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize texts: {}", e))?;
+
+        // Padding makes every encoding the same length
+        let batch = encodings.len();
+        let length = encodings.first().map_or(0, |e| e.len());
+        let flatten = |field: fn(&tokenizers::Encoding) -> &[u32]| -> Vec<i64> {
+            encodings
+                .iter()
+                .flat_map(|e| field(e).iter().map(|v| *v as i64))
+                .collect()
+        };
+        let mask = flatten(tokenizers::Encoding::get_attention_mask);
+
+        let mut inputs = ort::inputs! {
+            "input_ids" => Tensor::from_array(([batch, length], flatten(tokenizers::Encoding::get_ids)))?,
+            "attention_mask" => Tensor::from_array(([batch, length], mask.clone()))?,
+        };
+        if token_type_ids {
+            inputs.push((
+                "token_type_ids".into(),
+                Tensor::from_array(([batch, length], flatten(tokenizers::Encoding::get_type_ids)))?
+                    .into(),
+            ));
+        }
+
+        let mut session = session.lock().unwrap();
+        let outputs = session.run(inputs).context("Failed to run ONNX model")?;
+
+        // [batch, tokens, hidden] token embeddings
+        let (shape, values) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .context("Unexpected ONNX model output")?;
+        let hidden = match **shape {
+            [b, t, h] if b as usize == batch && t as usize == length => h as usize,
+            _ => anyhow::bail!("Unexpected ONNX model output shape {:?}", shape),
+        };
+
+        // Mean-pool over real tokens, then L2-normalize (which makes the mean's divisor moot)
+        let embeddings = (0..batch)
+            .map(|b| {
+                let mut pooled = vec![0.0f32; hidden];
+                for t in (0..length).filter(|t| mask[b * length + t] != 0) {
+                    let offset = (b * length + t) * hidden;
+                    for (sum, value) in pooled.iter_mut().zip(&values[offset..offset + hidden]) {
+                        *sum += value;
+                    }
+                }
+
+                let norm = pooled.iter().map(|v| v * v).sum::<f32>().sqrt();
+                if norm > 0.0 {
+                    pooled.iter_mut().for_each(|v| *v /= norm);
+                }
+                pooled
+            })
+            .collect();
+
+        Ok(embeddings)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OnnxEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let session = self.session.clone();
+        let tokenizer = self.tokenizer.clone();
+        let token_type_ids = self.token_type_ids;
+
+        // Inference is CPU-bound, keep it off the async runtime
+        tokio::task::spawn_blocking(move || Self::run(&session, &tokenizer, token_type_ids, texts))
+            .await?
+    }
+
+    fn tokenizer(&self) -> Option<Arc<Tokenizer>> {
+        Some(self.tokenizer.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    /// Embeds "n" as [n, 1] and anything else as [0, 1], failing first with any scripted errors
+    #[derive(Default)]
+    struct FakeProvider {
+        failures: Mutex<VecDeque<anyhow::Error>>,
+        fail_forever: Option<StatusCode>,
+        calls: AtomicUsize,
+    }
+
+    impl FakeProvider {
+        fn failing(failures: Vec<anyhow::Error>) -> Arc<Self> {
+            Arc::new(Self {
+                failures: Mutex::new(failures.into()),
+                ..Default::default()
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for FakeProvider {
+        fn model(&self) -> &str {
+            "fake"
+        }
+
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if let Some(status) = self.fail_forever {
+                return Err(status_error(status, None).into());
+            }
+            if let Some(error) = self.failures.lock().unwrap().pop_front() {
+                return Err(error);
+            }
+
+            let values: Vec<f32> = texts.iter().map(|t| t.parse().unwrap_or(0.0)).collect();
+            // Later batches finish first, to check results are put back in order
+            let delay = 20u64.saturating_sub(values[0] as u64 * 2);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+
+            Ok(values.into_iter().map(|v| vec![v, 1.0]).collect())
+        }
+    }
+
+    fn status_error(status: StatusCode, retry_after: Option<Duration>) -> EmbeddingError {
+        EmbeddingError::Status {
+            provider: "Fake".to_string(),
+            status,
+            retry_after,
+            message: String::new(),
+        }
+    }
+
+    fn options(max_retries: u32) -> EmbeddingOptions {
+        EmbeddingOptions {
+            batch_size: 2,
+            concurrency: 4,
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+
+    async fn service(provider: Arc<FakeProvider>, max_retries: u32) -> EmbeddingsService {
+        EmbeddingsService::new(provider, Some(2), options(max_retries))
+            .await
+            .unwrap()
+    }
+
+    fn texts(count: usize) -> Vec<String> {
+        (0..count).map(|n| n.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let provider = FakeProvider::failing(vec![
+            status_error(StatusCode::SERVICE_UNAVAILABLE, None).into(),
+            status_error(StatusCode::TOO_MANY_REQUESTS, None).into(),
+        ]);
+        let service = service(provider.clone(), 5).await;
+
+        let embedding = service.generate_embedding("7".to_string()).await.unwrap();
+
+        assert_eq!(embedding, vec![7.0, 1.0]);
+        assert_eq!(provider.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let provider = Arc::new(FakeProvider {
+            fail_forever: Some(StatusCode::BAD_GATEWAY),
+            ..Default::default()
+        });
+        let service = service(provider.clone(), 2).await;
+
+        let error = service
+            .generate_embedding("1".to_string())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<EmbeddingError>(),
+            Some(EmbeddingError::Unavailable { attempts: 3, .. })
+        ));
+        assert_eq!(provider.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_failures() {
+        let provider = FakeProvider::failing(vec![
+            status_error(StatusCode::UNAUTHORIZED, None).into(),
+            anyhow::anyhow!("unparseable response"),
+        ]);
+        let service = service(provider.clone(), 5).await;
+
+        let error = service
+            .generate_embedding("1".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<EmbeddingError>(),
+            Some(EmbeddingError::Status {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ));
+
+        let error = service
+            .generate_embedding("1".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "unparseable response");
+        assert_eq!(provider.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn waits_out_retry_after() {
+        let retry_after = Duration::from_millis(30);
+        let provider = FakeProvider::failing(vec![status_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(retry_after),
+        )
+        .into()]);
+        let service = service(provider.clone(), 5).await;
+
+        let started = Instant::now();
+        service.generate_embedding("1".to_string()).await.unwrap();
+
+        assert!(started.elapsed() >= retry_after);
+        assert_eq!(provider.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_retry_after_exceeds_max_delay() {
+        let provider = FakeProvider::failing(vec![status_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Some(Duration::from_secs(120)),
+        )
+        .into()]);
+        let service = service(provider.clone(), 5).await;
+
+        let error = service
+            .generate_embedding("1".to_string())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<EmbeddingError>(),
+            Some(EmbeddingError::Unavailable { attempts: 1, .. })
+        ));
+        assert_eq!(provider.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn batches_keep_input_order() {
+        let provider = Arc::new(FakeProvider::default());
+        let service = service(provider.clone(), 0).await;
+        let progress = Mutex::new(Vec::new());
+
+        let embeddings = service
+            .generate_embeddings_with_progress(texts(7), |done| progress.lock().unwrap().push(done))
+            .await
+            .unwrap();
+
+        let firsts: Vec<f32> = embeddings.iter().map(|e| e[0]).collect();
+        assert_eq!(firsts, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(*progress.lock().unwrap(), vec![2, 4, 6, 7]);
+        assert_eq!(provider.calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn rejects_vectors_of_the_wrong_dimension() {
+        let provider = Arc::new(FakeProvider::default());
+        let service = EmbeddingsService::new(provider, Some(3), options(0))
+            .await
+            .unwrap();
+
+        let error = service.generate_embeddings(texts(2)).await.unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("2-dimensional vector, expected 3"));
+    }
+
+    #[tokio::test]
+    async fn detects_dimension_from_the_provider() {
+        let provider =
+            FakeProvider::failing(vec![status_error(StatusCode::BAD_GATEWAY, None).into()]);
+
+        let service = EmbeddingsService::new(provider.clone(), None, options(1))
+            .await
+            .unwrap();
+
+        assert_eq!(service.dimension(), 2);
+        assert_eq!(provider.calls(), 2);
+    }
+
+    #[test]
+    fn retry_after_prefers_the_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(" 2.5 "));
+
+        assert_eq!(
+            retry_after(&headers, r#"{"estimated_time": 10.0}"#),
+            Some(Duration::from_millis(2500))
+        );
+    }
+
+    #[test]
+    fn retry_after_falls_back_to_estimated_time() {
+        let mut headers = HeaderMap::new();
+        // HTTP dates aren't supported, so they don't count
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2026 07:28:00 GMT"),
+        );
+
+        assert_eq!(
+            retry_after(&headers, r#"{"error": "loading", "estimated_time": 20}"#),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(retry_after(&HeaderMap::new(), "Service Unavailable"), None);
+    }
+
+    #[test]
+    fn retry_after_ignores_negative_values() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("-1"));
+
+        assert_eq!(retry_after(&headers, ""), None);
+    }
+
+    #[test]
+    fn classifies_retryable_errors() {
+        assert!(status_error(StatusCode::INTERNAL_SERVER_ERROR, None).is_retryable());
+        assert!(status_error(StatusCode::TOO_MANY_REQUESTS, None).is_retryable());
+        assert!(status_error(StatusCode::REQUEST_TIMEOUT, None).is_retryable());
+        assert!(!status_error(StatusCode::BAD_REQUEST, None).is_retryable());
+        assert!(!status_error(StatusCode::UNAUTHORIZED, None).is_retryable());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,429 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{redirect, Client, StatusCode, Url};
+
+/// Whole download, including redirects
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("URL scheme not allowed: {0}")]
+    SchemeNotAllowed(String),
+    #[error("Host not allowed: {0}")]
+    HostNotAllowed(String),
+    #[error("Host resolves to a non-public address: {0}")]
+    BlockedAddress(String),
+    #[error("Too many redirects")]
+    TooManyRedirects,
+    #[error("File exceeds the {0} byte limit")]
+    TooLarge(usize),
+    #[error("File is not a PDF")]
+    NotPdf,
+    #[error("Downloaded {actual} bytes but file_size says {expected}")]
+    SizeMismatch { expected: usize, actual: usize },
+    #[error("Remote server responded with {0}")]
+    Status(StatusCode),
+    #[error("Download failed: {0}")]
+    Request(reqwest::Error),
+}
+
+impl FetchError {
+    /// Transient failures worth retrying; everything else will fail the same way again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            FetchError::Request(_) => true,
+            _ => false,
+        }
+    }
+
+    fn from_reqwest(error: reqwest::Error) -> Self {
+        // Surface our own policy errors raised inside the resolver or redirect policy
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&error);
+        while let Some(e) = source {
+            if let Some(fetch_error) = e.downcast_ref::<FetchError>() {
+                return match fetch_error {
+                    FetchError::InvalidUrl(s) => FetchError::InvalidUrl(s.clone()),
+                    FetchError::SchemeNotAllowed(s) => FetchError::SchemeNotAllowed(s.clone()),
+                    FetchError::HostNotAllowed(s) => FetchError::HostNotAllowed(s.clone()),
+                    FetchError::BlockedAddress(s) => FetchError::BlockedAddress(s.clone()),
+                    FetchError::TooManyRedirects => FetchError::TooManyRedirects,
+                    _ => break,
+                };
+            }
+            source = e.source();
+        }
+        FetchError::Request(error)
+    }
+}
+
+/// Which URLs may be fetched at all
+#[derive(Debug, Clone)]
+pub struct UrlPolicy {
+    pub allowed_schemes: Vec<String>,
+    /// Hosts that may be fetched, including their subdomains; "*" allows any public host
+    pub allowed_hosts: Vec<String>,
+}
+
+impl UrlPolicy {
+    pub fn check(&self, url: &Url) -> Result<(), FetchError> {
+        if !self.allowed_schemes.iter().any(|s| s == url.scheme()) {
+            return Err(FetchError::SchemeNotAllowed(url.scheme().to_string()));
+        }
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| FetchError::InvalidUrl(url.to_string()))?
+            .to_ascii_lowercase();
+
+        // IP literals skip DNS resolution, so check them here
+        if let Ok(ip) = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            if !is_public_ip(ip) {
+                return Err(FetchError::BlockedAddress(host));
+            }
+        }
+
+        let allowed = self.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.trim_start_matches('.').to_ascii_lowercase();
+            allowed == "*"
+                || host == allowed
+                || host
+                    .strip_suffix(allowed.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        });
+        if !allowed {
+            return Err(FetchError::HostNotAllowed(host));
+        }
+
+        Ok(())
+    }
+
+    /// Check where a redirect leads, after `followed` redirects already happened
+    fn check_redirect(&self, url: &Url, followed: usize) -> Result<(), FetchError> {
+        if followed > MAX_REDIRECTS {
+            return Err(FetchError::TooManyRedirects);
+        }
+        self.check(url)
+    }
+}
+
+/// Downloads user-supplied URLs without letting them reach internal services
+#[derive(Clone)]
+pub struct RemoteFetcher {
+    client: Client,
+    policy: UrlPolicy,
+    max_bytes: usize,
+}
+
+impl RemoteFetcher {
+    pub fn new(policy: UrlPolicy, max_bytes: usize) -> anyhow::Result<Self> {
+        let redirect_policy = policy.clone();
+
+        let client = Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .connect_timeout(CONNECT_TIMEOUT)
+            // A proxy would resolve hosts itself, bypassing the address check
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .redirect(redirect::Policy::custom(move |attempt| {
+                let followed = attempt.previous().len();
+                match redirect_policy.check_redirect(attempt.url(), followed) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e),
+                }
+            }))
+            .build()?;
+
+        Ok(Self {
+            client,
+            policy,
+            max_bytes,
+        })
+    }
+
+    /// Check a URL against the policy before accepting it
+    pub fn check_url(&self, url: &str) -> Result<Url, FetchError> {
+        let url = Url::parse(url).map_err(|_| FetchError::InvalidUrl(url.to_string()))?;
+        self.policy.check(&url)?;
+        Ok(url)
+    }
+
+    /// Download a PDF, verifying its magic bytes and, if given, its expected size
+    pub async fn fetch_pdf(
+        &self,
+        url: &str,
+        expected_size: Option<usize>,
+    ) -> Result<Bytes, FetchError> {
+        let url = self.check_url(url)?;
+
+        let mut response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(FetchError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            return Err(FetchError::Status(response.status()));
+        }
+
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_bytes as u64)
+        {
+            return Err(FetchError::TooLarge(self.max_bytes));
+        }
+
+        // Content-Length can lie, so count while reading
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(FetchError::from_reqwest)? {
+            if body.len() + chunk.len() > self.max_bytes {
+                return Err(FetchError::TooLarge(self.max_bytes));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        if !body.starts_with(b"%PDF-") {
+            return Err(FetchError::NotPdf);
+        }
+
+        if let Some(expected) = expected_size {
+            if body.len() != expected {
+                return Err(FetchError::SizeMismatch {
+                    expected,
+                    actual: body.len(),
+                });
+            }
+        }
+
+        Ok(Bytes::from(body))
+    }
+}
+
+/// Resolves hostnames, dropping private, loopback and link-local addresses.
+/// Connections only go to the addresses returned here, so DNS rebinding can't sneak past.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(FetchError::BlockedAddress(host).into());
+            }
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether an address is on the public internet
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0 // "this network"
+        || (a == 100 && (64..128).contains(&b)) // carrier-grade NAT
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (b == 18 || b == 19)) // benchmarking
+        || a >= 240) // reserved
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let ipv4 = |high: u16, low: u16| Ipv4Addr::from((u32::from(high) << 16) | u32::from(low));
+
+    // Transition ranges tunnel to the IPv4 address they embed
+    match segments {
+        // IPv4-mapped, and IPv4-compatible which also covers :: and ::1
+        [0, 0, 0, 0, 0, 0xffff, high, low] | [0, 0, 0, 0, 0, 0, high, low] => {
+            return is_public_ipv4(ipv4(high, low))
+        }
+        // NAT64
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => return is_public_ipv4(ipv4(high, low)),
+        // 6to4
+        [0x2002, high, low, ..] => return is_public_ipv4(ipv4(high, low)),
+        // Teredo: server address, then the client address with its bits flipped
+        [0x2001, 0, server_high, server_low, _, _, client_high, client_low] => {
+            return is_public_ipv4(ipv4(server_high, server_low))
+                && is_public_ipv4(ipv4(!client_high, !client_low))
+        }
+        _ => {}
+    }
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00 // unique local
+        || (segments[0] & 0xffc0) == 0xfe80 // link-local
+        || (segments[0] & 0xffc0) == 0xfec0 // site-local (deprecated)
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8) // documentation
+        || (segments[0] == 0x64 && segments[1] == 0xff9b)) // local-use NAT64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(hosts: &[&str]) -> UrlPolicy {
+        UrlPolicy {
+            allowed_schemes: vec!["https".to_string()],
+            allowed_hosts: hosts.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+
+    fn check(policy: &UrlPolicy, url: &str) -> Result<(), FetchError> {
+        policy.check(&Url::parse(url).unwrap())
+    }
+
+    fn is_public(ip: &str) -> bool {
+        is_public_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn allows_listed_hosts_and_their_subdomains() {
+        let policy = policy(&["example.com", ".arxiv.org"]);
+
+        assert!(check(&policy, "https://example.com/a.pdf").is_ok());
+        assert!(check(&policy, "https://files.Example.com/a.pdf").is_ok());
+        assert!(check(&policy, "https://export.arxiv.org/a.pdf").is_ok());
+        assert!(matches!(
+            check(&policy, "https://badexample.com/a.pdf"),
+            Err(FetchError::HostNotAllowed(_))
+        ));
+        assert!(matches!(
+            check(&policy, "https://example.com.evil.net/a.pdf"),
+            Err(FetchError::HostNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        let policy = policy(&["*"]);
+
+        assert!(matches!(
+            check(&policy, "http://example.com/a.pdf"),
+            Err(FetchError::SchemeNotAllowed(_))
+        ));
+        assert!(matches!(
+            check(&policy, "file:///etc/passwd"),
+            Err(FetchError::SchemeNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_non_public_ip_literals_even_with_wildcard() {
+        let policy = policy(&["*"]);
+
+        assert!(check(&policy, "https://93.184.216.34/a.pdf").is_ok());
+        for url in [
+            "https://127.0.0.1/a.pdf",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/a.pdf",
+            "https://[::ffff:10.0.0.1]/a.pdf",
+            "https://[2002:c0a8:0101::]/a.pdf",
+        ] {
+            assert!(
+                matches!(check(&policy, url), Err(FetchError::BlockedAddress(_))),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_redirects_to_private_hosts() {
+        let policy = policy(&["*"]);
+        let private = Url::parse("https://192.168.1.1/admin").unwrap();
+        let public = Url::parse("https://example.com/a.pdf").unwrap();
+
+        assert!(policy.check_redirect(&public, 1).is_ok());
+        assert!(matches!(
+            policy.check_redirect(&private, 1),
+            Err(FetchError::BlockedAddress(_))
+        ));
+        assert!(matches!(
+            policy.check_redirect(&public, MAX_REDIRECTS + 1),
+            Err(FetchError::TooManyRedirects)
+        ));
+    }
+
+    #[test]
+    fn classifies_ipv4() {
+        assert!(is_public("8.8.8.8"));
+        for ip in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.0.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "198.18.0.1",
+            "224.0.0.1",
+            "255.255.255.255",
+        ] {
+            assert!(!is_public(ip), "{ip}");
+        }
+    }
+
+    #[test]
+    fn classifies_ipv6() {
+        assert!(is_public("2606:4700:4700::1111"));
+        for ip in ["::", "::1", "fc00::1", "fe80::1", "2001:db8::1", "ff02::1"] {
+            assert!(!is_public(ip), "{ip}");
+        }
+    }
+
+    #[test]
+    fn classifies_ipv6_by_embedded_ipv4() {
+        // IPv4-mapped and IPv4-compatible
+        assert!(is_public("::ffff:8.8.8.8"));
+        assert!(!is_public("::ffff:127.0.0.1"));
+        assert!(is_public("::8.8.8.8"));
+        assert!(!is_public("::10.0.0.1"));
+        // NAT64
+        assert!(is_public("64:ff9b::8.8.8.8"));
+        assert!(!is_public("64:ff9b::169.254.169.254"));
+        assert!(!is_public("64:ff9b:1::8.8.8.8"));
+        // 6to4
+        assert!(is_public("2002:0808:0808::1"));
+        assert!(!is_public("2002:7f00:0001::1"));
+        assert!(!is_public("2002:c0a8:0101::1"));
+        // Teredo, with the client address bit-flipped
+        assert!(is_public("2001:0:0808:0808::f7f7:f7f7"));
+        assert!(!is_public("2001:0:0808:0808::80ff:fffe"));
+        assert!(!is_public("2001:0:0a00:0001::f7f7:f7f7"));
+    }
+}
\ No newline at end of file
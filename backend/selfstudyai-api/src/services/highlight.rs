@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// Words in a search result snippet
+pub const SNIPPET_WORDS: usize = 40;
+
+/// Words too common to be worth highlighting, as Postgres' english configuration drops them
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "how", "in", "is", "it", "of",
+    "on", "or", "that", "the", "this", "to", "was", "what", "when", "where", "which", "who", "why",
+    "with",
+];
+
+/// Range of characters (not bytes) matching a query term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize, // exclusive
+}
+
+/// The best-matching stretch of a result's content
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<Highlight>, // relative to `text`
+    pub truncated_start: bool,      // content precedes the snippet
+    pub truncated_end: bool,        // content follows the snippet
+}
+
+/// Stemmed query terms, matched against result text so "forces" highlights "force"
+pub struct QueryTerms {
+    stemmer: Stemmer,
+    stems: Vec<String>,
+}
+
+/// A word of content, by character offset
+struct Word {
+    start: usize,
+    end: usize,
+    term: Option<usize>, // index of the query term it matches
+}
+
+/// Finds query terms in search results and picks snippets around them
+pub struct HighlightService;
+
+impl HighlightService {
+    /// Distinct stems of the query's words, without stop words
+    pub fn query_terms(query: &str) -> QueryTerms {
+        let stemmer = Stemmer::create(Algorithm::English);
+
+        let mut stems: Vec<String> = Vec::new();
+        for (_, _, word) in words(query) {
+            let word = word.to_lowercase();
+            // Single letters are mostly split-off contractions, like the s of "Newton's"
+            if word.chars().count() < 2 || STOP_WORDS.contains(&word.as_str()) {
+                continue;
+            }
+            let stem = stemmer.stem(&word).into_owned();
+            if !stems.contains(&stem) {
+                stems.push(stem);
+            }
+        }
+
+        QueryTerms { stemmer, stems }
+    }
+
+    /// Every match of a query term in `content`
+    pub fn highlights(content: &str, terms: &QueryTerms) -> Vec<Highlight> {
+        Self::match_words(content, terms)
+            .into_iter()
+            .filter(|w| w.term.is_some())
+            .map(|w| Highlight {
+                start: w.start,
+                end: w.end,
+            })
+            .collect()
+    }
+
+    /// The window of `max_words` words covering the most distinct query terms, then the
+    /// most matches, earliest first, centred on its matches. Content with no matches
+    /// starts from the top.
+    pub fn snippet(content: &str, terms: &QueryTerms, max_words: usize) -> Snippet {
+        let words = Self::match_words(content, terms);
+        let window = max_words.max(1).min(words.len());
+
+        let mut best = 0;
+        let mut best_score = (0, 0);
+        for first in 0..=words.len() - window {
+            let matched = &words[first..first + window];
+            let distinct: HashSet<usize> = matched.iter().filter_map(|w| w.term).collect();
+            let score = (
+                distinct.len(),
+                matched.iter().filter(|w| w.term.is_some()).count(),
+            );
+            if score > best_score {
+                best = first;
+                best_score = score;
+            }
+        }
+
+        // Centre the matches rather than leaving them at the window's edge
+        let matched: Vec<usize> = (best..best + window)
+            .filter(|&i| words[i].term.is_some())
+            .collect();
+        if let (Some(first), Some(last)) = (matched.first(), matched.last()) {
+            let centre = (first + last) / 2;
+            best = centre.saturating_sub(window / 2).min(words.len() - window);
+        }
+
+        let Some(last) = (best + window).checked_sub(1).map(|last| &words[last]) else {
+            return Snippet {
+                text: String::new(),
+                highlights: Vec::new(),
+                truncated_start: false,
+                truncated_end: false,
+            };
+        };
+        let start = words[best].start;
+        let end = last.end;
+
+        Snippet {
+            text: content.chars().skip(start).take(end - start).collect(),
+            highlights: words[best..best + window]
+                .iter()
+                .filter(|w| w.term.is_some())
+                .map(|w| Highlight {
+                    start: w.start - start,
+                    end: w.end - start,
+                })
+                .collect(),
+            truncated_start: best > 0,
+            truncated_end: best + window < words.len(),
+        }
+    }
+
+    fn match_words(content: &str, terms: &QueryTerms) -> Vec<Word> {
+        words(content)
+            .map(|(start, end, word)| {
+                let stem = terms.stemmer.stem(&word.to_lowercase()).into_owned();
+                Word {
+                    start,
+                    end,
+                    term: terms.stems.iter().position(|s| *s == stem),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Runs of letters and digits in `text`, with their character range
+fn words(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let mut chars = text.char_indices().enumerate().peekable();
+
+    std::iter::from_fn(move || {
+        let (start, start_byte) = loop {
+            let (index, (byte, c)) = chars.next()?;
+            if c.is_alphanumeric() {
+                break (index, byte);
+            }
+        };
+
+        let mut end = start + 1;
+        let mut end_byte = text.len();
+        while let Some(&(index, (byte, c))) = chars.peek() {
+            if !c.is_alphanumeric() {
+                end_byte = byte;
+                break;
+            }
+            end = index + 1;
+            chars.next();
+        }
+
+        Some((start, end, &text[start_byte..end_byte]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The highlighted text, sliced by character offsets
+    fn highlighted(text: &str, highlights: &[Highlight]) -> Vec<String> {
+        highlights
+            .iter()
+            .map(|h| text.chars().skip(h.start).take(h.end - h.start).collect())
+            .collect()
+    }
+
+    #[test]
+    fn query_terms_drop_stop_words_and_repeats() {
+        let terms = HighlightService::query_terms("What is the force of Newton's forces?");
+
+        assert_eq!(terms.stems, vec!["forc", "newton"]);
+    }
+
+    #[test]
+    fn highlights_stemmed_matches() {
+        let terms = HighlightService::query_terms("forces");
+        let content = "A force, forced; the Forces.";
+
+        let highlights = HighlightService::highlights(content, &terms);
+
+        assert_eq!(
+            highlighted(content, &highlights),
+            vec!["force", "forced", "Forces"]
+        );
+    }
+
+    #[test]
+    fn offsets_count_characters_in_non_ascii_content() {
+        let terms = HighlightService::query_terms("über größe");
+        let content = "Die Größe — über 10 µm; naïve über-Größe";
+
+        let highlights = HighlightService::highlights(content, &terms);
+
+        assert_eq!(highlights[0], Highlight { start: 4, end: 9 });
+        assert_eq!(
+            highlighted(content, &highlights),
+            vec!["Größe", "über", "über", "Größe"]
+        );
+    }
+
+    #[test]
+    fn snippet_picks_the_window_with_most_distinct_terms() {
+        let terms = HighlightService::query_terms("energy mass");
+        let content = "energy energy one two three four five six energy mass seven";
+
+        let snippet = HighlightService::snippet(content, &terms, 3);
+
+        assert_eq!(snippet.text, "six energy mass");
+        assert_eq!(
+            highlighted(&snippet.text, &snippet.highlights),
+            vec!["energy", "mass"]
+        );
+        assert!(snippet.truncated_start);
+        assert!(snippet.truncated_end);
+    }
+
+    #[test]
+    fn snippet_centres_matches_in_non_ascii_content() {
+        let terms = HighlightService::query_terms("café");
+        let content = "α β γ δ café ε ζ η θ";
+
+        let snippet = HighlightService::snippet(content, &terms, 3);
+
+        assert_eq!(snippet.text, "δ café ε");
+        assert_eq!(snippet.highlights, vec![Highlight { start: 2, end: 6 }]);
+    }
+
+    #[test]
+    fn snippet_without_matches_starts_from_the_top() {
+        let terms = HighlightService::query_terms("quantum");
+
+        let snippet = HighlightService::snippet("one two three four", &terms, 2);
+
+        assert_eq!(snippet.text, "one two");
+        assert!(snippet.highlights.is_empty());
+        assert!(!snippet.truncated_start);
+        assert!(snippet.truncated_end);
+    }
+
+    #[test]
+    fn snippet_of_short_or_empty_content() {
+        let terms = HighlightService::query_terms("mass");
+
+        let whole = HighlightService::snippet("Mass matters.", &terms, SNIPPET_WORDS);
+        assert_eq!(whole.text, "Mass matters");
+        assert!(!whole.truncated_start && !whole.truncated_end);
+
+        let empty = HighlightService::snippet("  ", &terms, SNIPPET_WORDS);
+        assert!(empty.text.is_empty());
+        assert!(empty.highlights.is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,391 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{NaiveDateTime, SubsecRound, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    PaginatorTrait, QueryFilter, Set, Statement,
+};
+use uuid::Uuid;
+
+use crate::entities::{document, ingestion_job};
+use crate::services::document::DocumentService;
+use crate::services::embeddings::EmbeddingError;
+use crate::services::fetcher::FetchError;
+use crate::services::progress::ProgressEvent;
+use crate::services::storage::is_remote_url;
+use crate::AppState;
+
+/// Attempts before a job (and its document) is marked failed
+const MAX_ATTEMPTS: i32 = 5;
+
+/// First retry waits this long, doubling on every further failure
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+
+/// A running job whose lease hasn't been renewed within this time is assumed dead
+const LOCK_TIMEOUT_SECS: i64 = 5 * 60;
+
+/// How often a worker renews the lease on the job it's running
+const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often idle workers look for new jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Claim the next due job, or one whose worker died, marking it running.
+/// `SKIP LOCKED` lets several workers poll without picking the same job.
+const CLAIM_JOB_SQL: &str = r#"
+UPDATE ingestion_job
+SET status = 'running', attempts = attempts + 1, locked_at = $1, updated_at = $1
+WHERE id = (
+    SELECT id FROM ingestion_job
+    WHERE (status = 'queued' AND run_at <= $1)
+       OR (status = 'running' AND locked_at < $2)
+    ORDER BY run_at
+    LIMIT 1
+    FOR UPDATE SKIP LOCKED
+)
+RETURNING *
+"#;
+
+/// The job was reclaimed by another worker after its lease expired
+#[derive(Debug, thiserror::Error)]
+#[error("Lost the lease on ingestion job {0}")]
+pub struct LeaseLost(Uuid);
+
+pub struct IngestionService;
+
+impl IngestionService {
+    /// Queue a document for processing
+    pub async fn enqueue(
+        db: &impl ConnectionTrait,
+        document_id: Uuid,
+    ) -> Result<ingestion_job::Model> {
+        let now = Utc::now().naive_utc();
+
+        let new_job = ingestion_job::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            document_id: Set(document_id),
+            status: Set("queued".to_string()),
+            attempts: Set(0),
+            max_attempts: Set(MAX_ATTEMPTS),
+            run_at: Set(now),
+            locked_at: Set(None),
+            last_error: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        };
+
+        let job = new_job.insert(db).await?;
+        Ok(job)
+    }
+
+    /// Whether the document is queued or being processed right now
+    pub async fn has_active_job(db: &impl ConnectionTrait, document_id: Uuid) -> Result<bool> {
+        let active = ingestion_job::Entity::find()
+            .filter(ingestion_job::Column::DocumentId.eq(document_id))
+            .filter(ingestion_job::Column::Status.is_in(["queued", "running"]))
+            .count(db)
+            .await?;
+
+        Ok(active > 0)
+    }
+
+    /// Startup recovery: requeue jobs left running by a previous process and
+    /// queue documents stuck in pending/processing without a job (e.g. uploaded
+    /// before the queue existed). Returns how many jobs were requeued or created.
+    ///
+    /// Assumes workers only run in this process, so nothing else holds those jobs.
+    pub async fn recover(db: &DatabaseConnection) -> Result<u64> {
+        let now = Utc::now().naive_utc();
+
+        let requeued = ingestion_job::Entity::update_many()
+            .col_expr(ingestion_job::Column::Status, Expr::value("queued"))
+            .col_expr(
+                ingestion_job::Column::LockedAt,
+                Expr::value(Option::<chrono::NaiveDateTime>::None),
+            )
+            .col_expr(ingestion_job::Column::RunAt, Expr::value(now))
+            .col_expr(ingestion_job::Column::UpdatedAt, Expr::value(now))
+            .filter(ingestion_job::Column::Status.eq("running"))
+            .exec(db)
+            .await?
+            .rows_affected;
+
+        let active_documents: Vec<Uuid> = ingestion_job::Entity::find()
+            .filter(ingestion_job::Column::Status.is_in(["queued", "running"]))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|job| job.document_id)
+            .collect();
+
+        let stuck = document::Entity::find()
+            .filter(document::Column::ProcessingStatus.is_in(["pending", "processing"]))
+            .filter(document::Column::Id.is_not_in(active_documents))
+            .all(db)
+            .await?;
+
+        for doc in &stuck {
+            Self::enqueue(db, doc.id).await?;
+        }
+
+        Ok(requeued + stuck.len() as u64)
+    }
+
+    /// Start `count` workers processing queued jobs in the background
+    pub fn spawn_workers(state: AppState, count: usize) {
+        for worker in 0..count {
+            let state = state.clone();
+            tokio::spawn(async move {
+                tracing::info!("Ingestion worker {} started", worker);
+                loop {
+                    match Self::claim_next(&state.db).await {
+                        Ok(Some(job)) => Self::run_job(&state, job).await,
+                        Ok(None) => tokio::time::sleep(POLL_INTERVAL).await,
+                        Err(e) => {
+                            tracing::error!(
+                                "Ingestion worker {} failed to claim a job: {}",
+                                worker,
+                                e
+                            );
+                            tokio::time::sleep(POLL_INTERVAL).await;
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    async fn claim_next(db: &DatabaseConnection) -> Result<Option<ingestion_job::Model>> {
+        let now = Utc::now().naive_utc();
+        let stale = now - chrono::Duration::seconds(LOCK_TIMEOUT_SECS);
+
+        let job = ingestion_job::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                CLAIM_JOB_SQL,
+                [now.into(), stale.into()],
+            ))
+            .one(db)
+            .await?;
+
+        Ok(job)
+    }
+
+    async fn run_job(state: &AppState, job: ingestion_job::Model) {
+        tracing::info!(
+            "Processing document {} (attempt {}/{})",
+            job.document_id,
+            job.attempts,
+            job.max_attempts
+        );
+
+        // `locked_at` doubles as the lease: renewed while the job runs, and required to
+        // match when the outcome is written so a reclaimed job isn't finished twice
+        let Some(lease) = job.locked_at else {
+            tracing::error!("Claimed ingestion job {} has no lease", job.id);
+            return;
+        };
+        let lease = Mutex::new(lease);
+
+        let result = if job.attempts > job.max_attempts {
+            // Reclaimed after its worker died on the final attempt
+            Err(anyhow::anyhow!("Processing was interrupted too many times"))
+        } else {
+            // Stop writing chunks as soon as another worker may have taken over
+            tokio::select! {
+                result = Self::ingest(state, job.document_id) => result,
+                lost = Self::hold_lease(&state.db, job.id, &lease) => Err(lost.into()),
+            }
+        };
+        let lease = *lease.lock().unwrap();
+
+        let outcome = match result {
+            Err(e) if e.is::<LeaseLost>() => {
+                tracing::warn!("Abandoning document {}: {}", job.document_id, e);
+                return;
+            }
+            Ok(()) => Self::complete(&state.db, job, lease).await,
+            Err(e) => {
+                // e.g. a blocked host, a file that isn't a PDF or an embedding request
+                // the provider rejects won't get better on retry
+                let retryable = e
+                    .downcast_ref::<FetchError>()
+                    .is_none_or(FetchError::is_retryable)
+                    && e.downcast_ref::<EmbeddingError>()
+                        .is_none_or(EmbeddingError::is_retryable);
+                let error = format!("{:#}", e);
+                tracing::error!("Failed to process document {}: {}", job.document_id, error);
+                Self::fail(state, job, lease, error, retryable).await
+            }
+        };
+
+        if let Err(e) = outcome {
+            tracing::error!("Failed to update ingestion job: {}", e);
+        }
+    }
+
+    /// Fetch a document's PDF and run it through the processing pipeline
+    async fn ingest(state: &AppState, document_id: Uuid) -> Result<()> {
+        let Some(doc) = document::Entity::find_by_id(document_id)
+            .one(&state.db)
+            .await?
+        else {
+            // Deleted while queued; nothing to do
+            return Ok(());
+        };
+
+        let pdf_bytes = if is_remote_url(&doc.file_url) {
+            state
+                .fetcher
+                .fetch_pdf(&doc.file_url, Some(doc.file_size as usize))
+                .await?
+        } else {
+            state.blob_store.get(&doc.file_url).await?
+        };
+
+        state.progress.publish(
+            document_id,
+            ProgressEvent::Downloaded {
+                bytes: pdf_bytes.len(),
+            },
+        );
+
+        DocumentService::process_pdf(state, document_id, &pdf_bytes).await
+    }
+
+    /// Renew the job's lease every `LEASE_RENEW_INTERVAL` until it's found to be lost.
+    /// Failed renewals are retried; the lease only lapses after `LOCK_TIMEOUT_SECS`.
+    async fn hold_lease(
+        db: &DatabaseConnection,
+        job_id: Uuid,
+        lease: &Mutex<NaiveDateTime>,
+    ) -> LeaseLost {
+        loop {
+            tokio::time::sleep(LEASE_RENEW_INTERVAL).await;
+
+            let current = *lease.lock().unwrap();
+            // Stored with microsecond precision, so it compares equal when read back
+            let renewed = Utc::now().naive_utc().trunc_subsecs(6);
+
+            let result = Self::running_job(job_id, current)
+                .col_expr(ingestion_job::Column::LockedAt, Expr::value(renewed))
+                .exec(db)
+                .await;
+
+            match result {
+                Ok(result) if result.rows_affected > 0 => *lease.lock().unwrap() = renewed,
+                Ok(_) => return LeaseLost(job_id),
+                Err(e) => {
+                    tracing::warn!("Failed to renew lease on ingestion job {}: {}", job_id, e)
+                }
+            }
+        }
+    }
+
+    /// Update of the job, applying only while this worker's lease on it holds
+    fn running_job(
+        job_id: Uuid,
+        lease: NaiveDateTime,
+    ) -> sea_orm::UpdateMany<ingestion_job::Entity> {
+        ingestion_job::Entity::update_many()
+            .filter(ingestion_job::Column::Id.eq(job_id))
+            .filter(ingestion_job::Column::Status.eq("running"))
+            .filter(ingestion_job::Column::LockedAt.eq(lease))
+    }
+
+    async fn complete(
+        db: &DatabaseConnection,
+        job: ingestion_job::Model,
+        lease: NaiveDateTime,
+    ) -> Result<()> {
+        let updated = Self::running_job(job.id, lease)
+            .col_expr(ingestion_job::Column::Status, Expr::value("completed"))
+            .col_expr(
+                ingestion_job::Column::LockedAt,
+                Expr::value(Option::<NaiveDateTime>::None),
+            )
+            .col_expr(
+                ingestion_job::Column::LastError,
+                Expr::value(Option::<String>::None),
+            )
+            .col_expr(
+                ingestion_job::Column::UpdatedAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .exec(db)
+            .await?
+            .rows_affected;
+
+        if updated == 0 {
+            return Err(LeaseLost(job.id).into());
+        }
+        Ok(())
+    }
+
+    /// Schedule a retry with exponential backoff, or give up once attempts run out
+    async fn fail(
+        state: &AppState,
+        job: ingestion_job::Model,
+        lease: NaiveDateTime,
+        error: String,
+        retryable: bool,
+    ) -> Result<()> {
+        let db = &state.db;
+        let now = Utc::now().naive_utc();
+        let document_id = job.document_id;
+        let give_up = !retryable || job.attempts >= job.max_attempts;
+        let delay = BASE_RETRY_DELAY_SECS
+            .saturating_mul(1 << (job.attempts - 1).clamp(0, 16))
+            .min(MAX_RETRY_DELAY_SECS);
+
+        let mut update = Self::running_job(job.id, lease);
+        if give_up {
+            update = update.col_expr(ingestion_job::Column::Status, Expr::value("failed"));
+        } else {
+            update = update
+                .col_expr(ingestion_job::Column::Status, Expr::value("queued"))
+                .col_expr(
+                    ingestion_job::Column::RunAt,
+                    Expr::value(now + chrono::Duration::seconds(delay)),
+                );
+        }
+        let updated = update
+            .col_expr(
+                ingestion_job::Column::LockedAt,
+                Expr::value(Option::<NaiveDateTime>::None),
+            )
+            .col_expr(ingestion_job::Column::LastError, Expr::value(error.clone()))
+            .col_expr(ingestion_job::Column::UpdatedAt, Expr::value(now))
+            .exec(db)
+            .await?
+            .rows_affected;
+
+        // Another worker has the job now and reports its own outcome
+        if updated == 0 {
+            return Err(LeaseLost(job.id).into());
+        }
+
+        // Surface the error on the document; it only counts as failed once retries run out
+        if let Some(doc) = document::Entity::find_by_id(document_id).one(db).await? {
+            let mut doc: document::ActiveModel = doc.into();
+            doc.processing_status = Set(if give_up { "failed" } else { "pending" }.to_string());
+            doc.processing_error = Set(Some(error.clone()));
+            doc.updated_at = Set(now);
+            doc.update(db).await?;
+        }
+
+        state.progress.publish(
+            document_id,
+            ProgressEvent::Failed {
+                error,
+                retrying: !give_up,
+            },
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,357 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whole request for non-streamed completions
+const COMPLETION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Longest wait for the next bytes of a response, so a stalled stream is abandoned
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String, // "system", "user" or "assistant"
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub content: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// An incremental piece of a streamed completion
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Token(String),
+    Usage(TokenUsage),
+}
+
+/// Dropping the stream cancels the upstream request
+pub type CompletionStream = BoxStream<'static, Result<StreamEvent>>;
+
+/// A chat-completion backend used to generate answers
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Generate a reply to the given conversation
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<Completion>;
+
+    /// Generate a reply token by token. Providers that can't stream send it in one piece.
+    async fn stream(&self, messages: Vec<ChatMessage>) -> Result<CompletionStream> {
+        let completion = self.complete(messages).await?;
+
+        let mut events = vec![Ok(StreamEvent::Token(completion.content))];
+        if let Some(usage) = completion.usage {
+            events.push(Ok(StreamEvent::Usage(usage)));
+        }
+
+        Ok(stream::iter(events).boxed())
+    }
+}
+
+/// Build the provider selected by `LLM_PROVIDER` ("openai_compatible" or "stub")
+pub fn from_config(
+    provider: &str,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    model: Option<String>,
+) -> Result<Arc<dyn LlmProvider>> {
+    match provider {
+        "openai_compatible" => {
+            let base_url = base_url.context("LLM_BASE_URL must be set for openai_compatible")?;
+            let model = model.context("LLM_MODEL must be set for openai_compatible")?;
+            Ok(Arc::new(OpenAiCompatibleProvider::new(
+                base_url, api_key, model,
+            )?))
+        }
+        "stub" => Ok(Arc::new(StubLlmProvider::default())),
+        other => anyhow::bail!("Unknown LLM provider: {}", other),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    temperature: f32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatChoice>,
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    delta: ChunkDelta,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkDelta {
+    content: Option<String>,
+}
+
+/// Any server speaking the OpenAI `/v1/chat/completions` API (vLLM, llama.cpp, Ollama, LM Studio...)
+pub struct OpenAiCompatibleProvider {
+    client: Client,
+    base_url: String, // e.g. http://localhost:11434/v1
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(base_url: String, api_key: Option<String>, model: String) -> Result<Self> {
+        // No overall timeout on the client: streams legitimately run for minutes
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT)
+            .build()
+            .context("Failed to build LLM HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+        })
+    }
+
+    async fn send(&self, messages: &[ChatMessage], stream: bool) -> Result<reqwest::Response> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        let request_body = ChatCompletionRequest {
+            model: &self.model,
+            messages,
+            temperature: 0.2,
+            stream,
+            stream_options: stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+        };
+
+        let mut request = self.client.post(&url).json(&request_body);
+        if !stream {
+            request = request.timeout(COMPLETION_TIMEOUT);
+        }
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .context("Failed to send request to LLM provider")?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("LLM provider error: {}", error_text);
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatibleProvider {
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<Completion> {
+        let response = self.send(&messages, false).await?;
+
+        let completion: ChatCompletionResponse = response
+            .json()
+            .await
+            .context("Failed to parse LLM provider response")?;
+
+        let content = completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content)
+            .ok_or_else(|| anyhow::anyhow!("LLM provider returned no choices"))?;
+
+        Ok(Completion {
+            content,
+            usage: completion.usage,
+        })
+    }
+
+    async fn stream(&self, messages: Vec<ChatMessage>) -> Result<CompletionStream> {
+        let response = self.send(&messages, true).await?;
+        let mut body = response.bytes_stream();
+
+        // Parse the server-sent `data:` lines as they arrive
+        let events = async_stream::try_stream! {
+            // Buffer raw bytes so multi-byte characters split across reads survive
+            let mut buffer: Vec<u8> = Vec::new();
+
+            while let Some(bytes) = body.next().await {
+                let bytes = bytes.context("LLM stream interrupted")?;
+                buffer.extend_from_slice(&bytes);
+
+                while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=newline).collect();
+                    let line = String::from_utf8_lossy(&line).trim().to_string();
+
+                    let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                        continue;
+                    };
+                    if data == "[DONE]" {
+                        return;
+                    }
+
+                    let chunk: ChatCompletionChunk = serde_json::from_str(data)
+                        .context("Failed to parse LLM stream chunk")?;
+
+                    for choice in chunk.choices {
+                        if let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) {
+                            yield StreamEvent::Token(content);
+                        }
+                    }
+                    if let Some(usage) = chunk.usage {
+                        yield StreamEvent::Usage(usage);
+                    }
+                }
+            }
+        };
+
+        Ok(events.boxed())
+    }
+}
+
+/// Returns a canned reply without calling out anywhere; for tests and local development
+pub struct StubLlmProvider {
+    response: String,
+}
+
+impl StubLlmProvider {
+    pub fn new(response: impl Into<String>) -> Self {
+        Self {
+            response: response.into(),
+        }
+    }
+}
+
+impl Default for StubLlmProvider {
+    fn default() -> Self {
+        Self::new("This is a stub answer based on your documents [1].")
+    }
+}
+
+#[async_trait]
+impl LlmProvider for StubLlmProvider {
+    async fn complete(&self, _messages: Vec<ChatMessage>) -> Result<Completion> {
+        Ok(Completion {
+            content: self.response.clone(),
+            usage: Some(TokenUsage::default()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stub_completes_with_its_canned_reply() {
+        let llm = StubLlmProvider::new("Forces cause acceleration [1].");
+
+        let completion = llm
+            .complete(vec![ChatMessage::user("What do forces do?")])
+            .await
+            .unwrap();
+
+        assert_eq!(completion.content, "Forces cause acceleration [1].");
+        assert!(completion.usage.is_some());
+    }
+
+    #[tokio::test]
+    async fn stub_streams_its_reply_in_one_piece() {
+        let llm = StubLlmProvider::new("Forces cause acceleration [1].");
+
+        let events: Vec<StreamEvent> = llm
+            .stream(vec![ChatMessage::user("What do forces do?")])
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert!(matches!(
+            events.as_slice(),
+            [StreamEvent::Token(token), StreamEvent::Usage(_)]
+                if token == "Forces cause acceleration [1]."
+        ));
+    }
+
+    #[test]
+    fn builds_configured_providers() {
+        assert!(from_config("stub", None, None, None).is_ok());
+        assert!(from_config(
+            "openai_compatible",
+            Some("http://localhost:11434/v1".to_string()),
+            None,
+            Some("llama3".to_string()),
+        )
+        .is_ok());
+        assert!(from_config("openai_compatible", None, None, None).is_err());
+        assert!(from_config("unknown", None, None, None).is_err());
+    }
+}
\ No newline at end of file
@@ -1,5 +1,21 @@
 pub mod auth;
+pub mod chunking;
+pub mod conversation;
+pub mod diversity;
 pub mod document;
+pub mod embedding_cache;
 pub mod embeddings;
+pub mod fetcher;
+pub mod highlight;
+pub mod ingestion;
+pub mod llm;
 pub mod pdf;
+pub mod progress;
+pub mod quiz;
+pub mod rag;
+pub mod reindex;
+pub mod reranker;
+pub mod search;
+pub mod search_history;
+pub mod storage;
 pub mod vector_db;
\ No newline at end of file
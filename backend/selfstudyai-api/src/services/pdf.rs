@@ -2,64 +2,444 @@ use anyhow::{Context, Result};
 use lopdf::Document as PdfDocument;
 use std::io::Cursor;
 
+/// Separates levels of a chunk's section path, e.g. "Ch 3 > 3.2 Kinematics"
+pub const SECTION_SEPARATOR: &str = " > ";
+
+/// Text of a whole PDF, remembering where each page and section starts
+#[derive(Debug, Clone)]
+pub struct ExtractedText {
+    pub text: String,
+    pub pages: Vec<PageSpan>,
+    pub headings: Vec<Heading>, // in text order
+}
+
+#[derive(Debug, Clone)]
+pub struct PageSpan {
+    pub number: i32,       // 1-based page number
+    pub char_start: usize, // offset of the page's first character in the text
+    pub byte_start: usize,
+}
+
+/// A section heading, from the PDF outline or detected in the text
+#[derive(Debug, Clone)]
+pub struct Heading {
+    pub level: usize, // 1 for top-level (chapters)
+    pub title: String,
+    pub char_start: usize,
+}
+
+impl ExtractedText {
+    /// Page containing the character at `offset`
+    pub fn page_at(&self, offset: usize) -> Option<i32> {
+        let index = self.pages.partition_point(|p| p.char_start <= offset);
+        index.checked_sub(1).map(|i| self.pages[i].number)
+    }
+}
+
 pub struct PdfService;
 
 impl PdfService {
-    /// Extract text from PDF bytes
-    pub fn extract_text(pdf_bytes: &[u8]) -> Result<String> {
+    /// Extract text from PDF bytes page by page, calling `on_page(done, total)` after each page.
+    /// Headings come from the PDF outline when it has one, otherwise from the text itself.
+    pub fn extract_text(
+        pdf_bytes: &[u8],
+        mut on_page: impl FnMut(usize, usize),
+    ) -> Result<ExtractedText> {
         let cursor = Cursor::new(pdf_bytes);
         let doc = PdfDocument::load_from(cursor)
             .context("Failed to load PDF document")?;
 
         let mut text = String::new();
-        let pages = doc.get_pages();
+        let mut char_count = 0;
+        let mut pages = Vec::new();
+        let pdf_pages = doc.get_pages();
+
+        for (index, (page_num, _)) in pdf_pages.iter().enumerate() {
+            pages.push(PageSpan {
+                number: *page_num as i32,
+                char_start: char_count,
+                byte_start: text.len(),
+            });
 
-        for (page_num, _) in pages.iter() {
             if let Ok(page_text) = doc.extract_text(&[*page_num]) {
-                text.push_str(&page_text);
-                text.push('\n');
+                let page_text = page_text.trim_end();
+                text.push_str(page_text);
+                char_count += page_text.chars().count();
             }
+            text.push('\n');
+            char_count += 1;
+
+            on_page(index + 1, pdf_pages.len());
         }
 
-        Ok(text.trim().to_string())
+        // Trimming the end only, so page offsets stay valid
+        text.truncate(text.trim_end().len());
+
+        let mut extracted = ExtractedText {
+            text,
+            pages,
+            headings: Vec::new(),
+        };
+
+        extracted.headings = match Self::outline_headings(&doc, &extracted) {
+            headings if !headings.is_empty() => headings,
+            _ => Self::detect_headings(&extracted.text),
+        };
+
+        Ok(extracted)
     }
 
-    /// Get page count from PDF
-    pub fn get_page_count(pdf_bytes: &[u8]) -> Result<i32> {
-        let cursor = Cursor::new(pdf_bytes);
-        let doc = PdfDocument::load_from(cursor)
-            .context("Failed to load PDF document")?;
+    /// Headings from the PDF's bookmarks, placed where their title appears on the target page
+    fn outline_headings(doc: &PdfDocument, extracted: &ExtractedText) -> Vec<Heading> {
+        let Ok(toc) = doc.get_toc() else {
+            return Vec::new();
+        };
+
+        let mut headings: Vec<Heading> = toc
+            .toc
+            .into_iter()
+            .filter_map(|entry| {
+                let title = entry.title.split_whitespace().collect::<Vec<_>>().join(" ");
+                let index = extracted
+                    .pages
+                    .iter()
+                    .position(|p| p.number as usize == entry.page)?;
+                let page = &extracted.pages[index];
+                let page_end = extracted
+                    .pages
+                    .get(index + 1)
+                    .map_or(extracted.text.len(), |p| p.byte_start)
+                    .min(extracted.text.len());
+                let page_text = extracted.text.get(page.byte_start..page_end)?;
+
+                // Fall back to the top of the page if the title isn't found verbatim
+                let char_start = match page_text.find(&title) {
+                    Some(byte) => page.char_start + page_text[..byte].chars().count(),
+                    None => page.char_start,
+                };
+
+                (!title.is_empty()).then_some(Heading {
+                    level: entry.level.max(1),
+                    title,
+                    char_start,
+                })
+            })
+            .collect();
+
+        headings.sort_by_key(|h| h.char_start);
+        headings
+    }
 
-        Ok(doc.get_pages().len() as i32)
+    /// Guess headings from lines that look like them: "Chapter 3", "3.2 Kinematics",
+    /// short ALL CAPS lines. Deliberately conservative, a missed heading only makes
+    /// sections coarser.
+    fn detect_headings(text: &str) -> Vec<Heading> {
+        let mut headings = Vec::new();
+        let mut char_start = 0;
+
+        for line in text.split('\n') {
+            let trimmed = line.trim();
+            if let Some(level) = Self::heading_level(trimmed) {
+                let leading = line.chars().take_while(|c| c.is_whitespace()).count();
+                headings.push(Heading {
+                    level,
+                    title: trimmed.split_whitespace().collect::<Vec<_>>().join(" "),
+                    char_start: char_start + leading,
+                });
+            }
+            char_start += line.chars().count() + 1;
+        }
+
+        headings
     }
 
-    /// Chunk text into smaller pieces with overlap
-    pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
-        let words: Vec<&str> = text.split_whitespace().collect();
-        let mut chunks = Vec::new();
+    fn heading_level(line: &str) -> Option<usize> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() || words.len() > 12 || line.chars().count() > 80 {
+            return None;
+        }
+
+        let first = words[0].to_lowercase();
+        let second_is_number = words.get(1).is_some_and(|w| {
+            let w = w.trim_end_matches(['.', ':']);
+            !w.is_empty()
+                && (w.chars().all(|c| c.is_ascii_digit())
+                    || w.chars().all(|c| "IVXLCivxlc".contains(c)))
+        });
 
-        if words.is_empty() {
-            return chunks;
+        // "Chapter 3", "PART II: Mechanics", "Section 4.1"
+        if second_is_number {
+            match first.as_str() {
+                "part" | "chapter" | "unit" | "appendix" | "lesson" | "module" => return Some(1),
+                "section" => return Some(2),
+                _ => {}
+            }
         }
 
-        let mut i = 0;
-        while i < words.len() {
-            let end = (i + chunk_size).min(words.len());
-            let chunk = words[i..end].join(" ");
-            chunks.push(chunk);
+        // Sentences and table-of-contents lines ("3.2 Kinematics ..... 45") aren't headings
+        if line.ends_with(['.', ',', ';']) || line.ends_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
 
-            // Move forward by chunk_size - overlap
-            if end >= words.len() {
-                break;
+        // "3.2 Kinematics", "3.2.1. Velocity"
+        let number = words[0].trim_end_matches('.');
+        let parts: Vec<&str> = number.split('.').collect();
+        let numbered = !number.is_empty()
+            && parts
+                .iter()
+                .all(|p| !p.is_empty() && p.len() <= 3 && p.chars().all(|c| c.is_ascii_digit()));
+        let title_starts_upper = words
+            .get(1)
+            .and_then(|w| w.chars().next())
+            .is_some_and(char::is_uppercase);
+
+        if numbered && title_starts_upper && words.len() > 1 {
+            // A bare "3 Something" is often a list item, so only trust it in Title Case
+            let title_case = words[1..]
+                .iter()
+                .filter(|w| w.len() > 3)
+                .all(|w| w.chars().next().is_some_and(char::is_uppercase));
+            if parts.len() > 1 || (title_case && words.len() <= 7) {
+                return Some(parts.len());
             }
-            i += chunk_size.saturating_sub(overlap);
         }
 
-        chunks
+        // "NEWTON'S LAWS OF MOTION"
+        let letters: Vec<char> = line.chars().filter(|c| c.is_alphabetic()).collect();
+        if letters.len() >= 4 && words.len() <= 8 && letters.iter().all(|c| c.is_uppercase()) {
+            return Some(1);
+        }
+
+        None
     }
 
     /// Estimate token count (rough approximation: 1 token ≈ 4 chars)
     pub fn estimate_tokens(text: &str) -> i32 {
         (text.len() / 4) as i32
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Bookmark, Object, Stream};
+
+    use super::*;
+
+    /// A PDF with one line of text per string on each page, and bookmarks of
+    /// (title, 0-based page index, parent bookmark)
+    fn pdf(pages: &[&[&str]], bookmarks: &[(&str, usize, Option<u32>)]) -> Vec<u8> {
+        let mut doc = PdfDocument::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+
+        let mut page_ids = Vec::new();
+        for lines in pages {
+            let mut operations = Vec::new();
+            for (i, line) in lines.iter().enumerate() {
+                operations.extend([
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![72.into(), (700 - 14 * i as i64).into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*line)]),
+                    Operation::new("ET", vec![]),
+                ]);
+            }
+            let content = Content { operations }.encode().unwrap();
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+            page_ids.push(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            }));
+        }
+
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => page_ids.iter().map(|id| Object::Reference(*id)).collect::<Vec<_>>(),
+                "Count" => page_ids.len() as i64,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+
+        for (title, page, parent) in bookmarks {
+            let bookmark = Bookmark::new(title.to_string(), [0.0, 0.0, 0.0], 0, page_ids[*page]);
+            doc.add_bookmark(bookmark, *parent);
+        }
+
+        let mut catalog = dictionary! { "Type" => "Catalog", "Pages" => pages_id };
+        if let Some(outline_id) = doc.build_outline() {
+            catalog.set("Outlines", outline_id);
+        }
+        let catalog_id = doc.add_object(catalog);
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn headings(extracted: &ExtractedText) -> Vec<(usize, &str, usize)> {
+        extracted
+            .headings
+            .iter()
+            .map(|h| (h.level, h.title.as_str(), h.char_start))
+            .collect()
+    }
+
+    fn span(number: i32, char_start: usize) -> PageSpan {
+        PageSpan {
+            number,
+            char_start,
+            byte_start: char_start,
+        }
+    }
+
+    #[test]
+    fn page_at_uses_the_page_starting_at_or_before_the_offset() {
+        let extracted = ExtractedText {
+            text: "a".repeat(30),
+            pages: vec![span(1, 0), span(2, 10), span(3, 20)],
+            headings: Vec::new(),
+        };
+
+        assert_eq!(extracted.page_at(0), Some(1));
+        assert_eq!(extracted.page_at(9), Some(1));
+        assert_eq!(extracted.page_at(10), Some(2));
+        assert_eq!(extracted.page_at(19), Some(2));
+        assert_eq!(extracted.page_at(20), Some(3));
+        assert_eq!(extracted.page_at(1_000), Some(3));
+    }
+
+    #[test]
+    fn page_at_without_pages() {
+        let extracted = ExtractedText {
+            text: String::new(),
+            pages: Vec::new(),
+            headings: Vec::new(),
+        };
+
+        assert_eq!(extracted.page_at(0), None);
+    }
+
+    #[test]
+    fn extracts_pages_with_character_offsets() {
+        let bytes = pdf(&[&["First page", "more"], &["Second page"]], &[]);
+        let mut progress = Vec::new();
+
+        let extracted =
+            PdfService::extract_text(&bytes, |done, total| progress.push((done, total))).unwrap();
+
+        assert_eq!(extracted.text, "First page\nmore\nSecond page");
+        let starts: Vec<(i32, usize)> = extracted
+            .pages
+            .iter()
+            .map(|p| (p.number, p.char_start))
+            .collect();
+        assert_eq!(starts, vec![(1, 0), (2, 16)]);
+        assert_eq!(extracted.page_at(15), Some(1));
+        assert_eq!(extracted.page_at(16), Some(2));
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn prefers_outline_headings() {
+        let bytes = pdf(
+            &[
+                &["Preface", "Intro text"],
+                &["Some text", "Kinematics", "Body"],
+            ],
+            &[("Mechanics", 0, None), ("Kinematics", 1, Some(1))],
+        );
+
+        let extracted = PdfService::extract_text(&bytes, |_, _| {}).unwrap();
+
+        // "Mechanics" isn't printed on its page, so it's placed at the top
+        assert_eq!(
+            headings(&extracted),
+            vec![(1, "Mechanics", 0), (2, "Kinematics", 29)]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_detected_headings_without_an_outline() {
+        let bytes = pdf(
+            &[&["CHAPTER 1", "Some text."], &["1.1 Motion", "More text."]],
+            &[],
+        );
+
+        let extracted = PdfService::extract_text(&bytes, |_, _| {}).unwrap();
+
+        assert_eq!(
+            headings(&extracted),
+            vec![(1, "CHAPTER 1", 0), (2, "1.1 Motion", 21)]
+        );
+    }
+
+    #[test]
+    fn detects_headings_at_character_offsets() {
+        let text = "Einführung in die Größen\n  3.2 Über Kräfte\nText.\nÉNERGIE UND ARBEIT";
+
+        let detected = PdfService::detect_headings(text);
+
+        let found: Vec<(usize, &str, usize)> = detected
+            .iter()
+            .map(|h| (h.level, h.title.as_str(), h.char_start))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(2, "3.2 Über Kräfte", 27), (1, "ÉNERGIE UND ARBEIT", 49)]
+        );
+        for heading in &detected {
+            let at: String = text.chars().skip(heading.char_start).collect();
+            assert!(at.starts_with(&heading.title), "{}", heading.title);
+        }
+    }
+
+    #[test]
+    fn heading_levels() {
+        assert_eq!(PdfService::heading_level("Chapter 3"), Some(1));
+        assert_eq!(PdfService::heading_level("PART II: Mechanics"), Some(1));
+        assert_eq!(PdfService::heading_level("Section 4"), Some(2));
+        assert_eq!(PdfService::heading_level("3 Newton's Laws"), Some(1));
+        assert_eq!(PdfService::heading_level("3.2 Kinematics"), Some(2));
+        assert_eq!(PdfService::heading_level("3.2.1. Velocity"), Some(3));
+        assert_eq!(
+            PdfService::heading_level("NEWTON'S LAWS OF MOTION"),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn rejects_lines_that_only_look_like_headings() {
+        // Sentences, table-of-contents entries, list items and long lines
+        assert_eq!(
+            PdfService::heading_level("3.2 Kinematics is the study."),
+            None
+        );
+        assert_eq!(PdfService::heading_level("3.2 Kinematics ..... 45"), None);
+        assert_eq!(
+            PdfService::heading_level("3 apples fall from the tree"),
+            None
+        );
+        assert_eq!(
+            PdfService::heading_level("2 The force acting on the body is large"),
+            None
+        );
+        assert_eq!(PdfService::heading_level("NASA"), Some(1));
+        assert_eq!(PdfService::heading_level("DNA"), None);
+        assert_eq!(PdfService::heading_level(""), None);
+        assert_eq!(PdfService::heading_level(&"WORD ".repeat(9)), None);
+    }
 }
\ No newline at end of file
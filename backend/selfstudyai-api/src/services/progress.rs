@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events buffered per document for slow subscribers before they start lagging
+const CHANNEL_CAPACITY: usize = 64;
+
+/// A step of document processing, sent to clients watching the document
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Downloaded { bytes: usize },
+    PagesExtracted { done: usize, total: usize },
+    ChunksEmbedded { done: usize, total: usize },
+    VectorsStored { count: usize },
+    Completed,
+    Failed { error: String, retrying: bool },
+}
+
+impl ProgressEvent {
+    /// SSE event name, same as the `stage` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgressEvent::Downloaded { .. } => "downloaded",
+            ProgressEvent::PagesExtracted { .. } => "pages_extracted",
+            ProgressEvent::ChunksEmbedded { .. } => "chunks_embedded",
+            ProgressEvent::VectorsStored { .. } => "vectors_stored",
+            ProgressEvent::Completed => "completed",
+            ProgressEvent::Failed { .. } => "failed",
+        }
+    }
+
+    /// No further events follow this one
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            ProgressEvent::Completed
+                | ProgressEvent::Failed {
+                    retrying: false,
+                    ..
+                }
+        )
+    }
+}
+
+struct Channel {
+    sender: broadcast::Sender<ProgressEvent>,
+    last: Option<ProgressEvent>,
+}
+
+/// In-process fan-out of processing progress, keyed by document
+#[derive(Clone, Default)]
+pub struct ProgressHub {
+    channels: Arc<Mutex<HashMap<Uuid, Channel>>>,
+}
+
+impl ProgressHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send an event to everyone watching the document
+    pub fn publish(&self, document_id: Uuid, event: ProgressEvent) {
+        let mut channels = self.channels.lock().unwrap();
+
+        if event.is_final() {
+            // Nothing follows, so later subscribers read the outcome from the document row
+            if let Some(channel) = channels.remove(&document_id) {
+                let _ = channel.sender.send(event);
+            }
+            return;
+        }
+
+        let channel = channels
+            .entry(document_id)
+            .or_insert_with(Self::new_channel);
+        channel.last = Some(event.clone());
+        // No receivers is fine, nobody is watching
+        let _ = channel.sender.send(event);
+    }
+
+    /// Watch a document's progress. Also returns the latest event, so clients
+    /// joining mid-way can show where processing is at.
+    pub fn subscribe(
+        &self,
+        document_id: Uuid,
+    ) -> (Option<ProgressEvent>, broadcast::Receiver<ProgressEvent>) {
+        let mut channels = self.channels.lock().unwrap();
+
+        // Forget channels of documents nobody watches that never started processing
+        channels.retain(|_, c| c.last.is_some() || c.sender.receiver_count() > 0);
+
+        let channel = channels
+            .entry(document_id)
+            .or_insert_with(Self::new_channel);
+        (channel.last.clone(), channel.sender.subscribe())
+    }
+
+    fn new_channel() -> Channel {
+        Channel {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            last: None,
+        }
+    }
+}
\ No newline at end of file
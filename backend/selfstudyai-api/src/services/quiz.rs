@@ -0,0 +1,338 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use uuid::Uuid;
+
+use crate::entities::question::{
+    Answer, Question, QuestionKind, QuizQuestions, CLOZE_BLANK,
+};
+use crate::entities::{document, document_chunk, quiz, quiz_attempt};
+
+const STOPWORDS: &[&str] = &[
+    "about", "above", "after", "again", "against", "among", "because", "before", "being",
+    "below", "between", "could", "doing", "during", "every", "other", "should", "their",
+    "there", "these", "those", "through", "under", "until", "where", "which", "while",
+    "would", "within", "without", "however", "therefore", "thus", "also", "often",
+];
+
+pub struct QuizService;
+
+impl QuizService {
+    /// Build a mix of multiple-choice, cloze and true/false questions from a document's chunks
+    pub async fn generate_questions(
+        db: &DatabaseConnection,
+        document_id: Uuid,
+        num_questions: usize,
+    ) -> Result<Vec<Question>> {
+        let chunks = document_chunk::Entity::find()
+            .filter(document_chunk::Column::DocumentId.eq(document_id))
+            .order_by_asc(document_chunk::Column::ChunkIndex)
+            .all(db)
+            .await?;
+
+        // Collect (sentence, keyword, keyword position) candidates, skipping repeats
+        // from chunk overlap
+        let mut candidates: Vec<(String, String, usize)> = Vec::new();
+        let mut seen_sentences = HashSet::new();
+        let mut seen_keywords = HashSet::new();
+
+        for chunk in &chunks {
+            for sentence in Self::split_sentences(&chunk.content) {
+                let word_count = sentence.split_whitespace().count();
+                if !(8..=40).contains(&word_count)
+                    || sentence.contains(CLOZE_BLANK)
+                    || !seen_sentences.insert(sentence.clone())
+                {
+                    continue;
+                }
+
+                let Some(keyword) = Self::pick_keyword(&sentence) else {
+                    continue;
+                };
+                let Some(position) = Self::find_word(&sentence, &keyword) else {
+                    continue;
+                };
+                if seen_keywords.insert(keyword.to_lowercase()) {
+                    candidates.push((sentence, keyword, position));
+                }
+            }
+        }
+
+        // Need at least one distractor per question
+        if candidates.len() < 2 {
+            return Ok(Vec::new());
+        }
+
+        // Spread the selected questions evenly across the document
+        let count = num_questions.min(candidates.len());
+        let step = candidates.len() as f64 / count as f64;
+
+        let questions = (0..count)
+            .map(|i| {
+                let (sentence, keyword, position) = &candidates[(i as f64 * step) as usize];
+
+                // Distractors are the other keywords closest in length to the answer
+                let mut distractors: Vec<&String> = candidates
+                    .iter()
+                    .map(|(_, k, _)| k)
+                    .filter(|k| !k.eq_ignore_ascii_case(keyword))
+                    .collect();
+                distractors.sort_by_key(|k| k.len().abs_diff(keyword.len()));
+
+                let blanked = Self::replace_at(sentence, keyword, *position, CLOZE_BLANK);
+
+                // Rotate through question types so a quiz exercises more than recognition
+                let (prompt, kind) = match i % 3 {
+                    0 => {
+                        let mut options: Vec<String> = distractors
+                            .iter()
+                            .take(3)
+                            .map(|d| d.to_string())
+                            .chain(std::iter::once(keyword.clone()))
+                            .collect();
+                        options.sort_by_key(|o| o.to_lowercase());
+
+                        let correct_option = options
+                            .iter()
+                            .position(|o| o == keyword)
+                            .unwrap_or_default();
+
+                        (
+                            blanked,
+                            QuestionKind::MultipleChoice {
+                                options,
+                                correct_option,
+                            },
+                        )
+                    }
+                    1 => (
+                        blanked,
+                        QuestionKind::Cloze {
+                            blanks: vec![vec![keyword.clone()]],
+                        },
+                    ),
+                    _ => {
+                        // Every other true/false statement has its key term swapped out
+                        let swap = distractors.first().filter(|_| i % 2 == 1);
+                        let statement = match swap {
+                            Some(d) => Self::replace_at(sentence, keyword, *position, d),
+                            None => sentence.clone(),
+                        };
+
+                        (
+                            format!("True or false: {}", statement),
+                            QuestionKind::TrueFalse {
+                                answer: swap.is_none(),
+                            },
+                        )
+                    }
+                };
+
+                Question {
+                    prompt,
+                    kind,
+                    explanation: Some(sentence.clone()),
+                }
+            })
+            .collect();
+
+        Ok(questions)
+    }
+
+    /// Create a quiz record from generated questions
+    pub async fn create_quiz(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        document: &document::Model,
+        title: Option<String>,
+        questions: Vec<Question>,
+    ) -> Result<quiz::Model> {
+        let questions = QuizQuestions::new(questions);
+
+        let new_quiz = quiz::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            document_id: Set(document.id),
+            user_id: Set(user_id),
+            title: Set(title.unwrap_or_else(|| format!("Quiz: {}", document.title))),
+            total_questions: Set(questions.questions.len() as i32),
+            questions: Set(questions),
+            created_at: Set(Utc::now().naive_utc()),
+        };
+
+        let quiz = new_quiz.insert(db).await?;
+        Ok(quiz)
+    }
+
+    /// Get user's quizzes, optionally limited to one document
+    pub async fn get_user_quizzes(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        document_id: Option<Uuid>,
+    ) -> Result<Vec<quiz::Model>> {
+        let mut query = quiz::Entity::find().filter(quiz::Column::UserId.eq(user_id));
+
+        if let Some(doc_id) = document_id {
+            query = query.filter(quiz::Column::DocumentId.eq(doc_id));
+        }
+
+        let quizzes = query
+            .order_by_desc(quiz::Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        Ok(quizzes)
+    }
+
+    /// Get quiz by ID
+    pub async fn get_quiz_by_id(
+        db: &DatabaseConnection,
+        quiz_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<quiz::Model>> {
+        let quiz = quiz::Entity::find()
+            .filter(quiz::Column::Id.eq(quiz_id))
+            .filter(quiz::Column::UserId.eq(user_id))
+            .one(db)
+            .await?;
+
+        Ok(quiz)
+    }
+
+    /// Delete a quiz (cascades to its attempts)
+    pub async fn delete_quiz(db: &DatabaseConnection, quiz: quiz::Model) -> Result<()> {
+        let quiz: quiz::ActiveModel = quiz.into();
+        quiz.delete(db).await?;
+        Ok(())
+    }
+
+    /// Grade answers against the quiz and persist the attempt
+    pub async fn submit_attempt(
+        db: &DatabaseConnection,
+        quiz: &quiz::Model,
+        user_id: Uuid,
+        answers: Vec<Option<Answer>>,
+    ) -> Result<quiz_attempt::Model> {
+        let graded = quiz.questions.grade(answers);
+
+        let new_attempt = quiz_attempt::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            quiz_id: Set(quiz.id),
+            user_id: Set(user_id),
+            score: Set(graded.score()),
+            total_questions: Set(graded.answers.len() as i32),
+            answers: Set(graded),
+            completed_at: Set(Utc::now().naive_utc()),
+        };
+
+        let attempt = new_attempt.insert(db).await?;
+        Ok(attempt)
+    }
+
+    /// Get user's attempts for a quiz
+    pub async fn get_attempts(
+        db: &DatabaseConnection,
+        quiz_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<quiz_attempt::Model>> {
+        let attempts = quiz_attempt::Entity::find()
+            .filter(quiz_attempt::Column::QuizId.eq(quiz_id))
+            .filter(quiz_attempt::Column::UserId.eq(user_id))
+            .order_by_desc(quiz_attempt::Column::CompletedAt)
+            .all(db)
+            .await?;
+
+        Ok(attempts)
+    }
+
+    /// Split text into sentences on terminal punctuation
+    fn split_sentences(text: &str) -> Vec<String> {
+        let mut sentences = Vec::new();
+        let mut current = String::new();
+
+        for word in text.split_whitespace() {
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+
+            if word.ends_with(['.', '?', '!']) {
+                sentences.push(std::mem::take(&mut current));
+            }
+        }
+
+        sentences
+    }
+
+    /// Byte position of the first whole-word occurrence of `word`, so "energy" isn't
+    /// found inside "synergy"
+    fn find_word(sentence: &str, word: &str) -> Option<usize> {
+        sentence.match_indices(word).map(|(i, _)| i).find(|&i| {
+            let before = sentence[..i].chars().next_back();
+            let after = sentence[i + word.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+    }
+
+    /// `sentence` with the `word` found at byte `position` replaced
+    fn replace_at(sentence: &str, word: &str, position: usize, replacement: &str) -> String {
+        format!(
+            "{}{}{}",
+            &sentence[..position],
+            replacement,
+            &sentence[position + word.len()..]
+        )
+    }
+
+    /// Pick the most specific-looking word of a sentence to blank out
+    fn pick_keyword(sentence: &str) -> Option<String> {
+        sentence
+            .split_whitespace()
+            .skip(1) // first word is capitalised anyway and rarely a key term
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|w| w.len() >= 5 && w.chars().all(|c| c.is_alphabetic()))
+            .filter(|w| !STOPWORDS.contains(&w.to_lowercase().as_str()))
+            .fold(None, |best: Option<&str>, w| match best {
+                Some(b) if b.len() >= w.len() => Some(b),
+                _ => Some(w),
+            })
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_whole_words_only() {
+        let sentence = "Synergy between systems releases energy quickly.";
+
+        let position = QuizService::find_word(sentence, "energy").unwrap();
+
+        assert_eq!(
+            QuizService::replace_at(sentence, "energy", position, CLOZE_BLANK),
+            format!("Synergy between systems releases {} quickly.", CLOZE_BLANK)
+        );
+    }
+
+    #[test]
+    fn finds_nothing_without_a_whole_word_match() {
+        assert_eq!(
+            QuizService::find_word("Synergy is not energy-free", "energ"),
+            None
+        );
+        assert_eq!(QuizService::find_word("Synergy helps", "energy"), None);
+    }
+
+    #[test]
+    fn picks_the_longest_non_stopword() {
+        assert_eq!(
+            QuizService::pick_keyword("Newton described the gravitational force between bodies."),
+            Some("gravitational".to_string())
+        );
+    }
+}
\ No newline at end of file
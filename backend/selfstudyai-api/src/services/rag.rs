@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use uuid::Uuid;
+
+use crate::entities::document;
+use crate::services::llm::{ChatMessage, TokenUsage};
+use crate::services::vector_db::{SearchFilter, SearchResult};
+use crate::AppState;
+
+const SYSTEM_PROMPT: &str = "You are a study assistant. Answer the student's question using only \
+the numbered sources provided. After every sentence, cite the sources that support it using their \
+numbers in square brackets, e.g. [1] or [2][3]. If the sources do not contain the answer, say so \
+plainly instead of guessing.";
+
+/// Reply used when retrieval finds nothing to ground an answer in
+pub const NO_SOURCES_ANSWER: &str = "I couldn't find anything relevant to that in your documents.";
+
+/// A retrieved chunk offered to the model as source `[number]`
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub number: usize,
+    pub chunk_id: String,
+    pub document_id: String,
+    pub document_title: Option<String>,
+    pub page: Option<i32>,
+    pub section_path: Option<String>,
+    pub content: String,
+    pub score: f32,
+}
+
+/// One sentence of the answer with the sources it cited
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnswerSentence {
+    pub text: String,
+    pub sources: Vec<usize>, // source numbers
+}
+
+#[derive(Debug, Clone)]
+pub struct RagAnswer {
+    pub answer: String,
+    pub sentences: Vec<AnswerSentence>,
+    pub sources: Vec<Source>,
+    pub usage: Option<TokenUsage>,
+}
+
+pub struct RagService;
+
+impl RagService {
+    /// Retrieve the user's chunks most relevant to a question as numbered sources
+    pub async fn retrieve(
+        state: &AppState,
+        user_id: Uuid,
+        question: &str,
+        limit: u64,
+        document_ids: &[Uuid],
+    ) -> Result<Vec<Source>> {
+        let query_embedding = state
+            .embeddings_service
+            .generate_embedding(question.to_string())
+            .await?;
+
+        let results = state
+            .vector_db
+            .search(
+                query_embedding,
+                limit,
+                user_id,
+                &SearchFilter {
+                    document_ids: document_ids.to_vec(),
+                    ..Default::default()
+                },
+                false,
+            )
+            .await?;
+
+        Self::to_sources(&state.db, results).await
+    }
+
+    /// Number search results and attach their document titles
+    async fn to_sources(
+        db: &DatabaseConnection,
+        results: Vec<SearchResult>,
+    ) -> Result<Vec<Source>> {
+        let document_ids: Vec<Uuid> = results
+            .iter()
+            .filter_map(|r| Uuid::parse_str(&r.document_id).ok())
+            .collect();
+
+        let titles: HashMap<String, String> = document::Entity::find()
+            .filter(document::Column::Id.is_in(document_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|doc| (doc.id.to_string(), doc.title))
+            .collect();
+
+        let sources = results
+            .into_iter()
+            .enumerate()
+            .map(|(index, r)| Source {
+                number: index + 1,
+                document_title: titles.get(&r.document_id).cloned(),
+                chunk_id: r.chunk_id,
+                document_id: r.document_id,
+                page: r.page_start,
+                section_path: r.section_path,
+                content: r.content,
+                score: r.score,
+            })
+            .collect();
+
+        Ok(sources)
+    }
+
+    /// Build the prompt: instructions, earlier turns, then numbered sources with the question
+    pub fn build_messages(
+        history: &[ChatMessage],
+        question: &str,
+        sources: &[Source],
+    ) -> Vec<ChatMessage> {
+        let context = sources
+            .iter()
+            .map(|s| {
+                let mut header = format!("[{}]", s.number);
+                if let Some(title) = &s.document_title {
+                    header.push_str(&format!(" {}", title));
+                }
+                if let Some(page) = s.page {
+                    header.push_str(&format!(", page {}", page));
+                }
+                if let Some(section_path) = &s.section_path {
+                    header.push_str(&format!(" ({})", section_path));
+                }
+                format!("{}\n{}", header, s.content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut messages = vec![ChatMessage::system(SYSTEM_PROMPT)];
+        messages.extend_from_slice(history);
+        messages.push(ChatMessage::user(format!(
+            "Sources:\n\n{}\n\nQuestion: {}",
+            context, question
+        )));
+        messages
+    }
+
+    /// Answer a question from the caller's documents
+    pub async fn answer(
+        state: &AppState,
+        user_id: Uuid,
+        question: &str,
+        limit: u64,
+        document_ids: &[Uuid],
+    ) -> Result<RagAnswer> {
+        let sources = Self::retrieve(state, user_id, question, limit, document_ids).await?;
+
+        // Nothing to ground an answer in, don't let the model make one up
+        if sources.is_empty() {
+            return Ok(Self::finish(NO_SOURCES_ANSWER.to_string(), sources, None));
+        }
+
+        let completion = state
+            .llm
+            .complete(Self::build_messages(&[], question, &sources))
+            .await?;
+
+        Ok(Self::finish(completion.content, sources, completion.usage))
+    }
+
+    /// Assemble a final answer from the generated text, so streamed and
+    /// non-streamed answers resolve citations the same way
+    pub fn finish(answer: String, sources: Vec<Source>, usage: Option<TokenUsage>) -> RagAnswer {
+        RagAnswer {
+            sentences: Self::parse_citations(&answer, sources.len()),
+            answer,
+            sources,
+            usage,
+        }
+    }
+
+    /// Split an answer into sentences and pull out their `[n]` citation markers.
+    /// Markers that don't refer to an offered source are dropped.
+    pub fn parse_citations(answer: &str, source_count: usize) -> Vec<AnswerSentence> {
+        let mut sentences = Vec::new();
+        let mut text = String::new();
+        let mut cited: Vec<usize> = Vec::new();
+        // Set after terminal punctuation; citations may still follow it: "... force. [2]"
+        let mut sentence_ended = false;
+        let mut chars = answer.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '[' => {
+                    // Collect the bracket contents, e.g. "2" or "1, 3", stopping at anything
+                    // a citation can't contain so a stray "[" doesn't swallow the answer
+                    let mut inner = String::new();
+                    let mut closed = false;
+                    while let Some(&next) = chars.peek() {
+                        if next == ']' {
+                            chars.next();
+                            closed = true;
+                            break;
+                        }
+                        if !(next.is_ascii_digit() || next == ',' || next == ' ') {
+                            break;
+                        }
+                        inner.push(next);
+                        chars.next();
+                    }
+
+                    let numbers: Option<Vec<usize>> = inner
+                        .split(',')
+                        .map(|n| n.trim().parse::<usize>().ok())
+                        .collect();
+
+                    match numbers {
+                        Some(numbers) if closed => {
+                            for n in numbers {
+                                if (1..=source_count).contains(&n) && !cited.contains(&n) {
+                                    cited.push(n);
+                                }
+                            }
+                            // "mass [1]." reads "mass." once the marker is gone
+                            if chars.peek().is_some_and(|n| ".,;:?!".contains(*n)) {
+                                text.truncate(text.trim_end().len());
+                            }
+                        }
+                        _ => {
+                            // Not a citation, keep it as text
+                            text.push('[');
+                            text.push_str(&inner);
+                            if closed {
+                                text.push(']');
+                            }
+                        }
+                    }
+                }
+                '.' | '?' | '!' => {
+                    text.push(c);
+                    // "3.14" or "e.g." mid-word doesn't end a sentence
+                    if chars.peek().is_none_or(|n| n.is_whitespace() || *n == '[') {
+                        sentence_ended = true;
+                    }
+                }
+                '\n' => {
+                    text.push(' ');
+                    sentence_ended = true;
+                }
+                _ => text.push(c),
+            }
+
+            if sentence_ended {
+                while chars.peek().is_some_and(|n| *n == ' ') {
+                    chars.next();
+                }
+                if chars.peek() != Some(&'[') {
+                    Self::push_sentence(&mut sentences, &mut text, &mut cited);
+                    sentence_ended = false;
+                }
+            }
+        }
+
+        Self::push_sentence(&mut sentences, &mut text, &mut cited);
+        sentences
+    }
+
+    fn push_sentence(
+        sentences: &mut Vec<AnswerSentence>,
+        text: &mut String,
+        cited: &mut Vec<usize>,
+    ) {
+        let trimmed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !trimmed.is_empty() {
+            sentences.push(AnswerSentence {
+                text: trimmed,
+                sources: std::mem::take(cited),
+            });
+        } else if let Some(last) = sentences.last_mut() {
+            // Trailing citations with no text of their own belong to the previous sentence
+            for n in cited.drain(..) {
+                if !last.sources.contains(&n) {
+                    last.sources.push(n);
+                }
+            }
+        }
+        text.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentence(text: &str, sources: &[usize]) -> AnswerSentence {
+        AnswerSentence {
+            text: text.to_string(),
+            sources: sources.to_vec(),
+        }
+    }
+
+    #[test]
+    fn parses_citations_per_sentence() {
+        let sentences = RagService::parse_citations(
+            "Force is mass times acceleration [1]. Momentum is conserved [2][3].",
+            3,
+        );
+
+        assert_eq!(
+            sentences,
+            vec![
+                sentence("Force is mass times acceleration.", &[1]),
+                sentence("Momentum is conserved.", &[2, 3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn attaches_citations_after_the_full_stop() {
+        let sentences = RagService::parse_citations("Energy is conserved. [2] It changes form.", 2);
+
+        assert_eq!(
+            sentences,
+            vec![
+                sentence("Energy is conserved.", &[2]),
+                sentence("It changes form.", &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_comma_separated_citations_once() {
+        let sentences = RagService::parse_citations("Both agree [1, 2][2].", 2);
+
+        assert_eq!(sentences, vec![sentence("Both agree.", &[1, 2])]);
+    }
+
+    #[test]
+    fn drops_citations_of_unknown_sources() {
+        let sentences = RagService::parse_citations("See the appendix [0][4].", 3);
+
+        assert_eq!(sentences, vec![sentence("See the appendix.", &[])]);
+    }
+
+    #[test]
+    fn keeps_brackets_that_are_not_citations() {
+        let sentences = RagService::parse_citations("The interval [a, b] is closed [1].", 1);
+
+        assert_eq!(
+            sentences,
+            vec![sentence("The interval [a, b] is closed.", &[1])]
+        );
+    }
+
+    #[test]
+    fn unclosed_bracket_does_not_swallow_the_answer() {
+        let sentences =
+            RagService::parse_citations("Velocity is a vector [1. Speed is not [2].", 2);
+
+        assert_eq!(
+            sentences,
+            vec![
+                sentence("Velocity is a vector [1.", &[]),
+                sentence("Speed is not.", &[2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_split_decimals() {
+        let sentences = RagService::parse_citations("Pi is about 3.14 in every circle [1].", 1);
+
+        assert_eq!(
+            sentences,
+            vec![sentence("Pi is about 3.14 in every circle.", &[1])]
+        );
+    }
+
+    #[test]
+    fn newlines_end_sentences() {
+        let sentences = RagService::parse_citations("First point [1]\nSecond point [2]", 2);
+
+        assert_eq!(
+            sentences,
+            vec![
+                sentence("First point", &[1]),
+                sentence("Second point", &[2])
+            ]
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use uuid::Uuid;
+
+use crate::entities::{document, document_chunk, vector_index};
+use crate::services::embeddings::EmbeddingsService;
+use crate::services::vector_db::{VectorDbService, LEGACY_COLLECTION, LEGACY_MODEL};
+use crate::AppState;
+
+/// Chunks re-embedded and stored per step
+const REINDEX_PAGE_SIZE: u64 = 256;
+
+/// Attempts at a re-index before leaving it failed until the next restart
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Keeps the search alias on a collection built with the configured embedding model,
+/// re-embedding every chunk into a new collection when the model changes
+pub struct ReindexService;
+
+impl ReindexService {
+    /// Point the search alias at this model's collection if it can be used as is, and
+    /// say whether a re-index is needed. Until one finishes, the alias stays on the
+    /// previous collection, which only serves searches if it was built with the same model.
+    pub async fn prepare(
+        db: &DatabaseConnection,
+        vector_db: &VectorDbService,
+        embeddings: &EmbeddingsService,
+    ) -> Result<bool> {
+        let model = embeddings.model();
+        let dimension = embeddings.dimension();
+        let target = vector_db.collection_name();
+
+        let mut active = vector_db.active_collection().await?;
+
+        // Upgrading from a single unversioned collection: keep serving it until replaced
+        if active.is_none() && vector_db.collection_exists(LEGACY_COLLECTION).await? {
+            let (legacy_model, legacy_dimension) = LEGACY_MODEL;
+            Self::record(
+                db,
+                LEGACY_COLLECTION,
+                legacy_model,
+                legacy_dimension,
+                "active",
+            )
+            .await?;
+            vector_db.activate(LEGACY_COLLECTION).await?;
+            active = Some(LEGACY_COLLECTION.to_string());
+        }
+
+        let Some(active) = active else {
+            // Fresh install, nothing to migrate
+            Self::record(db, target, model, dimension, "active").await?;
+            vector_db.activate(target).await?;
+            vector_db.set_ready(true);
+            return Ok(false);
+        };
+
+        if active == target {
+            Self::record(db, target, model, dimension, "active").await?;
+            vector_db.set_ready(true);
+            return Ok(false);
+        }
+
+        let existing = Self::find(db, target).await?;
+        if existing
+            .as_ref()
+            .is_some_and(|index| index.status == "retired")
+        {
+            // Rolling back to a model used before: switch straight back, then catch up on
+            // documents processed while the other model was active
+            tracing::info!("Switching search back to {}", target);
+            Self::switch(db, vector_db, &active).await?;
+            return Ok(true);
+        }
+
+        // Searching the old collection still works if it holds the same model's vectors
+        let same_model = Self::find(db, &active)
+            .await?
+            .is_some_and(|index| index.model == model && index.dimension == dimension as i32);
+        vector_db.set_ready(same_model);
+
+        Self::record(db, target, model, dimension, "building").await?;
+        Ok(true)
+    }
+
+    /// Re-index in the background, retrying a few times before giving up
+    pub fn spawn(state: AppState) {
+        tokio::spawn(async move {
+            for attempt in 1..=MAX_ATTEMPTS {
+                match Self::run(&state).await {
+                    Ok(()) => return,
+                    Err(e) => {
+                        let error = format!("{:#}", e);
+                        tracing::error!("Re-index attempt {} failed: {}", attempt, error);
+                        if let Err(e) = Self::mark_failed(&state, error).await {
+                            tracing::error!("Failed to record re-index failure: {}", e);
+                        }
+                    }
+                }
+                if attempt < MAX_ATTEMPTS {
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        });
+    }
+
+    /// Re-embed every chunk into this model's collection, then switch searches over to it.
+    /// Documents processed meanwhile are embedded with this model and stored here too, so
+    /// the switch makes them searchable even if paging by id passed them by. Until then
+    /// they're only in the old collection if it holds this model's vectors; see
+    /// `VectorDbService::store_chunks`.
+    async fn run(state: &AppState) -> Result<()> {
+        let db = &state.db;
+        let target = state.vector_db.collection_name();
+        let total = document_chunk::Entity::find().count(db).await?;
+
+        // A collection switched back to by a rollback serves searches while it catches up
+        let serving = state.vector_db.active_collection().await?.as_deref() == Some(target);
+        let status = if serving { "active" } else { "building" };
+
+        tracing::info!("Re-indexing {} chunks into {}", total, target);
+        Self::update_progress(db, target, status, total, 0).await?;
+
+        let mut indexed = 0;
+        let mut last_id: Option<Uuid> = None;
+
+        loop {
+            let mut query = document_chunk::Entity::find()
+                .order_by_asc(document_chunk::Column::Id)
+                .limit(REINDEX_PAGE_SIZE);
+            if let Some(last_id) = last_id {
+                query = query.filter(document_chunk::Column::Id.gt(last_id));
+            }
+
+            let chunks = query.all(db).await?;
+            let Some(last) = chunks.last() else {
+                break;
+            };
+            last_id = Some(last.id);
+
+            indexed += chunks.len() as u64;
+            Self::index_chunks(state, chunks).await?;
+
+            Self::update_progress(db, target, status, total.max(indexed), indexed).await?;
+            tracing::info!("Re-indexed {}/{} chunks into {}", indexed, total, target);
+        }
+
+        match state.vector_db.active_collection().await? {
+            Some(active) if active != target => Self::switch(db, &state.vector_db, &active).await?,
+            _ => state.vector_db.set_ready(true),
+        }
+
+        tracing::info!("Re-index into {} completed", target);
+        Ok(())
+    }
+
+    /// Embed and store one page of chunks, grouped by document
+    async fn index_chunks(state: &AppState, chunks: Vec<document_chunk::Model>) -> Result<()> {
+        let document_ids: Vec<Uuid> = chunks.iter().map(|c| c.document_id).collect();
+        let documents: HashMap<Uuid, document::Model> = document::Entity::find()
+            .filter(document::Column::Id.is_in(document_ids))
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .map(|doc| (doc.id, doc))
+            .collect();
+
+        // Unchanged text comes from the embedding cache, so re-runs and rollbacks are cheap
+        let embeddings = state
+            .embeddings_service
+            .generate_embeddings(chunks.iter().map(|c| c.content.clone()).collect())
+            .await?;
+
+        // Skip chunks deleted since the page was read, so they aren't brought back
+        let ids: Vec<Uuid> = chunks.iter().map(|c| c.id).collect();
+        let remaining: Vec<Uuid> = document_chunk::Entity::find()
+            .select_only()
+            .column(document_chunk::Column::Id)
+            .filter(document_chunk::Column::Id.is_in(ids))
+            .into_tuple()
+            .all(&state.db)
+            .await?;
+
+        let mut by_document: HashMap<Uuid, Vec<(document_chunk::Model, Vec<f32>)>> = HashMap::new();
+        for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
+            if remaining.contains(&chunk.id) {
+                by_document
+                    .entry(chunk.document_id)
+                    .or_default()
+                    .push((chunk, embedding));
+            }
+        }
+
+        for (document_id, chunk_data) in by_document {
+            let Some(document) = documents.get(&document_id) else {
+                continue;
+            };
+            state.vector_db.store_chunks(document, chunk_data).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Atomically point searches at this model's collection, keeping the previous
+    /// one as "retired" so switching back to its model is instant
+    async fn switch(
+        db: &DatabaseConnection,
+        vector_db: &VectorDbService,
+        previous: &str,
+    ) -> Result<()> {
+        let target = vector_db.collection_name();
+
+        vector_db.activate(target).await?;
+        vector_db.set_ready(true);
+
+        let now = Utc::now().naive_utc();
+        if let Some(index) = Self::find(db, target).await? {
+            let mut index: vector_index::ActiveModel = index.into();
+            index.status = Set("active".to_string());
+            index.error = Set(None);
+            index.activated_at = Set(Some(now));
+            index.updated_at = Set(now);
+            index.update(db).await?;
+        }
+        if let Some(index) = Self::find(db, previous).await? {
+            let mut index: vector_index::ActiveModel = index.into();
+            index.status = Set("retired".to_string());
+            index.updated_at = Set(now);
+            index.update(db).await?;
+        }
+
+        tracing::info!("Search switched from {} to {}", previous, target);
+        Ok(())
+    }
+
+    /// The record of this model's collection, for reporting progress
+    pub async fn status(
+        db: &DatabaseConnection,
+        vector_db: &VectorDbService,
+    ) -> Result<Option<vector_index::Model>> {
+        Self::find(db, vector_db.collection_name()).await
+    }
+
+    async fn find(
+        db: &DatabaseConnection,
+        collection_name: &str,
+    ) -> Result<Option<vector_index::Model>> {
+        let index = vector_index::Entity::find()
+            .filter(vector_index::Column::CollectionName.eq(collection_name))
+            .one(db)
+            .await?;
+
+        Ok(index)
+    }
+
+    /// Create or update a collection's record with the given status
+    async fn record(
+        db: &DatabaseConnection,
+        collection_name: &str,
+        model: &str,
+        dimension: usize,
+        status: &str,
+    ) -> Result<vector_index::Model> {
+        let now = Utc::now().naive_utc();
+
+        let index = match Self::find(db, collection_name).await? {
+            Some(index) if index.status == status => return Ok(index),
+            Some(index) => {
+                let mut index: vector_index::ActiveModel = index.into();
+                index.status = Set(status.to_string());
+                index.updated_at = Set(now);
+                if status == "active" {
+                    index.activated_at = Set(Some(now));
+                }
+                index.update(db).await?
+            }
+            None => {
+                vector_index::ActiveModel {
+                    id: Set(Uuid::new_v4()),
+                    collection_name: Set(collection_name.to_string()),
+                    model: Set(model.to_string()),
+                    dimension: Set(dimension as i32),
+                    status: Set(status.to_string()),
+                    total_chunks: Set(0),
+                    indexed_chunks: Set(0),
+                    error: Set(None),
+                    activated_at: Set((status == "active").then_some(now)),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                }
+                .insert(db)
+                .await?
+            }
+        };
+
+        Ok(index)
+    }
+
+    async fn update_progress(
+        db: &DatabaseConnection,
+        collection_name: &str,
+        status: &str,
+        total: u64,
+        indexed: u64,
+    ) -> Result<()> {
+        let Some(index) = Self::find(db, collection_name).await? else {
+            anyhow::bail!("No record of collection {}", collection_name);
+        };
+
+        let mut index: vector_index::ActiveModel = index.into();
+        index.status = Set(status.to_string());
+        index.total_chunks = Set(total as i32);
+        index.indexed_chunks = Set(indexed as i32);
+        index.error = Set(None);
+        index.updated_at = Set(Utc::now().naive_utc());
+        index.update(db).await?;
+
+        Ok(())
+    }
+
+    /// Leave the alias where it is and record why the re-index failed
+    async fn mark_failed(state: &AppState, error: String) -> Result<()> {
+        let db = &state.db;
+        let Some(index) = Self::find(db, state.vector_db.collection_name()).await? else {
+            return Ok(());
+        };
+
+        // A collection already serving searches stays active; it only missed a catch-up
+        let failed = index.status != "active";
+        let mut index: vector_index::ActiveModel = index.into();
+        if failed {
+            index.status = Set("failed".to_string());
+        }
+        index.error = Set(Some(error));
+        index.updated_at = Set(Utc::now().naive_utc());
+        index.update(db).await?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,428 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ort::session::Session;
+use ort::value::Tensor;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+
+use crate::services::embeddings::load_onnx_model;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whole re-ranking request; searches wait on it, so past this they go unranked instead
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Scores how relevant each candidate passage is to a query, reading both together
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Name or path of the re-ranking model
+    fn model(&self) -> &str;
+
+    /// Relevance of each document to the query, in input order; higher is more relevant
+    async fn score(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>>;
+
+    /// Whether scoring does anything. Searches skip over-fetching when it doesn't.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+}
+
+/// Build the re-ranker selected by `RERANKER_PROVIDER`
+/// ("none", "cohere_compatible", "tei" or "onnx")
+pub fn from_config(
+    provider: &str,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    model: Option<String>,
+    model_path: Option<String>,
+) -> Result<Arc<dyn Reranker>> {
+    match provider {
+        "none" => Ok(Arc::new(NoopReranker)),
+        "cohere_compatible" => {
+            let base_url =
+                base_url.context("RERANKER_BASE_URL must be set for cohere_compatible")?;
+            let model = model.context("RERANKER_MODEL must be set for cohere_compatible")?;
+            Ok(Arc::new(CohereCompatibleReranker::new(
+                base_url, api_key, model,
+            )?))
+        }
+        "tei" => {
+            let base_url = base_url.context("RERANKER_BASE_URL must be set for tei")?;
+            Ok(Arc::new(TeiReranker::new(base_url, api_key, model)?))
+        }
+        "onnx" => {
+            let model_path = model_path.context("RERANKER_MODEL_PATH must be set for onnx")?;
+            Ok(Arc::new(OnnxReranker::load(&model_path, model)?))
+        }
+        other => anyhow::bail!("Unknown reranker provider: {}", other),
+    }
+}
+
+/// Leaves results in retrieval order
+pub struct NoopReranker;
+
+#[async_trait]
+impl Reranker for NoopReranker {
+    fn model(&self) -> &str {
+        "none"
+    }
+
+    async fn score(&self, _query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        Ok(vec![0.0; documents.len()])
+    }
+
+    fn is_enabled(&self) -> bool {
+        false
+    }
+}
+
+fn http_client() -> Result<Client> {
+    Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("Failed to build reranker HTTP client")
+}
+
+/// Send a JSON request to a re-ranking endpoint, failing on error statuses
+async fn post_json<T: Serialize + ?Sized>(
+    request: reqwest::RequestBuilder,
+    body: &T,
+) -> Result<reqwest::Response> {
+    let response = request
+        .json(body)
+        .send()
+        .await
+        .context("Failed to send request to reranker")?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        anyhow::bail!("Reranker error: {}", error_text);
+    }
+
+    Ok(response)
+}
+
+#[derive(Debug, Serialize)]
+struct CohereRerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereRerankResponse {
+    results: Vec<CohereRerankResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereRerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+/// Any server speaking Cohere's `/rerank` API (Cohere, Jina, Voyage, vLLM, Infinity...)
+pub struct CohereCompatibleReranker {
+    client: Client,
+    base_url: String, // e.g. https://api.cohere.com/v2
+    api_key: Option<String>,
+    model: String, // e.g. rerank-v3.5
+}
+
+impl CohereCompatibleReranker {
+    pub fn new(base_url: String, api_key: Option<String>, model: String) -> Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+        })
+    }
+}
+
+#[async_trait]
+impl Reranker for CohereCompatibleReranker {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn score(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        let url = format!("{}/rerank", self.base_url);
+        let count = documents.len();
+
+        let mut request = self.client.post(&url);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response: CohereRerankResponse = post_json(
+            request,
+            &CohereRerankRequest {
+                model: &self.model,
+                query,
+                documents,
+            },
+        )
+        .await?
+        .json()
+        .await
+        .context("Failed to parse reranker response")?;
+
+        // Results come back sorted by relevance, not input order
+        scores_by_index(
+            count,
+            response
+                .results
+                .into_iter()
+                .map(|r| (r.index, r.relevance_score)),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TeiRerankRequest<'a> {
+    query: &'a str,
+    texts: Vec<String>,
+    truncate: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TeiRerankResult {
+    index: usize,
+    score: f32,
+}
+
+/// Hugging Face text-embeddings-inference serving a cross-encoder
+pub struct TeiReranker {
+    client: Client,
+    base_url: String, // e.g. http://localhost:8081
+    api_key: Option<String>,
+    model: String, // informational; TEI serves a single model
+}
+
+impl TeiReranker {
+    pub fn new(base_url: String, api_key: Option<String>, model: Option<String>) -> Result<Self> {
+        let base_url = base_url.trim_end_matches('/').to_string();
+        Ok(Self {
+            client: http_client()?,
+            model: model.unwrap_or_else(|| base_url.clone()),
+            base_url,
+            api_key,
+        })
+    }
+}
+
+#[async_trait]
+impl Reranker for TeiReranker {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn score(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        let url = format!("{}/rerank", self.base_url);
+        let count = documents.len();
+
+        let mut request = self.client.post(&url);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let results: Vec<TeiRerankResult> = post_json(
+            request,
+            &TeiRerankRequest {
+                query,
+                texts: documents,
+                truncate: true,
+            },
+        )
+        .await?
+        .json()
+        .await
+        .context("Failed to parse reranker response")?;
+
+        scores_by_index(count, results.into_iter().map(|r| (r.index, r.score)))
+    }
+}
+
+/// Put `(index, score)` pairs back in input order, requiring one score per document
+fn scores_by_index(count: usize, results: impl Iterator<Item = (usize, f32)>) -> Result<Vec<f32>> {
+    let mut scores = vec![None; count];
+    for (index, score) in results {
+        if let Some(slot) = scores.get_mut(index) {
+            *slot = Some(score);
+        }
+    }
+
+    scores
+        .into_iter()
+        .collect::<Option<Vec<f32>>>()
+        .ok_or_else(|| anyhow::anyhow!("Reranker didn't score every document"))
+}
+
+/// A cross-encoder (e.g. cross-encoder/ms-marco-MiniLM-L-6-v2) exported to ONNX and run
+/// in-process, from a directory holding `model.onnx` and `tokenizer.json`
+pub struct OnnxReranker {
+    session: Arc<Mutex<Session>>,
+    tokenizer: Arc<Tokenizer>,
+    model: String,
+    token_type_ids: bool, // whether the model takes a token_type_ids input
+}
+
+impl OnnxReranker {
+    /// Load the model from a directory. `model` names it, defaulting to the directory path.
+    pub fn load(model_path: &str, model: Option<String>) -> Result<Self> {
+        let (session, tokenizer) = load_onnx_model(model_path)?;
+        let token_type_ids = session.inputs.iter().any(|i| i.name == "token_type_ids");
+
+        Ok(Self {
+            session: Arc::new(Mutex::new(session)),
+            tokenizer: Arc::new(tokenizer),
+            model: model.unwrap_or_else(|| model_path.to_string()),
+            token_type_ids,
+        })
+    }
+
+    /// Run the model on (query, document) pairs, mapping its relevance logits to 0..1
+    fn run(
+        session: &Mutex<Session>,
+        tokenizer: &Tokenizer,
+        token_type_ids: bool,
+        query: String,
+        documents: Vec<String>,
+    ) -> Result<Vec<f32>> {
+        let pairs: Vec<(String, String)> = documents
+            .into_iter()
+            .map(|document| (query.clone(), document))
+            .collect();
+        let encodings = tokenizer
+            .encode_batch(pairs, true)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize texts: {}", e))?;
+
+        // Padding makes every encoding the same length
+        let batch = encodings.len();
+        let length = encodings.first().map_or(0, |e| e.len());
+        let flatten = |field: fn(&tokenizers::Encoding) -> &[u32]| -> Vec<i64> {
+            encodings
+                .iter()
+                .flat_map(|e| field(e).iter().map(|v| *v as i64))
+                .collect()
+        };
+
+        let mut inputs = ort::inputs! {
+            "input_ids" => Tensor::from_array(([batch, length], flatten(tokenizers::Encoding::get_ids)))?,
+            "attention_mask" => Tensor::from_array(([batch, length], flatten(tokenizers::Encoding::get_attention_mask)))?,
+        };
+        if token_type_ids {
+            inputs.push((
+                "token_type_ids".into(),
+                Tensor::from_array(([batch, length], flatten(tokenizers::Encoding::get_type_ids)))?
+                    .into(),
+            ));
+        }
+
+        let mut session = session.lock().unwrap();
+        let outputs = session.run(inputs).context("Failed to run ONNX model")?;
+
+        // [batch] or [batch, labels] logits; the last label is "relevant"
+        let (shape, values) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .context("Unexpected ONNX model output")?;
+        let labels = match **shape {
+            [b] if b as usize == batch => 1,
+            [b, l] if b as usize == batch && l > 0 => l as usize,
+            _ => anyhow::bail!("Unexpected ONNX model output shape {:?}", shape),
+        };
+
+        Ok((0..batch)
+            .map(|b| sigmoid(values[b * labels + labels - 1]))
+            .collect())
+    }
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[async_trait]
+impl Reranker for OnnxReranker {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn score(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let session = self.session.clone();
+        let tokenizer = self.tokenizer.clone();
+        let token_type_ids = self.token_type_ids;
+        let query = query.to_string();
+
+        // Inference is CPU-bound, keep it off the async runtime
+        tokio::task::spawn_blocking(move || {
+            Self::run(&session, &tokenizer, token_type_ids, query, documents)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_by_index_restores_input_order() {
+        let scores = scores_by_index(3, [(2, 0.1), (0, 0.9), (1, 0.5)].into_iter()).unwrap();
+
+        assert_eq!(scores, vec![0.9, 0.5, 0.1]);
+    }
+
+    #[test]
+    fn scores_by_index_requires_every_document() {
+        assert!(scores_by_index(3, [(0, 0.9), (2, 0.1)].into_iter()).is_err());
+        assert!(scores_by_index(2, [(0, 0.9), (1, 0.5), (5, 1.0)].into_iter()).is_ok());
+        assert!(scores_by_index(0, std::iter::empty()).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn noop_reranker_is_disabled() {
+        let reranker = from_config("none", None, None, None, None).unwrap();
+
+        assert!(!reranker.is_enabled());
+        assert_eq!(
+            reranker
+                .score("query", vec!["a".to_string(), "b".to_string()])
+                .await
+                .unwrap(),
+            vec![0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn builds_http_rerankers_from_config() {
+        let tei = from_config(
+            "tei",
+            Some("http://localhost:8081/".into()),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(tei.unwrap().model(), "http://localhost:8081");
+
+        assert!(from_config(
+            "cohere_compatible",
+            Some("http://x".into()),
+            None,
+            None,
+            None
+        )
+        .is_err());
+        assert!(from_config("tei", None, None, None, None).is_err());
+        assert!(from_config("bogus", None, None, None, None).is_err());
+    }
+}
\ No newline at end of file
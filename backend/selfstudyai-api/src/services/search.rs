@@ -0,0 +1,732 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sea_orm::sea_query::{Alias, Expr};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, FromQueryResult, JoinType,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entities::{document, document_chunk};
+use crate::services::diversity::DiversityService;
+use crate::services::document::FOLDER_SEPARATOR;
+use crate::services::pdf::SECTION_SEPARATOR;
+use crate::services::reranker::Reranker;
+use crate::services::vector_db::{IndexUnavailable, SearchFilter, SearchResult};
+use crate::AppState;
+
+/// Rank constant from the original RRF paper; damps the weight of top ranks
+const RRF_K: f32 = 60.0;
+
+/// Candidates taken from each ranking per requested result before fusing
+const HYBRID_CANDIDATE_FACTOR: u64 = 4;
+
+/// Candidates fetched per requested result when re-ranking, diversifying or collapsing
+/// picks among them, and the most ever fetched, since cross-encoders score each one
+const CANDIDATE_FACTOR: u64 = 4;
+const MAX_CANDIDATES: u64 = 50;
+
+/// Deepest a search can page, since every page re-ranks from the top
+pub const MAX_SEARCH_DEPTH: u64 = 100;
+
+/// How a search matches chunks to the query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Embedding similarity
+    #[default]
+    Vector,
+    /// Postgres full-text search, for exact terms like formula names and acronyms
+    Keyword,
+    /// Both, fused with reciprocal rank fusion
+    Hybrid,
+}
+
+impl SearchMode {
+    /// Whether a score threshold means anything for this mode. Cosine similarity (-1 to 1),
+    /// full-text rank (0 and up) and re-ranker scores each have a scale of their own, but
+    /// fused scores only reflect rank, topping out at 2 / (k + 1), about 0.033.
+    pub fn supports_min_score(self, reranked: bool) -> bool {
+        reranked || self != SearchMode::Hybrid
+    }
+}
+
+/// What to retrieve and how to rank it
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    pub limit: u64,
+    pub rerank: bool, // reorder over-fetched candidates with the configured re-ranker
+    pub mmr_lambda: Option<f32>, // diversify with MMR: 1 = relevance only, 0 = most diverse
+    pub collapse_adjacent: bool, // merge neighbouring chunks of a document into one hit
+    pub context_chunks: u32, // chunks either side to include with each hit
+    pub min_score: Option<f32>, // on the re-ranker score when re-ranked
+    pub offset: u64, // results to skip, for paging
+}
+
+/// Opaque position of the next page, handed back as `next_cursor`
+#[derive(Debug, Serialize, Deserialize)]
+struct SearchCursor {
+    offset: u64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct KeywordMatch {
+    id: Uuid,
+    document_id: Uuid,
+    chunk_index: i32,
+    content: String,
+    page_start: Option<i32>,
+    page_end: Option<i32>,
+    char_start: Option<i32>,
+    char_end: Option<i32>,
+    section_path: Option<String>,
+    score: f32,
+}
+
+pub struct SearchService;
+
+impl SearchService {
+    /// Search the user's chunks, optionally narrowed by `filter`, then re-ranked,
+    /// diversified and collapsed as the options ask. Each page is ranked from the top
+    /// and `offset` results skipped.
+    pub async fn search(
+        state: &AppState,
+        query: &str,
+        options: &SearchOptions,
+        user_id: Uuid,
+        filter: &SearchFilter,
+    ) -> Result<Vec<SearchResult>> {
+        let rerank = options.rerank && state.reranker.is_enabled();
+        let depth = options.offset + options.limit;
+
+        let candidates = if rerank || options.mmr_lambda.is_some() || options.collapse_adjacent {
+            (depth * CANDIDATE_FACTOR).min(MAX_CANDIDATES).max(depth)
+        } else {
+            depth
+        };
+
+        let mut results = Self::retrieve(
+            state,
+            query,
+            options.mode,
+            candidates,
+            user_id,
+            filter,
+            options.mmr_lambda.is_some(),
+        )
+        .await?;
+
+        if rerank {
+            results = Self::rerank(state.reranker.as_ref(), query, results).await;
+        }
+
+        if let Some(min_score) = options.min_score {
+            results.retain(|r| Self::meets_min_score(r, options.mode, min_score));
+        }
+
+        if let Some(lambda) = options.mmr_lambda {
+            results = Self::diversify(state, results, lambda).await;
+        }
+
+        if options.collapse_adjacent {
+            results = DiversityService::collapse_adjacent(results, depth as usize);
+        }
+
+        results.truncate(depth as usize);
+        results.drain(..results.len().min(options.offset as usize));
+
+        if options.context_chunks > 0 {
+            results = Self::expand_context(&state.db, results, options.context_chunks).await?;
+        }
+
+        Ok(results)
+    }
+
+    /// Compare the re-ranker score if there is one, else the mode's own score. Fused scores
+    /// have no scale to compare against, so a hybrid search the re-ranker failed on keeps
+    /// every result.
+    fn meets_min_score(result: &SearchResult, mode: SearchMode, min_score: f32) -> bool {
+        match result.rerank_score {
+            Some(score) => score >= min_score,
+            None => !mode.supports_min_score(false) || result.score >= min_score,
+        }
+    }
+
+    /// Cursor for the page starting at `offset`
+    pub fn encode_cursor(offset: u64) -> String {
+        let cursor = serde_json::to_vec(&SearchCursor { offset }).unwrap_or_default();
+        hex::encode(cursor)
+    }
+
+    /// Offset a cursor points at, if it's one of ours
+    pub fn decode_cursor(cursor: &str) -> Option<u64> {
+        let bytes = hex::decode(cursor).ok()?;
+        let cursor: SearchCursor = serde_json::from_slice(&bytes).ok()?;
+        Some(cursor.offset)
+    }
+
+    /// Merge each hit with the `count` chunks before and after it in its document, so it
+    /// reads as a passage rather than starting mid-argument
+    async fn expand_context(
+        db: &DatabaseConnection,
+        results: Vec<SearchResult>,
+        count: u32,
+    ) -> Result<Vec<SearchResult>> {
+        let count = count as i32;
+
+        // A hit collapsed from neighbours covers a run of consecutive chunks
+        let window = |hit: &SearchResult| {
+            let document_id = Uuid::parse_str(&hit.document_id).ok()?;
+            let first = hit.chunk_index?;
+            let last = first + hit.chunk_ids.len() as i32 - 1;
+            Some((document_id, first - count, last + count))
+        };
+
+        let windows: Vec<_> = results.iter().filter_map(window).collect();
+        if windows.is_empty() {
+            return Ok(results);
+        }
+
+        let mut condition = Condition::any();
+        for (document_id, start, end) in &windows {
+            condition = condition.add(
+                Condition::all()
+                    .add(document_chunk::Column::DocumentId.eq(*document_id))
+                    .add(document_chunk::Column::ChunkIndex.between(*start, *end)),
+            );
+        }
+
+        let mut chunks_by_document: HashMap<String, Vec<document_chunk::Model>> = HashMap::new();
+        for chunk in document_chunk::Entity::find()
+            .filter(condition)
+            .order_by_asc(document_chunk::Column::ChunkIndex)
+            .all(db)
+            .await?
+        {
+            chunks_by_document
+                .entry(chunk.document_id.to_string())
+                .or_default()
+                .push(chunk);
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|hit| {
+                let Some((_, start, end)) = window(&hit) else {
+                    return hit;
+                };
+
+                let neighbours = chunks_by_document
+                    .get(&hit.document_id)
+                    .into_iter()
+                    .flatten()
+                    .filter(|c| (start..=end).contains(&c.chunk_index))
+                    .filter(|c| !hit.chunk_ids.contains(&c.id.to_string()))
+                    .map(Self::chunk_result)
+                    .collect();
+
+                DiversityService::merge(hit, neighbours)
+            })
+            .collect())
+    }
+
+    /// A stored chunk as an unscored result, for merging into a hit
+    fn chunk_result(chunk: &document_chunk::Model) -> SearchResult {
+        SearchResult {
+            chunk_id: chunk.id.to_string(),
+            chunk_ids: vec![chunk.id.to_string()],
+            chunk_index: Some(chunk.chunk_index),
+            document_id: chunk.document_id.to_string(),
+            content: chunk.content.clone(),
+            page_start: chunk.page_start,
+            page_end: chunk.page_end,
+            char_start: chunk.char_start,
+            char_end: chunk.char_end,
+            section_path: chunk.section_path.clone(),
+            score: 0.0,
+            rerank_score: None,
+            embedding: None,
+        }
+    }
+
+    /// Candidates in retrieval order, scored by the chosen mode
+    async fn retrieve(
+        state: &AppState,
+        query: &str,
+        mode: SearchMode,
+        limit: u64,
+        user_id: Uuid,
+        filter: &SearchFilter,
+        with_vectors: bool,
+    ) -> Result<Vec<SearchResult>> {
+        match mode {
+            SearchMode::Vector => {
+                Self::vector_search(state, query, limit, user_id, filter, with_vectors).await
+            }
+            SearchMode::Keyword => {
+                Self::keyword_search(&state.db, query, limit, user_id, filter).await
+            }
+            SearchMode::Hybrid => {
+                let candidates = limit * HYBRID_CANDIDATE_FACTOR;
+                let (vector, keyword) = tokio::join!(
+                    Self::vector_search(state, query, candidates, user_id, filter, with_vectors),
+                    Self::keyword_search(&state.db, query, candidates, user_id, filter),
+                );
+
+                let vector = match vector {
+                    Ok(results) => results,
+                    // Keyword matches still work while the vector index is rebuilt
+                    Err(e) if e.downcast_ref::<IndexUnavailable>().is_some() => {
+                        tracing::warn!("Hybrid search falling back to keywords: {}", e);
+                        Vec::new()
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                Ok(Self::reciprocal_rank_fusion(vec![vector, keyword?], limit))
+            }
+        }
+    }
+
+    /// Reorder candidates by re-ranker score, keeping retrieval order if the re-ranker fails
+    async fn rerank(
+        reranker: &dyn Reranker,
+        query: &str,
+        mut results: Vec<SearchResult>,
+    ) -> Vec<SearchResult> {
+        let documents = results.iter().map(|r| r.content.clone()).collect();
+
+        match reranker.score(query, documents).await {
+            Ok(scores) if scores.len() == results.len() => {
+                for (result, score) in results.iter_mut().zip(scores) {
+                    result.rerank_score = Some(score);
+                }
+                // Stable, so ties keep their retrieval order
+                results.sort_by(|a, b| {
+                    b.rerank_score
+                        .unwrap_or_default()
+                        .total_cmp(&a.rerank_score.unwrap_or_default())
+                });
+            }
+            Ok(scores) => tracing::warn!(
+                "Reranker {} returned {} scores for {} results",
+                reranker.model(),
+                scores.len(),
+                results.len()
+            ),
+            Err(e) => tracing::warn!("Reranker {} failed: {}", reranker.model(), e),
+        }
+
+        results
+    }
+
+    /// Reorder candidates by MMR, first fetching embeddings for keyword matches that came
+    /// without one. Without embeddings, e.g. mid re-index, candidates keep their order.
+    async fn diversify(
+        state: &AppState,
+        mut results: Vec<SearchResult>,
+        lambda: f32,
+    ) -> Vec<SearchResult> {
+        let missing: Vec<String> = results
+            .iter()
+            .filter(|r| r.embedding.is_none())
+            .map(|r| r.chunk_id.clone())
+            .collect();
+
+        if !missing.is_empty() {
+            match state.vector_db.get_vectors(&missing).await {
+                Ok(mut vectors) => {
+                    for result in results.iter_mut().filter(|r| r.embedding.is_none()) {
+                        result.embedding = vectors.remove(&result.chunk_id);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to fetch embeddings for diversification: {}", e),
+            }
+        }
+
+        DiversityService::mmr(results, lambda)
+    }
+
+    async fn vector_search(
+        state: &AppState,
+        query: &str,
+        limit: u64,
+        user_id: Uuid,
+        filter: &SearchFilter,
+        with_vectors: bool,
+    ) -> Result<Vec<SearchResult>> {
+        let query_embedding = state
+            .embeddings_service
+            .generate_embedding(query.to_string())
+            .await?;
+
+        state
+            .vector_db
+            .search(query_embedding, limit, user_id, filter, with_vectors)
+            .await
+    }
+
+    /// Full-text search over chunk content, ranked by cover density
+    pub async fn keyword_search(
+        db: &DatabaseConnection,
+        query: &str,
+        limit: u64,
+        user_id: Uuid,
+        filter: &SearchFilter,
+    ) -> Result<Vec<SearchResult>> {
+        // websearch syntax: quoted phrases, OR, and -excluded terms
+        let tsquery = "websearch_to_tsquery('english', $1)";
+
+        let mut select = document_chunk::Entity::find()
+            .select_only()
+            .columns([
+                document_chunk::Column::Id,
+                document_chunk::Column::DocumentId,
+                document_chunk::Column::ChunkIndex,
+                document_chunk::Column::Content,
+                document_chunk::Column::PageStart,
+                document_chunk::Column::PageEnd,
+                document_chunk::Column::CharStart,
+                document_chunk::Column::CharEnd,
+                document_chunk::Column::SectionPath,
+            ])
+            .column_as(
+                Expr::cust_with_values(
+                    format!(
+                        "ts_rank_cd(\"document_chunk\".\"content_tsv\", {})",
+                        tsquery
+                    ),
+                    [query],
+                ),
+                "score",
+            )
+            .join(
+                JoinType::InnerJoin,
+                document_chunk::Relation::Document.def(),
+            )
+            .filter(document::Column::UserId.eq(user_id))
+            .filter(Expr::cust_with_values(
+                format!("\"document_chunk\".\"content_tsv\" @@ {}", tsquery),
+                [query],
+            ));
+
+        if !filter.document_ids.is_empty() {
+            select = select
+                .filter(document_chunk::Column::DocumentId.is_in(filter.document_ids.clone()));
+        }
+
+        if let Some(section) = &filter.section {
+            // The section itself or any of its subsections, as with the vector search
+            select = select.filter(
+                Condition::any()
+                    .add(document_chunk::Column::SectionPath.eq(section.as_str()))
+                    .add(Expr::cust_with_values(
+                        "starts_with(\"document_chunk\".\"section_path\", $1)",
+                        [format!("{}{}", section, SECTION_SEPARATOR)],
+                    )),
+            );
+        }
+
+        if !filter.tags.is_empty() {
+            // Documents carrying any of the tags
+            select = select.filter(Expr::cust_with_values(
+                "\"document\".\"tags\" && $1",
+                [filter.tags.clone()],
+            ));
+        }
+
+        if !filter.folders.is_empty() {
+            // The folders themselves or any folder beneath them
+            let mut folders = Condition::any();
+            for folder in &filter.folders {
+                folders = folders
+                    .add(document::Column::Folder.eq(folder.as_str()))
+                    .add(Expr::cust_with_values(
+                        "starts_with(\"document\".\"folder\", $1)",
+                        [format!("{}{}", folder, FOLDER_SEPARATOR)],
+                    ));
+            }
+            select = select.filter(folders);
+        }
+
+        if let Some(after) = filter.uploaded_after {
+            select = select.filter(document::Column::CreatedAt.gte(after));
+        }
+        if let Some(before) = filter.uploaded_before {
+            select = select.filter(document::Column::CreatedAt.lte(before));
+        }
+
+        // Chunks overlapping the page range
+        if let Some(page_from) = filter.page_from {
+            select = select.filter(document_chunk::Column::PageEnd.gte(page_from));
+        }
+        if let Some(page_to) = filter.page_to {
+            select = select.filter(document_chunk::Column::PageStart.lte(page_to));
+        }
+
+        let matches = select
+            .order_by_desc(Expr::col(Alias::new("score")))
+            .order_by_asc(document_chunk::Column::Id)
+            .limit(limit)
+            .into_model::<KeywordMatch>()
+            .all(db)
+            .await?;
+
+        Ok(matches
+            .into_iter()
+            .map(|m| SearchResult {
+                chunk_id: m.id.to_string(),
+                chunk_ids: vec![m.id.to_string()],
+                chunk_index: Some(m.chunk_index),
+                document_id: m.document_id.to_string(),
+                content: m.content,
+                page_start: m.page_start,
+                page_end: m.page_end,
+                char_start: m.char_start,
+                char_end: m.char_end,
+                section_path: m.section_path,
+                score: m.score,
+                rerank_score: None,
+                embedding: None,
+            })
+            .collect())
+    }
+
+    /// Merge rankings by summing 1 / (k + rank) for each chunk; the fused sum becomes its score
+    fn reciprocal_rank_fusion(rankings: Vec<Vec<SearchResult>>, limit: u64) -> Vec<SearchResult> {
+        let mut fused: HashMap<String, SearchResult> = HashMap::new();
+
+        for ranking in rankings {
+            for (rank, result) in ranking.into_iter().enumerate() {
+                let contribution = 1.0 / (RRF_K + rank as f32 + 1.0);
+                fused
+                    .entry(result.chunk_id.clone())
+                    .and_modify(|existing| existing.score += contribution)
+                    .or_insert(SearchResult {
+                        score: contribution,
+                        ..result
+                    });
+            }
+        }
+
+        let mut results: Vec<SearchResult> = fused.into_values().collect();
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.chunk_id.cmp(&b.chunk_id))
+        });
+        results.truncate(limit as usize);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(chunk_id: &str, score: f32) -> SearchResult {
+        SearchResult {
+            chunk_id: chunk_id.to_string(),
+            chunk_ids: vec![chunk_id.to_string()],
+            chunk_index: None,
+            document_id: "doc".to_string(),
+            content: format!("content of {}", chunk_id),
+            page_start: None,
+            page_end: None,
+            char_start: None,
+            char_end: None,
+            section_path: None,
+            score,
+            rerank_score: None,
+            embedding: None,
+        }
+    }
+
+    fn ranking(chunk_ids: &[&str]) -> Vec<SearchResult> {
+        chunk_ids.iter().map(|id| result(id, 0.5)).collect()
+    }
+
+    fn ids(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.chunk_id.as_str()).collect()
+    }
+
+    #[test]
+    fn fused_score_sums_reciprocal_ranks() {
+        let fused = SearchService::reciprocal_rank_fusion(
+            vec![ranking(&["a", "b"]), ranking(&["b", "c"])],
+            10,
+        );
+
+        assert_eq!(ids(&fused), vec!["b", "a", "c"]);
+        let expected_b = 1.0 / (RRF_K + 2.0) + 1.0 / (RRF_K + 1.0);
+        assert!((fused[0].score - expected_b).abs() < 1e-6);
+        assert!((fused[1].score - 1.0 / (RRF_K + 1.0)).abs() < 1e-6);
+        assert!((fused[2].score - 1.0 / (RRF_K + 2.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ignores_the_original_scores() {
+        let fused = SearchService::reciprocal_rank_fusion(
+            vec![vec![result("a", 0.01), result("b", 100.0)]],
+            10,
+        );
+
+        assert_eq!(ids(&fused), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn breaks_ties_by_chunk_id() {
+        let fused = SearchService::reciprocal_rank_fusion(
+            vec![ranking(&["z", "y"]), ranking(&["y", "z"])],
+            10,
+        );
+
+        assert_eq!(ids(&fused), vec!["y", "z"]);
+        assert_eq!(fused[0].score, fused[1].score);
+    }
+
+    #[test]
+    fn truncates_to_limit() {
+        let fused = SearchService::reciprocal_rank_fusion(
+            vec![ranking(&["a", "b", "c"]), ranking(&["d"])],
+            2,
+        );
+
+        assert_eq!(ids(&fused), vec!["a", "d"]);
+        assert!(SearchService::reciprocal_rank_fusion(vec![], 5).is_empty());
+    }
+
+    /// Scores documents by their length, or fails
+    struct FakeReranker {
+        fail: bool,
+        drop_last: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl Reranker for FakeReranker {
+        fn model(&self) -> &str {
+            "fake"
+        }
+
+        async fn score(&self, _query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+            if self.fail {
+                anyhow::bail!("reranker timed out");
+            }
+            let mut scores: Vec<f32> = documents.iter().map(|d| d.len() as f32).collect();
+            if self.drop_last {
+                scores.pop();
+            }
+            Ok(scores)
+        }
+    }
+
+    fn with_content(chunk_id: &str, content: &str) -> SearchResult {
+        SearchResult {
+            content: content.to_string(),
+            ..result(chunk_id, 0.5)
+        }
+    }
+
+    fn candidates() -> Vec<SearchResult> {
+        vec![
+            with_content("a", "short"),
+            with_content("b", "the longest passage"),
+            with_content("c", "medium text"),
+            with_content("d", "tiny!"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn rerank_orders_by_reranker_score() {
+        let reranker = FakeReranker {
+            fail: false,
+            drop_last: false,
+        };
+
+        let reranked = SearchService::rerank(&reranker, "query", candidates()).await;
+
+        // "a" and "d" tie, keeping their retrieval order
+        assert_eq!(ids(&reranked), vec!["b", "c", "a", "d"]);
+        assert_eq!(reranked[0].rerank_score, Some(19.0));
+        assert!(reranked.iter().all(|r| r.score == 0.5));
+    }
+
+    #[tokio::test]
+    async fn rerank_keeps_retrieval_order_when_the_reranker_fails() {
+        let reranker = FakeReranker {
+            fail: true,
+            drop_last: false,
+        };
+
+        let reranked = SearchService::rerank(&reranker, "query", candidates()).await;
+
+        assert_eq!(ids(&reranked), vec!["a", "b", "c", "d"]);
+        assert!(reranked.iter().all(|r| r.rerank_score.is_none()));
+    }
+
+    #[tokio::test]
+    async fn rerank_ignores_a_short_score_list() {
+        let reranker = FakeReranker {
+            fail: false,
+            drop_last: true,
+        };
+
+        let reranked = SearchService::rerank(&reranker, "query", candidates()).await;
+
+        assert_eq!(ids(&reranked), vec!["a", "b", "c", "d"]);
+        assert!(reranked.iter().all(|r| r.rerank_score.is_none()));
+    }
+
+    #[test]
+    fn min_score_applies_to_each_modes_own_scale() {
+        let close = result("close", 0.8);
+        let far = result("far", 0.2);
+
+        for mode in [SearchMode::Vector, SearchMode::Keyword] {
+            assert!(SearchService::meets_min_score(&close, mode, 0.5));
+            assert!(!SearchService::meets_min_score(&far, mode, 0.5));
+        }
+    }
+
+    #[test]
+    fn min_score_prefers_the_rerank_score() {
+        let mut reranked = result("a", 0.9);
+        reranked.rerank_score = Some(0.1);
+
+        assert!(!SearchService::meets_min_score(
+            &reranked,
+            SearchMode::Vector,
+            0.5
+        ));
+        assert!(!SearchService::meets_min_score(
+            &reranked,
+            SearchMode::Hybrid,
+            0.5
+        ));
+        reranked.rerank_score = Some(0.7);
+        assert!(SearchService::meets_min_score(
+            &reranked,
+            SearchMode::Hybrid,
+            0.5
+        ));
+    }
+
+    #[test]
+    fn min_score_never_filters_fused_scores() {
+        // The best possible fused score, far below any threshold meant for other modes
+        let top = result("top", 2.0 / (RRF_K + 1.0));
+
+        assert!(SearchService::meets_min_score(
+            &top,
+            SearchMode::Hybrid,
+            0.5
+        ));
+        assert!(!SearchMode::Hybrid.supports_min_score(false));
+        assert!(SearchMode::Hybrid.supports_min_score(true));
+        assert!(SearchMode::Vector.supports_min_score(false));
+        assert!(SearchMode::Keyword.supports_min_score(false));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+use uuid::Uuid;
+
+use crate::entities::search_history;
+
+/// Unpinned searches kept per user; older ones are dropped as new ones are recorded
+const MAX_HISTORY_ENTRIES: u64 = 500;
+
+/// Past searches a user can look back on, re-run, or pin as saved searches
+pub struct SearchHistoryService;
+
+impl SearchHistoryService {
+    /// Record a search with the request that ran it and the chunks it returned
+    pub async fn record(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        query: String,
+        request: serde_json::Value,
+        chunk_ids: &[String],
+    ) -> Result<search_history::Model> {
+        let entry = search_history::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            query: Set(query),
+            request: Set(request),
+            chunk_ids: Set(serde_json::to_value(chunk_ids)?),
+            pinned: Set(false),
+            name: Set(None),
+            created_at: Set(Utc::now().naive_utc()),
+        }
+        .insert(db)
+        .await?;
+
+        Self::prune(db, user_id).await?;
+
+        Ok(entry)
+    }
+
+    /// Drop the user's oldest unpinned searches beyond `MAX_HISTORY_ENTRIES`
+    async fn prune(db: &DatabaseConnection, user_id: Uuid) -> Result<()> {
+        let overflow: Vec<Uuid> = search_history::Entity::find()
+            .select_only()
+            .column(search_history::Column::Id)
+            .filter(search_history::Column::UserId.eq(user_id))
+            .filter(search_history::Column::Pinned.eq(false))
+            .order_by_desc(search_history::Column::CreatedAt)
+            .offset(MAX_HISTORY_ENTRIES)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        if !overflow.is_empty() {
+            search_history::Entity::delete_many()
+                .filter(search_history::Column::Id.is_in(overflow))
+                .exec(db)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get user's searches, newest first, optionally only the pinned ones
+    pub async fn get_user_history(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        pinned_only: bool,
+        limit: u64,
+    ) -> Result<Vec<search_history::Model>> {
+        let mut query =
+            search_history::Entity::find().filter(search_history::Column::UserId.eq(user_id));
+
+        if pinned_only {
+            query = query.filter(search_history::Column::Pinned.eq(true));
+        }
+
+        let entries = query
+            .order_by_desc(search_history::Column::CreatedAt)
+            .limit(limit)
+            .all(db)
+            .await?;
+
+        Ok(entries)
+    }
+
+    /// Get history entry by ID
+    pub async fn get_entry_by_id(
+        db: &DatabaseConnection,
+        entry_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<search_history::Model>> {
+        let entry = search_history::Entity::find()
+            .filter(search_history::Column::Id.eq(entry_id))
+            .filter(search_history::Column::UserId.eq(user_id))
+            .one(db)
+            .await?;
+
+        Ok(entry)
+    }
+
+    /// Pin or unpin an entry and/or rename it. Only the fields given are changed.
+    pub async fn update_entry(
+        db: &DatabaseConnection,
+        entry: search_history::Model,
+        pinned: Option<bool>,
+        name: Option<Option<String>>,
+    ) -> Result<search_history::Model> {
+        let mut entry: search_history::ActiveModel = entry.into();
+        if let Some(pinned) = pinned {
+            entry.pinned = Set(pinned);
+        }
+        if let Some(name) = name {
+            entry.name = Set(name);
+        }
+
+        Ok(entry.update(db).await?)
+    }
+
+    /// Delete a single entry, pinned or not
+    pub async fn delete_entry(db: &DatabaseConnection, entry: search_history::Model) -> Result<()> {
+        let entry: search_history::ActiveModel = entry.into();
+        entry.delete(db).await?;
+        Ok(())
+    }
+
+    /// Delete the user's history, keeping saved searches unless `include_pinned`.
+    /// Returns how many entries were removed.
+    pub async fn clear_history(
+        db: &DatabaseConnection,
+        user_id: Uuid,
+        include_pinned: bool,
+    ) -> Result<u64> {
+        let mut delete = search_history::Entity::delete_many()
+            .filter(search_history::Column::UserId.eq(user_id));
+
+        if !include_pinned {
+            delete = delete.filter(search_history::Column::Pinned.eq(false));
+        }
+
+        Ok(delete.exec(db).await?.rows_affected)
+    }
+
+    /// Parse the chunk ids stored on an entry
+    pub fn chunk_ids(entry: &search_history::Model) -> Vec<String> {
+        serde_json::from_value(entry.chunk_ids.clone()).unwrap_or_default()
+    }
+}
\ No newline at end of file
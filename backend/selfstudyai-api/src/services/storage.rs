@@ -0,0 +1,258 @@
+use std::path::{Component, Path as FsPath, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+
+/// Where uploaded files live, addressed by storage key (e.g. `uploads/{user_id}/{id}.pdf`)
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()>;
+
+    async fn get(&self, key: &str) -> Result<Bytes>;
+
+    /// Deleting a missing key is not an error
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Documents created from a URL (e.g. Vercel Blob) hold that URL instead of a storage key
+pub fn is_remote_url(file_url: &str) -> bool {
+    file_url.starts_with("http://") || file_url.starts_with("https://")
+}
+
+/// Settings for the S3-compatible backend
+pub struct S3Config {
+    pub endpoint: Option<String>, // e.g. http://localhost:9000 for MinIO; AWS when unset
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+/// Build the store selected by `BLOB_STORE` ("local" or "s3")
+pub fn from_config(
+    backend: &str,
+    local_path: String,
+    s3: Option<S3Config>,
+) -> Result<Arc<dyn BlobStore>> {
+    match backend {
+        "local" => Ok(Arc::new(LocalBlobStore::new(local_path))),
+        "s3" => {
+            let config = s3.context(
+                "S3_BUCKET, S3_ACCESS_KEY_ID and S3_SECRET_ACCESS_KEY must be set for s3",
+            )?;
+            Ok(Arc::new(S3BlobStore::new(config)?))
+        }
+        other => anyhow::bail!("Unknown blob store: {}", other),
+    }
+}
+
+/// Files under a directory on the local disk, for single-machine deployments
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Map a key to a path, refusing anything that would escape the root
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = FsPath::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            anyhow::bail!("Invalid storage key: {}", key);
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context("Failed to create storage directory")?;
+        }
+
+        // Write then rename so readers never see a partial file
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, &bytes)
+            .await
+            .context("Failed to write file")?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .context("Failed to write file")?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes> {
+        let bytes = tokio::fs::read(self.path(key)?)
+            .await
+            .with_context(|| format!("Failed to read stored file {}", key))?;
+        Ok(Bytes::from(bytes))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).context("Failed to delete stored file"),
+        }
+    }
+}
+
+/// Any S3-compatible object store: AWS S3, MinIO, Cloudflare R2...
+pub struct S3BlobStore {
+    store: AmazonS3,
+}
+
+impl S3BlobStore {
+    pub fn new(config: S3Config) -> Result<Self> {
+        let mut builder = AmazonS3Builder::new()
+            .with_bucket_name(config.bucket)
+            .with_region(config.region)
+            .with_access_key_id(config.access_key_id)
+            .with_secret_access_key(config.secret_access_key);
+
+        if let Some(endpoint) = config.endpoint {
+            // Self-hosted servers usually use path-style URLs and may not have TLS
+            builder = builder
+                .with_allow_http(endpoint.starts_with("http://"))
+                .with_virtual_hosted_style_request(false)
+                .with_endpoint(endpoint);
+        }
+
+        let store = builder
+            .build()
+            .context("Failed to configure S3 blob store")?;
+        Ok(Self { store })
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+        self.store
+            .put(&ObjectPath::from(key), bytes.into())
+            .await
+            .context("Failed to upload file to S3")?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes> {
+        let bytes = self
+            .store
+            .get(&ObjectPath::from(key))
+            .await
+            .with_context(|| format!("Failed to fetch {} from S3", key))?
+            .bytes()
+            .await
+            .context("Failed to read file from S3")?;
+        Ok(bytes)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match self.store.delete(&ObjectPath::from(key)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e).context("Failed to delete file from S3"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (tempfile::TempDir, LocalBlobStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalBlobStore::new(dir.path().join("blobs"));
+        (dir, store)
+    }
+
+    #[test]
+    fn keys_map_under_the_root() {
+        let store = LocalBlobStore::new("/data/blobs");
+
+        assert_eq!(
+            store.path("uploads/user/doc.pdf").unwrap(),
+            PathBuf::from("/data/blobs/uploads/user/doc.pdf")
+        );
+    }
+
+    #[test]
+    fn rejects_keys_escaping_the_root() {
+        let store = LocalBlobStore::new("/data/blobs");
+
+        for key in [
+            "",
+            "../secret",
+            "uploads/../../etc/passwd",
+            "/etc/passwd",
+            "./uploads/doc.pdf",
+        ] {
+            assert!(store.path(key).is_err(), "{key:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn round_trips_files() {
+        let (_dir, store) = store();
+        let key = "uploads/user/doc.pdf";
+
+        store
+            .put(key, Bytes::from_static(b"%PDF-1.7 first"))
+            .await
+            .unwrap();
+        store
+            .put(key, Bytes::from_static(b"%PDF-1.7 second"))
+            .await
+            .unwrap();
+
+        assert_eq!(store.get(key).await.unwrap(), "%PDF-1.7 second");
+        assert!(!store.path(key).unwrap().with_extension("partial").exists());
+    }
+
+    #[tokio::test]
+    async fn deletes_files_idempotently() {
+        let (_dir, store) = store();
+        let key = "uploads/user/doc.pdf";
+        store.put(key, Bytes::from_static(b"%PDF")).await.unwrap();
+
+        store.delete(key).await.unwrap();
+        store.delete(key).await.unwrap();
+
+        assert!(store.get(key).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_traversal_on_every_operation() {
+        let (dir, store) = store();
+        let outside = dir.path().join("outside.pdf");
+        std::fs::write(&outside, b"%PDF").unwrap();
+
+        assert!(store.put("../outside.pdf", Bytes::new()).await.is_err());
+        assert!(store.get("../outside.pdf").await.is_err());
+        assert!(store.delete("../outside.pdf").await.is_err());
+        assert_eq!(std::fs::read(&outside).unwrap(), b"%PDF");
+    }
+
+    #[test]
+    fn recognizes_remote_urls() {
+        assert!(is_remote_url("https://blob.vercel-storage.com/doc.pdf"));
+        assert!(is_remote_url("http://localhost:9000/doc.pdf"));
+        assert!(!is_remote_url("uploads/user/doc.pdf"));
+        assert!(!is_remote_url("file:///etc/passwd"));
+    }
+}
\ No newline at end of file
@@ -3,60 +3,96 @@ use qdrant_client::qdrant::{
     CreateCollection, DeletePoints, PointStruct, SearchPoints, UpsertPoints, VectorParams,
     VectorsConfig, WithPayloadSelector, value::Kind as QValueKind, Value as QValue, 
     ListValue as QListValue, Struct as QStruct, Filter, Condition, FieldCondition,
+    CreateFieldIndexCollection, FieldType, IsEmptyCondition, ScrollPoints, SetPayloadPoints,
+    CreateAlias, GetPoints, PointId, VectorsOutput, WithVectorsSelector, Range,
 };
 use qdrant_client::Qdrant;
+use chrono::NaiveDateTime;
 use serde_json::Value as JsonValue;
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::entities::{document, document_chunk};
+use crate::services::document::FOLDER_SEPARATOR;
+use crate::services::pdf::SECTION_SEPARATOR;
+
+/// Searches read through this alias, which points at the active collection
+const SEARCH_ALIAS: &str = "documents_current";
+
+/// The single collection used before collections were versioned by model
+pub const LEGACY_COLLECTION: &str = "documents";
+
+/// The model and dimension every vector in the legacy collection was made with
+pub const LEGACY_MODEL: (&str, usize) = ("sentence-transformers/all-MiniLM-L6-v2", 384);
+
+/// Returned by searches while the active collection holds another model's vectors
+#[derive(Debug, thiserror::Error)]
+#[error("Search is unavailable while documents are re-indexed for the new embedding model")]
+pub struct IndexUnavailable;
+
 #[derive(Clone)]
 pub struct VectorDbService {
     client: Qdrant,
-    collection_name: String,
+    collection_name: String, // this model's collection, where new vectors are written
+    dimension: usize,
+    ready: Arc<AtomicBool>, // whether the search alias holds vectors from this model
 }
 
 impl VectorDbService {
-    /// Create a new VectorDbService instance
-    pub async fn new(url: String, api_key: String) -> Result<Self> {
+    /// Create a new VectorDbService instance for vectors from the given model
+    pub async fn new(url: String, api_key: String, model: &str, dimension: usize) -> Result<Self> {
         let client = Qdrant::from_url(&url)
             .api_key(api_key)
             .build()
             .context("Failed to create Qdrant client")?;
 
-        let collection_name = "documents".to_string();
-
         Ok(Self {
             client,
-            collection_name,
+            collection_name: Self::collection_name_for(model, dimension),
+            dimension,
+            ready: Arc::new(AtomicBool::new(false)),
         })
     }
 
-    /// Initialize the collection (create if doesn't exist)
-    pub async fn initialize_collection(&self) -> Result<()> {
-        // Check if collection exists
-        let collections = self
-            .client
-            .list_collections()
-            .await
-            .context("Failed to list collections")?;
+    /// Versioned collection name, e.g. "documents_sentence_transformers_all_minilm_l6_v2_384"
+    pub fn collection_name_for(model: &str, dimension: usize) -> String {
+        let slug: String = model
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{}_{}_{}", LEGACY_COLLECTION, slug.trim_matches('_'), dimension)
+    }
 
-        let collection_exists = collections
-            .collections
-            .iter()
-            .any(|c| c.name == self.collection_name);
+    /// This model's collection
+    pub fn collection_name(&self) -> &str {
+        &self.collection_name
+    }
+
+    /// Whether searches can be served, i.e. the search alias holds this model's vectors
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
 
-        if !collection_exists {
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Release);
+    }
+
+    /// Initialize this model's collection (create if doesn't exist)
+    pub async fn initialize_collection(&self) -> Result<()> {
+        if !self.collection_exists(&self.collection_name).await? {
             tracing::info!("Creating Qdrant collection: {}", self.collection_name);
 
-            // Create collection with 384 dimensions (for all-MiniLM-L6-v2)
             self.client
                 .create_collection(CreateCollection {
                     collection_name: self.collection_name.clone(),
                     vectors_config: Some(VectorsConfig {
                         config: Some(qdrant_client::qdrant::vectors_config::Config::Params(
                             VectorParams {
-                                size: 384,
+                                size: self.dimension as u64,
                                 distance: qdrant_client::qdrant::Distance::Cosine.into(),
                                 ..Default::default()
                             },
@@ -72,9 +108,173 @@ impl VectorDbService {
             tracing::info!("Collection already exists: {}", self.collection_name);
         }
 
+        // Payload indexes for the fields searches filter on (no-op if present)
+        let indexes = [
+            ("user_id", FieldType::Keyword),
+            ("document_id", FieldType::Keyword),
+            ("sections", FieldType::Keyword),
+            ("tags", FieldType::Keyword),
+            ("folders", FieldType::Keyword),
+            ("uploaded_at", FieldType::Integer),
+            ("page_start", FieldType::Integer),
+            ("page_end", FieldType::Integer),
+        ];
+        for (field_name, field_type) in indexes {
+            self.client
+                .create_field_index(CreateFieldIndexCollection {
+                    collection_name: self.collection_name.clone(),
+                    field_name: field_name.to_string(),
+                    field_type: Some(field_type.into()),
+                    wait: Some(true),
+                    ..Default::default()
+                })
+                .await
+                .with_context(|| format!("Failed to create payload index on {}", field_name))?;
+        }
+
         Ok(())
     }
 
+    pub async fn collection_exists(&self, collection_name: &str) -> Result<bool> {
+        self.client
+            .collection_exists(collection_name)
+            .await
+            .context("Failed to check collection")
+    }
+
+    /// The collection the search alias points at, if it exists yet
+    pub async fn active_collection(&self) -> Result<Option<String>> {
+        let aliases = self
+            .client
+            .list_aliases()
+            .await
+            .context("Failed to list aliases")?;
+
+        Ok(aliases
+            .aliases
+            .into_iter()
+            .find(|a| a.alias_name == SEARCH_ALIAS)
+            .map(|a| a.collection_name))
+    }
+
+    /// Point the search alias at a collection. Qdrant re-points an existing alias in
+    /// a single operation, so searches never see it missing.
+    pub async fn activate(&self, collection_name: &str) -> Result<()> {
+        self.client
+            .create_alias(CreateAlias {
+                collection_name: collection_name.to_string(),
+                alias_name: SEARCH_ALIAS.to_string(),
+            })
+            .await
+            .with_context(|| format!("Failed to point search alias at {}", collection_name))?;
+
+        Ok(())
+    }
+
+    /// Every collection this service has written chunks to, current or not
+    async fn all_collections(&self) -> Result<Vec<String>> {
+        let collections = self
+            .client
+            .list_collections()
+            .await
+            .context("Failed to list collections")?;
+
+        Ok(collections
+            .collections
+            .into_iter()
+            .map(|c| c.name)
+            .filter(|name| {
+                name == LEGACY_COLLECTION || name.starts_with(&format!("{}_", LEGACY_COLLECTION))
+            })
+            .collect())
+    }
+
+    /// Helper: condition matching a keyword payload field exactly
+    fn keyword_condition(key: &str, value: String) -> Condition {
+        Condition {
+            condition_one_of: Some(
+                qdrant_client::qdrant::condition::ConditionOneOf::Field(FieldCondition {
+                    key: key.to_string(),
+                    r#match: Some(qdrant_client::qdrant::Match {
+                        match_value: Some(
+                            qdrant_client::qdrant::r#match::MatchValue::Keyword(value),
+                        ),
+                    }),
+                    ..Default::default()
+                }),
+            ),
+        }
+    }
+
+    /// Helper: condition matching any of the given keyword values
+    fn any_keyword_condition(key: &str, values: Vec<String>) -> Condition {
+        Condition {
+            condition_one_of: Some(
+                qdrant_client::qdrant::condition::ConditionOneOf::Field(FieldCondition {
+                    key: key.to_string(),
+                    r#match: Some(qdrant_client::qdrant::Match {
+                        match_value: Some(
+                            qdrant_client::qdrant::r#match::MatchValue::Keywords(
+                                qdrant_client::qdrant::RepeatedStrings { strings: values },
+                            ),
+                        ),
+                    }),
+                    ..Default::default()
+                }),
+            ),
+        }
+    }
+
+    /// Helper: condition matching a numeric payload field within bounds (inclusive)
+    fn range_condition(key: &str, gte: Option<f64>, lte: Option<f64>) -> Condition {
+        Condition {
+            condition_one_of: Some(
+                qdrant_client::qdrant::condition::ConditionOneOf::Field(FieldCondition {
+                    key: key.to_string(),
+                    range: Some(Range {
+                        gte,
+                        lte,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            ),
+        }
+    }
+
+    /// Helper: condition matching points that lack a payload field
+    fn is_empty_condition(key: &str) -> Condition {
+        Condition {
+            condition_one_of: Some(
+                qdrant_client::qdrant::condition::ConditionOneOf::IsEmpty(IsEmptyCondition {
+                    key: key.to_string(),
+                }),
+            ),
+        }
+    }
+
+    /// Helper: extract a payload field as a string
+    fn payload_str(payload: &HashMap<String, QValue>, key: &str) -> String {
+        payload
+            .get(key)
+            .and_then(|v| match &v.kind {
+                Some(QValueKind::StringValue(s)) => Some(s.clone()),
+                Some(QValueKind::IntegerValue(i)) => Some(i.to_string()),
+                Some(QValueKind::DoubleValue(f)) => Some(f.to_string()),
+                Some(QValueKind::BoolValue(b)) => Some(b.to_string()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Helper: extract an optional integer payload field (absent on older points)
+    fn payload_i32(payload: &HashMap<String, QValue>, key: &str) -> Option<i32> {
+        payload.get(key).and_then(|v| match &v.kind {
+            Some(QValueKind::IntegerValue(i)) => i32::try_from(*i).ok(),
+            _ => None,
+        })
+    }
+
     /// Helper: convert serde_json::Value -> qdrant_client::qdrant::Value
     fn json_to_qvalue(j: &JsonValue) -> QValue {
         match j {
@@ -104,7 +304,7 @@ impl VectorDbService {
                 kind: Some(QValueKind::StringValue(s.clone())),
             },
             JsonValue::Array(arr) => {
-                let values = arr.iter().map(|v| Self::json_to_qvalue(v)).collect();
+                let values = arr.iter().map(Self::json_to_qvalue).collect();
                 QValue {
                     kind: Some(QValueKind::ListValue(QListValue { values })),
                 }
@@ -121,35 +321,77 @@ impl VectorDbService {
         }
     }
 
-    /// Store document chunks with embeddings
+    /// Helper: "A > B > C" -> ["A", "A > B", "A > B > C"]
+    fn path_prefixes(path: Option<&str>, separator: &str) -> Vec<String> {
+        let Some(path) = path else {
+            return Vec::new();
+        };
+
+        let parts: Vec<&str> = path.split(separator).collect();
+        (1..=parts.len())
+            .map(|n| parts[..n].join(separator))
+            .collect()
+    }
+
+    /// Helper: the document-level payload fields every one of its points carries
+    fn document_payload(document: &document::Model) -> JsonValue {
+        json!({
+            "tags": document.tags,
+            // The folder and each of its ancestors, so filtering on a folder matches its subfolders
+            "folders": Self::path_prefixes(document.folder.as_deref(), FOLDER_SEPARATOR),
+            "uploaded_at": document.created_at.and_utc().timestamp(),
+        })
+    }
+
+    /// Helper: convert a JSON object into a Qdrant payload
+    fn to_payload(json: &JsonValue) -> HashMap<String, QValue> {
+        match json.as_object() {
+            Some(map) => map
+                .iter()
+                .map(|(k, v)| (k.clone(), Self::json_to_qvalue(v)))
+                .collect(),
+            None => HashMap::new(),
+        }
+    }
+
+    /// Store a document's chunks with embeddings in this model's collection. While a
+    /// re-index is building it, searches still read the previous collection; when that one
+    /// holds the same model's vectors the chunks go there too, so they're searchable now
+    /// rather than after the switch.
     pub async fn store_chunks(
         &self,
-        document_id: Uuid,
-        chunks: Vec<(Uuid, String, Vec<f32>)>, // (chunk_id, content, embedding)
+        document: &document::Model,
+        chunks: Vec<(document_chunk::Model, Vec<f32>)>, // (chunk, embedding)
     ) -> Result<()> {
+        let document_payload = Self::to_payload(&Self::document_payload(document));
+
         // build points vec
         let mut points: Vec<PointStruct> = Vec::with_capacity(chunks.len());
 
-        for (chunk_id, content, embedding) in chunks.into_iter() {
+        for (chunk, embedding) in chunks.into_iter() {
             // build serde payload first
             let payload_json = json!({
-                "document_id": document_id.to_string(),
-                "chunk_id": chunk_id.to_string(),
-                "content": content,
+                "document_id": document.id.to_string(),
+                "user_id": document.user_id.to_string(),
+                "chunk_id": chunk.id.to_string(),
+                "chunk_index": chunk.chunk_index,
+                "content": chunk.content,
+                "page_start": chunk.page_start,
+                "page_end": chunk.page_end,
+                "char_start": chunk.char_start,
+                "char_end": chunk.char_end,
+                "section_path": chunk.section_path,
+                // The path and each of its ancestors, so filtering on a chapter matches its subsections
+                "sections": Self::path_prefixes(chunk.section_path.as_deref(), SECTION_SEPARATOR),
             });
 
             // convert to HashMap<String, QValue>
-            let payload_map: HashMap<String, QValue> = match payload_json.as_object() {
-                Some(map) => map
-                    .iter()
-                    .map(|(k, v)| (k.clone(), Self::json_to_qvalue(v)))
-                    .collect(),
-                None => HashMap::new(),
-            };
+            let mut payload_map = Self::to_payload(&payload_json);
+            payload_map.extend(document_payload.clone());
 
             // Use PointStruct::new - it handles the PointId conversion properly
             let point = PointStruct::new(
-                chunk_id.to_string(),
+                chunk.id.to_string(),
                 embedding,
                 payload_map,
             );
@@ -157,6 +399,24 @@ impl VectorDbService {
             points.push(point);
         }
 
+        let serving = if self.is_ready() {
+            self.active_collection()
+                .await?
+                .filter(|active| *active != self.collection_name)
+        } else {
+            None
+        };
+        if let Some(serving) = serving {
+            self.client
+                .upsert_points(UpsertPoints {
+                    collection_name: serving,
+                    points: points.clone(),
+                    ..Default::default()
+                })
+                .await
+                .context("Failed to upsert points to Qdrant")?;
+        }
+
         // Build UpsertPoints request
         let upsert = UpsertPoints {
             collection_name: self.collection_name.clone(),
@@ -173,47 +433,78 @@ impl VectorDbService {
         Ok(())
     }
 
-    /// Search for similar chunks
+    /// Search the user's chunks for similar ones, optionally narrowed by `filter`, with
+    /// their stored embeddings if `with_vectors` is set
     pub async fn search(
         &self,
         query_embedding: Vec<f32>,
         limit: u64,
-        document_id: Option<Uuid>,
+        user_id: Uuid,
+        filter: &SearchFilter,
+        with_vectors: bool,
     ) -> Result<Vec<SearchResult>> {
-        let mut search_points = SearchPoints {
-            collection_name: self.collection_name.clone(),
+        if !self.is_ready() {
+            return Err(IndexUnavailable.into());
+        }
+
+        // Always scope to the caller's own chunks
+        let mut must = vec![Self::keyword_condition("user_id", user_id.to_string())];
+
+        if !filter.document_ids.is_empty() {
+            must.push(Self::any_keyword_condition(
+                "document_id",
+                filter.document_ids.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+
+        if let Some(section) = &filter.section {
+            must.push(Self::keyword_condition("sections", section.clone()));
+        }
+
+        if !filter.tags.is_empty() {
+            must.push(Self::any_keyword_condition("tags", filter.tags.clone()));
+        }
+
+        if !filter.folders.is_empty() {
+            must.push(Self::any_keyword_condition("folders", filter.folders.clone()));
+        }
+
+        if filter.uploaded_after.is_some() || filter.uploaded_before.is_some() {
+            let timestamp = |t: &NaiveDateTime| t.and_utc().timestamp() as f64;
+            must.push(Self::range_condition(
+                "uploaded_at",
+                filter.uploaded_after.as_ref().map(timestamp),
+                filter.uploaded_before.as_ref().map(timestamp),
+            ));
+        }
+
+        // Chunks overlapping the page range
+        if let Some(page_from) = filter.page_from {
+            must.push(Self::range_condition("page_end", Some(page_from as f64), None));
+        }
+        if let Some(page_to) = filter.page_to {
+            must.push(Self::range_condition("page_start", None, Some(page_to as f64)));
+        }
+
+        let search_points = SearchPoints {
+            collection_name: SEARCH_ALIAS.to_string(),
             vector: query_embedding,
             limit,
+            filter: Some(Filter {
+                must,
+                ..Default::default()
+            }),
             with_payload: Some(WithPayloadSelector {
                 selector_options: Some(qdrant_client::qdrant::with_payload_selector::SelectorOptions::Enable(true)),
             }),
+            with_vectors: Some(WithVectorsSelector {
+                selector_options: Some(
+                    qdrant_client::qdrant::with_vectors_selector::SelectorOptions::Enable(with_vectors),
+                ),
+            }),
             ..Default::default()
         };
 
-        // Filter by document_id if provided
-        if let Some(doc_id) = document_id {
-            search_points.filter = Some(Filter {
-                must: vec![Condition {
-                    condition_one_of: Some(
-                        qdrant_client::qdrant::condition::ConditionOneOf::Field(
-                            FieldCondition {
-                                key: "document_id".to_string(),
-                                r#match: Some(qdrant_client::qdrant::Match {
-                                    match_value: Some(
-                                        qdrant_client::qdrant::r#match::MatchValue::Keyword(
-                                            doc_id.to_string(),
-                                        )
-                                    ),
-                                }),
-                                ..Default::default()
-                            },
-                        ),
-                    ),
-                }],
-                ..Default::default()
-            });
-        }
-
         let search_result = self
             .client
             .search_points(search_points)
@@ -226,25 +517,23 @@ impl VectorDbService {
             .map(|point| {
                 // point.payload is already HashMap<String, Value>
                 let payload_map = point.payload;
-
-                // helper to extract string fields safely
-                let get_str = |m: &HashMap<String, QValue>, key: &str| -> String {
-                    m.get(key)
-                        .and_then(|v| match &v.kind {
-                            Some(QValueKind::StringValue(s)) => Some(s.clone()),
-                            Some(QValueKind::IntegerValue(i)) => Some(i.to_string()),
-                            Some(QValueKind::DoubleValue(f)) => Some(f.to_string()),
-                            Some(QValueKind::BoolValue(b)) => Some(b.to_string()),
-                            _ => None,
-                        })
-                        .unwrap_or_default()
-                };
+                let chunk_id = Self::payload_str(&payload_map, "chunk_id");
 
                 SearchResult {
-                    chunk_id: get_str(&payload_map, "chunk_id"),
-                    document_id: get_str(&payload_map, "document_id"),
-                    content: get_str(&payload_map, "content"),
+                    chunk_ids: vec![chunk_id.clone()],
+                    chunk_id,
+                    chunk_index: Self::payload_i32(&payload_map, "chunk_index"),
+                    document_id: Self::payload_str(&payload_map, "document_id"),
+                    content: Self::payload_str(&payload_map, "content"),
+                    page_start: Self::payload_i32(&payload_map, "page_start"),
+                    page_end: Self::payload_i32(&payload_map, "page_end"),
+                    char_start: Self::payload_i32(&payload_map, "char_start"),
+                    char_end: Self::payload_i32(&payload_map, "char_end"),
+                    section_path: Some(Self::payload_str(&payload_map, "section_path"))
+                        .filter(|s| !s.is_empty()),
                     score: point.score,
+                    rerank_score: None,
+                    embedding: Self::dense_vector(point.vectors),
                 }
             })
             .collect();
@@ -252,45 +541,191 @@ impl VectorDbService {
         Ok(results)
     }
 
-    /// Delete all chunks for a document
+    /// Stored embeddings of the given chunks in the active collection, by chunk id
+    pub async fn get_vectors(&self, chunk_ids: &[String]) -> Result<HashMap<String, Vec<f32>>> {
+        if !self.is_ready() {
+            return Err(IndexUnavailable.into());
+        }
+
+        let response = self
+            .client
+            .get_points(GetPoints {
+                collection_name: SEARCH_ALIAS.to_string(),
+                ids: chunk_ids.iter().map(|id| PointId::from(id.clone())).collect(),
+                with_payload: Some(WithPayloadSelector {
+                    selector_options: Some(
+                        qdrant_client::qdrant::with_payload_selector::SelectorOptions::Enable(false),
+                    ),
+                }),
+                with_vectors: Some(WithVectorsSelector {
+                    selector_options: Some(
+                        qdrant_client::qdrant::with_vectors_selector::SelectorOptions::Enable(true),
+                    ),
+                }),
+                ..Default::default()
+            })
+            .await
+            .context("Failed to get points from Qdrant")?;
+
+        Ok(response
+            .result
+            .into_iter()
+            .filter_map(|point| {
+                let id = match point.id?.point_id_options? {
+                    qdrant_client::qdrant::point_id::PointIdOptions::Uuid(id) => id,
+                    qdrant_client::qdrant::point_id::PointIdOptions::Num(id) => id.to_string(),
+                };
+                Some((id, Self::dense_vector(point.vectors)?))
+            })
+            .collect())
+    }
+
+    /// Helper: the single dense vector of a point, if it was returned
+    fn dense_vector(vectors: Option<VectorsOutput>) -> Option<Vec<f32>> {
+        match vectors?.vectors_options? {
+            qdrant_client::qdrant::vectors_output::VectorsOptions::Vector(vector) => {
+                match vector.into_vector() {
+                    qdrant_client::qdrant::vector_output::Vector::Dense(dense) => Some(dense.data),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Delete all chunks for a document, from retired collections too so a rollback
+    /// doesn't bring them back
     pub async fn delete_document_chunks(&self, document_id: Uuid) -> Result<()> {
-        // Build filter
-        let filter = Filter {
-            must: vec![Condition {
-                condition_one_of: Some(
-                    qdrant_client::qdrant::condition::ConditionOneOf::Field(
-                        FieldCondition {
-                            key: "document_id".to_string(),
-                            r#match: Some(qdrant_client::qdrant::Match {
-                                match_value: Some(
-                                    qdrant_client::qdrant::r#match::MatchValue::Keyword(
-                                        document_id.to_string()
-                                    ),
-                                ),
-                            }),
-                            ..Default::default()
-                        },
+        for collection_name in self.all_collections().await? {
+            // Build filter
+            let filter = Filter {
+                must: vec![Self::keyword_condition("document_id", document_id.to_string())],
+                ..Default::default()
+            };
+
+            // In the newer API, DeletePoints uses points selector instead of filter
+            let delete_req = DeletePoints {
+                collection_name,
+                points: Some(qdrant_client::qdrant::PointsSelector {
+                    points_selector_one_of: Some(
+                        qdrant_client::qdrant::points_selector::PointsSelectorOneOf::Filter(filter)
                     ),
-                ),
-            }],
-            ..Default::default()
-        };
+                }),
+                ..Default::default()
+            };
 
-        // In the newer API, DeletePoints uses points selector instead of filter
-        let delete_req = DeletePoints {
-            collection_name: self.collection_name.clone(),
-            points: Some(qdrant_client::qdrant::PointsSelector {
-                points_selector_one_of: Some(
-                    qdrant_client::qdrant::points_selector::PointsSelectorOneOf::Filter(filter)
-                ),
-            }),
-            ..Default::default()
-        };
+            self.client
+                .delete_points(delete_req)
+                .await
+                .context("Failed to delete points from Qdrant")?;
+        }
 
-        self.client
-            .delete_points(delete_req)
-            .await
-            .context("Failed to delete points from Qdrant")?;
+        Ok(())
+    }
+
+    /// Document ids of points stored before `field` was part of the payload
+    pub async fn documents_missing(&self, field: &str) -> Result<HashSet<String>> {
+        let mut document_ids = HashSet::new();
+        let mut offset = None;
+
+        loop {
+            let page = self
+                .client
+                .scroll(ScrollPoints {
+                    collection_name: SEARCH_ALIAS.to_string(),
+                    filter: Some(Filter {
+                        must: vec![Self::is_empty_condition(field)],
+                        ..Default::default()
+                    }),
+                    offset,
+                    limit: Some(256),
+                    with_payload: Some(WithPayloadSelector {
+                        selector_options: Some(
+                            qdrant_client::qdrant::with_payload_selector::SelectorOptions::Include(
+                                qdrant_client::qdrant::PayloadIncludeSelector {
+                                    fields: vec!["document_id".to_string()],
+                                },
+                            ),
+                        ),
+                    }),
+                    ..Default::default()
+                })
+                .await
+                .context("Failed to scroll Qdrant points")?;
+
+            for point in &page.result {
+                document_ids.insert(Self::payload_str(&point.payload, "document_id"));
+            }
+
+            match page.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(document_ids)
+    }
+
+    /// Copy a document's tags, folder and upload time onto its points, in every
+    /// collection so a rollback to an earlier model sees them too
+    pub async fn set_document_labels(&self, document: &document::Model) -> Result<()> {
+        let payload = Self::to_payload(&Self::document_payload(document));
+
+        for collection_name in self.all_collections().await? {
+            let filter = Filter {
+                must: vec![Self::keyword_condition("document_id", document.id.to_string())],
+                ..Default::default()
+            };
+
+            self.client
+                .set_payload(SetPayloadPoints {
+                    collection_name,
+                    payload: payload.clone(),
+                    points_selector: Some(qdrant_client::qdrant::PointsSelector {
+                        points_selector_one_of: Some(
+                            qdrant_client::qdrant::points_selector::PointsSelectorOneOf::Filter(filter),
+                        ),
+                    }),
+                    wait: Some(true),
+                    ..Default::default()
+                })
+                .await
+                .context("Failed to set payload in Qdrant")?;
+        }
+
+        Ok(())
+    }
+
+    /// Tag a document's untagged points with their owner, in every collection so points
+    /// still in an older collection are covered too
+    pub async fn set_document_owner(&self, document_id: Uuid, user_id: Uuid) -> Result<()> {
+        let payload: HashMap<String, QValue> =
+            HashMap::from([("user_id".to_string(), QValue::from(user_id.to_string()))]);
+
+        for collection_name in self.all_collections().await? {
+            let filter = Filter {
+                must: vec![
+                    Self::keyword_condition("document_id", document_id.to_string()),
+                    Self::is_empty_condition("user_id"),
+                ],
+                ..Default::default()
+            };
+
+            self.client
+                .set_payload(SetPayloadPoints {
+                    collection_name,
+                    payload: payload.clone(),
+                    points_selector: Some(qdrant_client::qdrant::PointsSelector {
+                        points_selector_one_of: Some(
+                            qdrant_client::qdrant::points_selector::PointsSelectorOneOf::Filter(filter),
+                        ),
+                    }),
+                    wait: Some(true),
+                    ..Default::default()
+                })
+                .await
+                .context("Failed to set payload in Qdrant")?;
+        }
 
         Ok(())
     }
@@ -299,7 +734,29 @@ impl VectorDbService {
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub chunk_id: String,
+    pub chunk_ids: Vec<String>, // every chunk making up the hit, in document order
+    pub chunk_index: Option<i32>,
     pub document_id: String,
     pub content: String,
+    pub page_start: Option<i32>,
+    pub page_end: Option<i32>,
+    pub char_start: Option<i32>,
+    pub char_end: Option<i32>,
+    pub section_path: Option<String>,
     pub score: f32,
+    pub rerank_score: Option<f32>, // set when results were re-ranked
+    pub embedding: Option<Vec<f32>>, // only when requested
+}
+
+/// Narrows a search beyond the user's own chunks
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    pub document_ids: Vec<Uuid>, // any of these documents; all when empty
+    pub section: Option<String>, // a section path or any of its ancestors
+    pub tags: Vec<String>,       // documents with any of these tags
+    pub folders: Vec<String>,    // documents in any of these folders or their subfolders
+    pub uploaded_after: Option<NaiveDateTime>, // inclusive bounds on document upload time
+    pub uploaded_before: Option<NaiveDateTime>,
+    pub page_from: Option<i32>, // chunks overlapping this page range
+    pub page_to: Option<i32>,
 }
\ No newline at end of file